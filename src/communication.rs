@@ -1,14 +1,26 @@
 use std::fmt::{Debug, Display};
-use crate::databases::{StoredDom, TrackedPage, User};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::comparators::aggregation::AggregatedResult;
+use crate::databases::{Incident, StoredDom, TrackedPage, User};
 
+pub mod chat;
+pub mod dispatcher;
 pub mod email;
+pub mod outbox;
+pub mod templates;
+#[cfg(test)]
+pub mod test_fixtures;
+pub mod webhook;
 
 pub trait CommunicationMethod<T>: Send + Sync
     where T: Display {
+    ///The name of the channel, used to report the outcome of the deliveries
+    fn name(&self) -> &str;
+
+    ///Whether this method is able to send to the given kind of contact
     fn matches(&self, comm: &CommData) -> bool;
-    fn send_report_to(&self, user: &User, comm_method: &UserCommunication, tracked_pape: &TrackedPage,
-                      stored_dom: &StoredDom<T>, latest_dom: &T)
-                      -> Result<String, String>;
+    fn send_notification_to(&self, user: &User, comm_method: &UserCommunication, notification: &Notification<T>)
+                            -> Result<String, String>;
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -20,7 +32,42 @@ pub struct UserCommunication {
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum CommData {
-    Email(String)
+    Email(String),
+    //The payloads are signed with the secret, when the contact has one
+    Webhook { url: String, secret: Option<String> },
+    //The URL of the incoming webhook
+    Slack(String),
+    Mattermost(String),
+    Matrix { homeserver: String, room_id: String, access_token: String },
+    Telegram { bot_token: String, chat_id: String },
+}
+
+///The kinds of messages that are sent to the users about their tracked pages
+pub enum Notification<'a, T> {
+    //A defacement was detected in the page
+    Defacement(DefacementReport<'a, T>),
+    //The page that was defaced went back to normal
+    Recovery(RecoveryReport<'a>),
+}
+
+///All the information about a detected defacement that is sent to the users
+pub struct DefacementReport<'a, T> {
+    tracked_page: &'a TrackedPage,
+    stored_dom: &'a StoredDom<T>,
+    latest_dom: &'a T,
+    //Screenshot of the page when the defacement was detected, if the page has screenshots enabled
+    latest_screenshot: Option<&'a Vec<u8>>,
+    //The combined verdict with the result of every comparator
+    result: &'a AggregatedResult,
+    //When the defacement was detected (millis since the epoch), the report may be sent later than that
+    detected_time: u128,
+}
+
+///Sent to the contacts that were alerted of a defacement once the page is back to normal
+pub struct RecoveryReport<'a> {
+    tracked_page: &'a TrackedPage,
+    //The resolved incident, with the contacts that were alerted of it
+    incident: &'a Incident,
 }
 
 impl UserCommunication {
@@ -38,4 +85,122 @@ impl UserCommunication {
     pub fn comm_id(&self) -> u32 {
         self.comm_id
     }
-}
\ No newline at end of file
+}
+
+impl<'a, T> DefacementReport<'a, T> {
+    pub fn new(tracked_page: &'a TrackedPage, stored_dom: &'a StoredDom<T>, latest_dom: &'a T,
+               latest_screenshot: Option<&'a Vec<u8>>, result: &'a AggregatedResult) -> Self {
+        let detected_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        Self { tracked_page, stored_dom, latest_dom, latest_screenshot, result, detected_time }
+    }
+
+    pub fn with_detected_time(mut self, detected_time: u128) -> Self {
+        self.detected_time = detected_time;
+        self
+    }
+
+    pub fn tracked_page(&self) -> &TrackedPage {
+        self.tracked_page
+    }
+    pub fn stored_dom(&self) -> &StoredDom<T> {
+        self.stored_dom
+    }
+    pub fn latest_dom(&self) -> &T {
+        self.latest_dom
+    }
+    pub fn latest_screenshot(&self) -> Option<&Vec<u8>> {
+        self.latest_screenshot
+    }
+    pub fn result(&self) -> &AggregatedResult {
+        self.result
+    }
+    pub fn detected_time(&self) -> u128 {
+        self.detected_time
+    }
+}
+
+impl<'a> RecoveryReport<'a> {
+    pub fn new(tracked_page: &'a TrackedPage, incident: &'a Incident) -> Self {
+        Self { tracked_page, incident }
+    }
+
+    pub fn tracked_page(&self) -> &TrackedPage {
+        self.tracked_page
+    }
+    pub fn incident(&self) -> &Incident {
+        self.incident
+    }
+
+    ///How long the page was defaced, from the first defaced check until the check that found it back to normal
+    pub fn breach_duration(&self) -> Duration {
+        let end_time = self.incident.end_time().unwrap_or_else(|| self.incident.start_time());
+
+        Duration::from_millis(end_time.saturating_sub(self.incident.start_time()) as u64)
+    }
+}
+
+///A duration in a format that is easy to read for the users, such as 1h 5m 20s
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    let (hours, minutes, seconds) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+///A time (millis since the epoch) as the UTC date and time, such as 2022-01-31 13:05:20 UTC
+pub fn format_timestamp(time: u128) -> String {
+    let seconds = (time / 1000) as u64;
+
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+    //The civil date of the days since the epoch, counted in eras of 400 years that start in March
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, seconds / 3600, (seconds % 3600) / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod communication_tests {
+    use std::time::Duration;
+    use crate::communication::{format_duration, format_timestamp, RecoveryReport};
+    use crate::databases::{Incident, IncidentResolution, TrackedPage, TrackedPageType};
+
+    #[test]
+    fn test_breach_duration() {
+        let page = TrackedPage::new(1, String::from("https://example.org"), 1, 0, 0, 0, 0, 5, false,
+                                    TrackedPageType::Static);
+
+        let incident = Incident::new(1, 1, 1, 10_000, Some(3_735_000), Vec::new(), String::new(), vec![1],
+                                     Some(IncidentResolution::Recovered));
+
+        let report = RecoveryReport::new(&page, &incident);
+
+        assert_eq!(report.breach_duration(), Duration::from_millis(3_725_000));
+        assert_eq!(format_duration(report.breach_duration()), "1h 2m 5s");
+        assert_eq!(format_duration(Duration::from_secs(65)), "1m 5s");
+        assert_eq!(format_duration(Duration::from_millis(900)), "0s");
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(951_782_400_000), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_timestamp(1_792_241_589_123), "2026-10-17 12:53:09 UTC");
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt::Display;
+use std::time::Duration;
+use serde_json::Value as JsonValue;
+use toml::Value;
+use ureq::{Agent, AgentBuilder, Request};
+
+use crate::communication::Notification;
+use crate::communication::templates::NotificationTemplates;
+use crate::databases::User;
+
+/*
+What the chat platforms (Slack, Mattermost, Matrix and Telegram) have in common.
+Chat messages should be read at a glance, so instead of the whole dom they get the page,
+The verdict, the evidence of the comparators that found the defacement and a short excerpt of the diff.
+The messages are rendered from the plain text chat templates, so they look the same on every platform without escaping.
+ */
+
+pub mod matrix;
+pub mod slack;
+pub mod telegram;
+
+///The HTTP client the chat communicators send their messages with, configured by the chat config file
+pub fn chat_agent(config: &Value) -> Agent {
+    AgentBuilder::new()
+        .user_agent(config["user_agent"].as_str().unwrap())
+        .timeout(Duration::from_secs(config["timeout_secs"].as_integer().unwrap() as u64))
+        .build()
+}
+
+///Send the JSON payload with the request, the platforms answer with an error status when they refuse the message.
+///The URL of the request is left out of the errors, as it can hold the credentials of the contact (the Telegram bot token)
+pub fn send_chat_payload(platform: &str, request: Request, payload: &JsonValue) -> Result<String, String> {
+    match request.set("Content-Type", "application/json").send_string(payload.to_string().as_str()) {
+        Ok(response) => { Ok(format!("Chat message sent with status {}", response.status())) }
+        Err(ureq::Error::Status(status, response)) => {
+            Err(format!("{} refused the message with status {}. {}", platform, status,
+                        response.into_string().unwrap_or_default()))
+        }
+        Err(e) => { Err(format!("Failed to send the message to {}. {}", platform, e.kind())) }
+    }
+}
+
+///The text of the chat message for the notification, rendered from the chat templates of the locale of the user
+pub fn chat_message<T>(templates: &NotificationTemplates, user: &User, notification: &Notification<T>) -> Result<String, String>
+    where T: Display {
+    templates.render_notification(user, "chat", notification)
+}
+
+#[cfg(test)]
+mod chat_tests {
+    use crate::communication::{DefacementReport, Notification};
+    use crate::communication::chat::chat_message;
+    use crate::comparators::{ComparatorResult, CompareResult, Verdict};
+    use crate::comparators::aggregation::AggregatedResult;
+    use crate::communication::test_fixtures::{test_owner, test_page, test_templates};
+    use crate::databases::StoredDom;
+
+    #[test]
+    fn test_defacement_message() {
+        let page = test_page();
+
+        let stored_dom = StoredDom::new(1, 4, String::from("<h1>Shop</h1>\n<p>Welcome</p>"));
+        let current_dom = format!("<h1>Hacked by someone</h1>\n<p>Welcome</p>\n<p>{}</p>", "x".repeat(500));
+
+        let result = AggregatedResult::new(String::from("AnyDefaced"), Verdict::Defaced, 1.0,
+                                           vec![ComparatorResult::new("Signature", CompareResult::defaced(1.0, String::from("Matched Hacked by"))),
+                                                ComparatorResult::new("Tree", CompareResult::maybe_defaced("Below the threshold"))]);
+
+        let templates = test_templates();
+
+        let message = chat_message(&templates, &test_owner(),
+                                   &Notification::Defacement(DefacementReport::new(&page, &stored_dom, &current_dom,
+                                                                                   None, &result))).unwrap();
+
+        assert!(message.contains("https://example.org"));
+        assert!(message.contains("Verdict: Defaced"));
+        assert!(message.contains("- Signature: Matched Hacked by"));
+        assert!(!message.contains("Tree"));
+        assert!(message.contains("+ <h1>Hacked by someone</h1>"));
+        assert!(message.contains("- <h1>Shop</h1>"));
+        //The excerpt is short, not the whole dom
+        assert!(!message.contains(&"x".repeat(200)));
+    }
+}
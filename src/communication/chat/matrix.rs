@@ -0,0 +1,103 @@
+use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::json;
+use toml::Value;
+use ureq::Agent;
+use url::form_urlencoded::byte_serialize;
+
+use crate::communication::{CommData, CommunicationMethod, Notification, UserCommunication};
+use crate::communication::chat::{chat_agent, chat_message, send_chat_payload};
+use crate::communication::templates::NotificationTemplates;
+use crate::databases::User;
+
+/*
+Sends the messages to a Matrix room through the client-server API of the contact's homeserver,
+With the access token of the account the contact registered for the monitor
+ */
+pub struct MatrixCommunicator {
+    agent: Agent,
+    templates: Arc<NotificationTemplates>,
+    //Matrix deduplicates the messages by their transaction ID, so every message needs a new one
+    transaction_count: AtomicU64,
+}
+
+impl MatrixCommunicator {
+    pub fn new(config_file: &str, templates: Arc<NotificationTemplates>) -> Self {
+        let config = config_file.parse::<Value>().unwrap();
+
+        Self { agent: chat_agent(&config), templates, transaction_count: AtomicU64::new(0) }
+    }
+
+    fn next_transaction_id(&self) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        format!("defacement-{}-{}", now, self.transaction_count.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl<T> CommunicationMethod<T> for MatrixCommunicator where T: Display {
+    fn name(&self) -> &str {
+        "Matrix"
+    }
+
+    fn matches(&self, comm: &CommData) -> bool {
+        matches!(comm, CommData::Matrix { .. })
+    }
+
+    fn send_notification_to(&self, user: &User, comm_method: &UserCommunication, notification: &Notification<T>) -> Result<String, String> {
+        match comm_method.communication() {
+            CommData::Matrix { homeserver, room_id, access_token } => {
+                let message = chat_message(&self.templates, user, notification)?;
+
+                let url = format!("{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+                                  homeserver.trim_end_matches('/'), byte_serialize(room_id.as_bytes()).collect::<String>(),
+                                  self.next_transaction_id());
+
+                let request = self.agent.put(url.as_str())
+                    .set("Authorization", format!("Bearer {}", access_token).as_str());
+
+                send_chat_payload("Matrix", request, &json!({ "msgtype": "m.text", "body": message }))
+            }
+            _ => {
+                Err(String::from("There is no Matrix room registered to that communication method."))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod matrix_tests {
+    use crate::communication::{CommData, CommunicationMethod, Notification, RecoveryReport, UserCommunication};
+    use crate::communication::chat::matrix::MatrixCommunicator;
+    use crate::communication::test_fixtures::{recovered_incident, serve_responses, test_owner, test_page, test_templates};
+
+    #[test]
+    fn test_matrix_room_message() {
+        let (address, server) = serve_responses(vec![(200, "{\"event_id\": \"$1\"}")]);
+
+        let (page, incident) = (test_page(), recovered_incident());
+
+        let notification: Notification<String> = Notification::Recovery(RecoveryReport::new(&page, &incident));
+
+        let contact = UserCommunication::new(1, 2, CommData::Matrix {
+            homeserver: format!("{}/", address),
+            room_id: String::from("!room:example.org"),
+            access_token: String::from("token"),
+        });
+
+        MatrixCommunicator::new(include_str!("../../../resources/chat.toml"), test_templates())
+            .send_notification_to(&test_owner(), &contact, &notification).unwrap();
+
+        let received = server.join().unwrap();
+
+        let payload: serde_json::Value = serde_json::from_str(received[0].body.as_str()).unwrap();
+
+        assert_eq!(received[0].method, "PUT");
+        assert!(received[0].url.starts_with("/_matrix/client/v3/rooms/%21room%3Aexample.org/send/m.room.message/defacement-"));
+        assert_eq!(received[0].header("Authorization"), Some("Bearer token"));
+        assert_eq!(payload["msgtype"], "m.text");
+        assert!(payload["body"].as_str().unwrap().contains("incident 9"));
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt::Display;
+use std::sync::Arc;
+use serde_json::json;
+use toml::Value;
+use ureq::Agent;
+
+use crate::communication::{CommData, CommunicationMethod, Notification, UserCommunication};
+use crate::communication::chat::{chat_agent, chat_message, send_chat_payload};
+use crate::communication::templates::NotificationTemplates;
+use crate::databases::User;
+
+/*
+Slack and Mattermost incoming webhooks, Mattermost accepts the same payload as Slack
+So both are sent the same way to the URL of the contact's webhook
+ */
+pub struct SlackCommunicator {
+    agent: Agent,
+    templates: Arc<NotificationTemplates>,
+}
+
+impl SlackCommunicator {
+    pub fn new(config_file: &str, templates: Arc<NotificationTemplates>) -> Self {
+        let config = config_file.parse::<Value>().unwrap();
+
+        Self { agent: chat_agent(&config), templates }
+    }
+}
+
+impl<T> CommunicationMethod<T> for SlackCommunicator where T: Display {
+    fn name(&self) -> &str {
+        "Slack"
+    }
+
+    fn matches(&self, comm: &CommData) -> bool {
+        matches!(comm, CommData::Slack(_) | CommData::Mattermost(_))
+    }
+
+    fn send_notification_to(&self, user: &User, comm_method: &UserCommunication, notification: &Notification<T>) -> Result<String, String> {
+        match comm_method.communication() {
+            CommData::Slack(webhook_url) | CommData::Mattermost(webhook_url) => {
+                let message = chat_message(&self.templates, user, notification)?;
+
+                send_chat_payload("Slack", self.agent.post(webhook_url), &json!({ "text": message }))
+            }
+            _ => {
+                Err(String::from("There is no Slack or Mattermost webhook registered to that communication method."))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod slack_tests {
+    use crate::communication::{CommData, CommunicationMethod, Notification, RecoveryReport, UserCommunication};
+    use crate::communication::chat::slack::SlackCommunicator;
+    use crate::communication::test_fixtures::{recovered_incident, serve_responses, test_owner, test_page, test_templates};
+
+    #[test]
+    fn test_slack_webhook() {
+        let (address, server) = serve_responses(vec![(200, "ok")]);
+
+        let (page, incident) = (test_page(), recovered_incident());
+
+        let notification: Notification<String> = Notification::Recovery(RecoveryReport::new(&page, &incident));
+
+        let contact = UserCommunication::new(1, 2, CommData::Mattermost(format!("{}/hooks/abc", address)));
+
+        SlackCommunicator::new(include_str!("../../../resources/chat.toml"), test_templates())
+            .send_notification_to(&test_owner(), &contact, &notification).unwrap();
+
+        let received = server.join().unwrap();
+
+        let payload: serde_json::Value = serde_json::from_str(received[0].body.as_str()).unwrap();
+
+        assert_eq!(received[0].url, "/hooks/abc");
+        assert!(payload["text"].as_str().unwrap().contains("https://example.org (ID 4) is back to normal"));
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt::Display;
+use std::sync::Arc;
+use serde_json::json;
+use toml::Value;
+use ureq::Agent;
+
+use crate::communication::{CommData, CommunicationMethod, Notification, UserCommunication};
+use crate::communication::chat::{chat_agent, chat_message, send_chat_payload};
+use crate::communication::templates::NotificationTemplates;
+use crate::databases::User;
+
+/*
+Sends the messages to a Telegram chat with the contact's bot, through the Telegram bot API
+ */
+pub struct TelegramCommunicator {
+    agent: Agent,
+    templates: Arc<NotificationTemplates>,
+    //The URL of the bot API, without the trailing slash
+    api_url: String,
+}
+
+impl TelegramCommunicator {
+    pub fn new(config_file: &str, templates: Arc<NotificationTemplates>) -> Self {
+        let config = config_file.parse::<Value>().unwrap();
+
+        let api_url = config["telegram_api_url"].as_str().unwrap().trim_end_matches('/');
+
+        Self { agent: chat_agent(&config), templates, api_url: String::from(api_url) }
+    }
+
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+}
+
+impl<T> CommunicationMethod<T> for TelegramCommunicator where T: Display {
+    fn name(&self) -> &str {
+        "Telegram"
+    }
+
+    fn matches(&self, comm: &CommData) -> bool {
+        matches!(comm, CommData::Telegram { .. })
+    }
+
+    fn send_notification_to(&self, user: &User, comm_method: &UserCommunication, notification: &Notification<T>) -> Result<String, String> {
+        match comm_method.communication() {
+            CommData::Telegram { bot_token, chat_id } => {
+                let url = format!("{}/bot{}/sendMessage", self.api_url, bot_token);
+
+                let message = chat_message(&self.templates, user, notification)?;
+
+                send_chat_payload("Telegram", self.agent.post(url.as_str()), &json!({
+                    "chat_id": chat_id,
+                    "text": message,
+                    "disable_web_page_preview": true,
+                }))
+            }
+            _ => {
+                Err(String::from("There is no Telegram chat registered to that communication method."))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod telegram_tests {
+    use crate::communication::{CommData, CommunicationMethod, Notification, RecoveryReport, UserCommunication};
+    use crate::communication::chat::telegram::TelegramCommunicator;
+    use crate::communication::test_fixtures::{recovered_incident, serve_responses, test_owner, test_page, test_templates};
+
+    #[test]
+    fn test_telegram_bot_message() {
+        let (address, server) = serve_responses(vec![(200, "{\"ok\": true}")]);
+
+        let config = format!("user_agent = \"defacement-monitor-test\"\ntimeout_secs = 5\n\
+        telegram_api_url = \"{}/\"", address);
+
+        let (page, incident) = (test_page(), recovered_incident());
+
+        let notification: Notification<String> = Notification::Recovery(RecoveryReport::new(&page, &incident));
+
+        let contact = UserCommunication::new(1, 2, CommData::Telegram {
+            bot_token: String::from("123:abc"),
+            chat_id: String::from("-100200"),
+        });
+
+        TelegramCommunicator::new(config.as_str(), test_templates())
+            .send_notification_to(&test_owner(), &contact, &notification).unwrap();
+
+        let received = server.join().unwrap();
+
+        let payload: serde_json::Value = serde_json::from_str(received[0].body.as_str()).unwrap();
+
+        assert_eq!(received[0].url, "/bot123:abc/sendMessage");
+        assert_eq!(payload["chat_id"], "-100200");
+        assert!(payload["text"].as_str().unwrap().contains("lasted 1m 0s"));
+    }
+
+    #[test]
+    fn test_error_hides_bot_token() {
+        //Nothing listens on the port, so the message can't be sent
+        let config = "user_agent = \"defacement-monitor-test\"\ntimeout_secs = 5\n\
+        telegram_api_url = \"http://127.0.0.1:1\"";
+
+        let (page, incident) = (test_page(), recovered_incident());
+
+        let notification: Notification<String> = Notification::Recovery(RecoveryReport::new(&page, &incident));
+
+        let contact = UserCommunication::new(1, 2, CommData::Telegram {
+            bot_token: String::from("123:abc"),
+            chat_id: String::from("-100200"),
+        });
+
+        let error = TelegramCommunicator::new(config, test_templates())
+            .send_notification_to(&test_owner(), &contact, &notification).unwrap_err();
+
+        assert!(error.starts_with("Failed to send the message to Telegram"));
+        assert!(!error.contains("123:abc"));
+    }
+}
@@ -0,0 +1,232 @@
+use std::fmt::Display;
+use crate::communication::{CommunicationMethod, Notification, UserCommunication};
+use crate::databases::User;
+
+/*
+Routes the notifications to the contacts of a user. Every contact is sent through every communication
+Method that matches its kind, so a failure in one channel does not stop the others from being tried,
+And the outcome of every delivery is reported so partial failures can be told apart from complete ones.
+The outbox stores a notification for every route and delivers each of them on its own, see outbox.rs
+ */
+pub struct NotificationDispatcher<T> where T: Display {
+    communications: Vec<Box<dyn CommunicationMethod<T>>>,
+}
+
+///The outcome of sending a notification to a contact through a single channel
+#[derive(PartialEq, Debug, Clone)]
+pub struct DeliveryOutcome {
+    contact_id: u32,
+    channel: String,
+    //The answer of the channel when it was delivered, the error when it was not
+    result: Result<String, String>,
+}
+
+///The outcome of every delivery of a notification
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct DispatchReport {
+    outcomes: Vec<DeliveryOutcome>,
+    //The contacts that no communication method is able to send to
+    unrouted_contacts: Vec<u32>,
+}
+
+impl<T> NotificationDispatcher<T> where T: Display {
+    pub fn new(communications: Vec<Box<dyn CommunicationMethod<T>>>) -> Self {
+        Self { communications }
+    }
+
+    ///Send the notification to every contact, through every communication method that matches it
+    pub fn dispatch(&self, user: &User, contacts: &[UserCommunication], notification: &Notification<T>) -> DispatchReport {
+        let mut report = DispatchReport::default();
+
+        for contact in contacts {
+            let channels = self.routes(contact);
+
+            if channels.is_empty() {
+                report.unrouted_contacts.push(contact.comm_id());
+            }
+
+            for channel in channels {
+                report.outcomes.push(self.deliver(channel, user, contact, notification));
+            }
+        }
+
+        report
+    }
+
+    ///The names of the communication methods that are able to send to the contact
+    pub fn routes(&self, contact: &UserCommunication) -> Vec<&str> {
+        self.communications.iter()
+            .filter(|comm_method| comm_method.matches(contact.communication()))
+            .map(|comm_method| comm_method.name())
+            .collect()
+    }
+
+    ///Send the notification to the contact through the communication method with the given name
+    pub fn deliver(&self, channel: &str, user: &User, contact: &UserCommunication, notification: &Notification<T>) -> DeliveryOutcome {
+        let result = match self.communications.iter().find(|comm_method| comm_method.name() == channel) {
+            Some(comm_method) if comm_method.matches(contact.communication()) => {
+                comm_method.send_notification_to(user, contact, notification)
+            }
+            Some(_) => { Err(format!("The {} channel is not able to send to contact {}", channel, contact.comm_id())) }
+            None => { Err(format!("There is no {} channel", channel)) }
+        };
+
+        DeliveryOutcome::new(contact.comm_id(), channel, result)
+    }
+
+    pub fn communications(&self) -> &Vec<Box<dyn CommunicationMethod<T>>> {
+        &self.communications
+    }
+}
+
+impl DeliveryOutcome {
+    pub fn new(contact_id: u32, channel: &str, result: Result<String, String>) -> Self {
+        Self { contact_id, channel: String::from(channel), result }
+    }
+
+    pub fn contact_id(&self) -> u32 {
+        self.contact_id
+    }
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+    pub fn result(&self) -> &Result<String, String> {
+        &self.result
+    }
+    pub fn is_delivered(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+impl DispatchReport {
+    pub fn outcomes(&self) -> &Vec<DeliveryOutcome> {
+        &self.outcomes
+    }
+    pub fn unrouted_contacts(&self) -> &Vec<u32> {
+        &self.unrouted_contacts
+    }
+
+    ///The contacts that received the notification through at least one channel
+    pub fn delivered_contacts(&self) -> Vec<u32> {
+        let mut delivered: Vec<u32> = self.outcomes.iter()
+            .filter(|outcome| outcome.is_delivered())
+            .map(DeliveryOutcome::contact_id)
+            .collect();
+
+        delivered.dedup();
+
+        delivered
+    }
+
+    pub fn failures(&self) -> Vec<&DeliveryOutcome> {
+        self.outcomes.iter().filter(|outcome| !outcome.is_delivered()).collect()
+    }
+
+    ///Nobody received the notification
+    pub fn is_complete_failure(&self) -> bool {
+        self.delivered_contacts().is_empty()
+    }
+
+    ///Some channels (or contacts) received the notification but not all of them
+    pub fn is_partial_failure(&self) -> bool {
+        !self.is_complete_failure() && (!self.failures().is_empty() || !self.unrouted_contacts.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod dispatcher_tests {
+    use crate::communication::{CommData, CommunicationMethod, Notification, RecoveryReport, UserCommunication};
+    use crate::communication::dispatcher::{DispatchReport, NotificationDispatcher};
+    use crate::communication::test_fixtures::{recovered_incident, test_owner, test_page};
+    use crate::databases::User;
+
+    struct MockCommunicator {
+        name: &'static str,
+        email: bool,
+        fail: bool,
+    }
+
+    impl MockCommunicator {
+        fn new(name: &'static str, email: bool, fail: bool) -> Self {
+            Self { name, email, fail }
+        }
+    }
+
+    impl CommunicationMethod<String> for MockCommunicator {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn matches(&self, comm: &CommData) -> bool {
+            matches!(comm, CommData::Email(_)) == self.email
+        }
+
+        fn send_notification_to(&self, _user: &User, _comm_method: &UserCommunication, _notification: &Notification<String>) -> Result<String, String> {
+            if self.fail { Err(String::from("Channel down")) } else { Ok(String::from("Sent")) }
+        }
+    }
+
+    fn dispatch(communications: Vec<Box<dyn CommunicationMethod<String>>>, contacts: &[UserCommunication]) -> DispatchReport {
+        let (page, incident) = (test_page(), recovered_incident());
+
+        let notification = Notification::Recovery(RecoveryReport::new(&page, &incident));
+
+        NotificationDispatcher::new(communications).dispatch(&test_owner(), contacts, &notification)
+    }
+
+    #[test]
+    fn test_routes_to_every_matching_channel() {
+        let contacts = vec![UserCommunication::new(1, 2, CommData::Email(String::from("a@b.c"))),
+                            UserCommunication::new(2, 2, CommData::Slack(String::from("https://hooks.slack.com/x")))];
+
+        let report = dispatch(vec![Box::new(MockCommunicator::new("Failing", true, true)),
+                                   Box::new(MockCommunicator::new("Email", true, false)),
+                                   Box::new(MockCommunicator::new("Chat", false, false))], &contacts);
+
+        //Every contact went through every channel that matches it, even after a failure
+        assert_eq!(report.outcomes().len(), 3);
+        assert_eq!(report.delivered_contacts(), vec![1, 2]);
+        assert_eq!(report.failures().len(), 1);
+        assert_eq!(report.failures()[0].channel(), "Failing");
+        assert!(report.is_partial_failure());
+        assert!(!report.is_complete_failure());
+    }
+
+    #[test]
+    fn test_reports_failures() {
+        let contacts = vec![UserCommunication::new(1, 2, CommData::Email(String::from("a@b.c"))),
+                            UserCommunication::new(2, 2, CommData::Slack(String::from("https://hooks.slack.com/x")))];
+
+        let report = dispatch(vec![Box::new(MockCommunicator::new("Email", true, true))], &contacts);
+
+        assert_eq!(report.unrouted_contacts(), &vec![2]);
+        assert!(report.delivered_contacts().is_empty());
+        assert!(report.is_complete_failure());
+        assert!(!report.is_partial_failure());
+
+        let report = dispatch(vec![Box::new(MockCommunicator::new("Email", true, false))], &contacts[..1]);
+
+        assert!(!report.is_partial_failure() && !report.is_complete_failure());
+    }
+
+    #[test]
+    fn test_deliver_by_channel() {
+        let dispatcher: NotificationDispatcher<String> = NotificationDispatcher::new(vec![Box::new(MockCommunicator::new("Email", true, false)),
+                                                                                          Box::new(MockCommunicator::new("Chat", false, false))]);
+
+        let contact = UserCommunication::new(1, 2, CommData::Email(String::from("a@b.c")));
+
+        assert_eq!(dispatcher.routes(&contact), vec!["Email"]);
+
+        let (page, incident) = (test_page(), recovered_incident());
+
+        let notification = Notification::Recovery(RecoveryReport::new(&page, &incident));
+
+        let user = test_owner();
+
+        assert!(dispatcher.deliver("Email", &user, &contact, &notification).is_delivered());
+        //The channel must still exist and still be able to send to the contact
+        assert!(!dispatcher.deliver("Chat", &user, &contact, &notification).is_delivered());
+        assert!(!dispatcher.deliver("Pager", &user, &contact, &notification).is_delivered());
+    }
+}
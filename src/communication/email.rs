@@ -1,10 +1,24 @@
-use std::fmt::{Debug, Display};
+use std::fmt::Display;
+use std::sync::Arc;
 use lettre::{Message, SmtpTransport, Transport};
+use lettre::message::{Attachment, MultiPart};
+use lettre::message::header::ContentType;
 use lettre::transport::smtp::authentication::Credentials;
+use serde_json::json;
 use toml::Value;
 
-use crate::communication::{CommData, CommunicationMethod, UserCommunication};
-use crate::databases::{StoredDom, TrackedPage, User};
+use crate::communication::{CommData, CommunicationMethod, DefacementReport, Notification, UserCommunication};
+use crate::communication::templates::{notification_event, NotificationTemplates};
+use crate::databases::User;
+
+/*
+The emails are rendered from the email templates of the locale of the user,
+Every email has a subject, a plain text body and an HTML body. The defacement emails show the verdict
+Of every comparator and only the regions of the page that changed, the full doms are attached
+ */
+
+//The directory of the email templates in every locale
+const CHANNEL: &str = "email";
 
 pub struct EmailSMTPData {
     smtp_server: String,
@@ -18,10 +32,20 @@ pub struct EmailSMTPData {
 pub struct EmailCommunicator {
     mailer: SmtpTransport,
     smtp_data: EmailSMTPData,
+    templates: Arc<NotificationTemplates>,
+}
+
+///An email rendered from the templates
+struct EmailContent {
+    subject: String,
+    text: String,
+    html: String,
+    //Made of the file name, the content type and the content of the file
+    attachments: Vec<(String, &'static str, Vec<u8>)>,
 }
 
 impl EmailCommunicator {
-    pub fn new(config_file: &str) -> Self {
+    pub fn new(config_file: &str, templates: Arc<NotificationTemplates>) -> Self {
         let value = config_file.parse::<Value>().unwrap();
 
         let email_smtp = EmailSMTPData::new(String::from(value["smtp_server"].as_str().unwrap()),
@@ -42,20 +66,48 @@ impl EmailCommunicator {
         Self {
             mailer,
             smtp_data: email_smtp,
+            templates,
         }
     }
 
-    fn send_mail_to(&self, from: &str, destination: &str, subject: &str, body: &str) -> Result<String, String> {
-        let msg = Message::builder()
-            .from(from.parse().unwrap())
-            .to(destination.parse().unwrap())
-            .subject(subject)
-            .body(String::from(body))
-            .unwrap();
+    ///Send the email with the plain text and the HTML versions of the body, followed by its attachments
+    fn send_rendered_mail_to(&self, from: &str, destination: &str, mail: &EmailContent) -> Result<String, String> {
+        let mut multipart = MultiPart::mixed()
+            .multipart(MultiPart::alternative_plain_html(mail.text.clone(), mail.html.clone()));
 
-        let result = self.mailer.send(&msg);
+        for (file_name, content_type, content) in &mail.attachments {
+            let content_type = match ContentType::parse(content_type) {
+                Ok(content_type) => { content_type }
+                Err(e) => { return Err(e.to_string()); }
+            };
 
-        match result {
+            multipart = multipart.singlepart(Attachment::new(file_name.clone()).body(content.clone(), content_type));
+        }
+
+        let msg = match self.message_builder(from, destination, mail.subject.as_str())?.multipart(multipart) {
+            Ok(msg) => { msg }
+            Err(e) => { return Err(e.to_string()); }
+        };
+
+        self.send_message(&msg)
+    }
+
+    fn message_builder(&self, from: &str, destination: &str, subject: &str) -> Result<lettre::message::MessageBuilder, String> {
+        let from = match from.parse() {
+            Ok(from) => { from }
+            Err(e) => { return Err(format!("The sender {} is not valid. {}", from, e)); }
+        };
+
+        let destination = match destination.parse() {
+            Ok(destination) => { destination }
+            Err(e) => { return Err(format!("The destination {} is not valid. {}", destination, e)); }
+        };
+
+        Ok(Message::builder().from(from).to(destination).subject(subject))
+    }
+
+    fn send_message(&self, msg: &Message) -> Result<String, String> {
+        match self.mailer.send(msg) {
             Ok(_) => {
                 Ok(String::from("Email sent successfully"))
             }
@@ -65,6 +117,32 @@ impl EmailCommunicator {
         }
     }
 
+    ///The email of the notification in the locale of the user, defacements have the doms and screenshots attached
+    fn notification_mail<T>(&self, user: &User, notification: &Notification<T>) -> Result<EmailContent, String> where T: Display {
+        let attachments = match notification {
+            Notification::Defacement(report) => { defacement_attachments(report) }
+            Notification::Recovery(_) => { Vec::new() }
+        };
+
+        let mut context = self.templates.notification_context(user, notification);
+
+        context["attachments"] = json!(attachments.iter().map(|(file_name, _, _)| file_name.clone()).collect::<Vec<String>>());
+
+        let event = notification_event(notification);
+
+        let render = |template: String| -> Result<String, String> {
+            self.templates.render(user.locale(), format!("{}/{}", CHANNEL, template).as_str(), &context)
+        };
+
+        Ok(EmailContent {
+            //The subject must be a single line, the template files usually end with one
+            subject: render(format!("{}_subject.txt", event))?.trim().replace('\n', " "),
+            text: render(format!("{}.txt", event))?,
+            html: render(format!("{}.html", event))?,
+            attachments,
+        })
+    }
+
     pub fn mailer(&self) -> &SmtpTransport {
         &self.mailer
     }
@@ -73,6 +151,22 @@ impl EmailCommunicator {
     }
 }
 
+///The previous and current doms of the page, followed by their screenshots
+fn defacement_attachments<T>(report: &DefacementReport<T>) -> Vec<(String, &'static str, Vec<u8>)> where T: Display {
+    let mut attachments = vec![(String::from("previous_dom.html"), "text/html; charset=utf-8", report.stored_dom().dom().to_string().into_bytes()),
+                               (String::from("current_dom.html"), "text/html; charset=utf-8", report.latest_dom().to_string().into_bytes())];
+
+    if let Some(screenshot) = report.stored_dom().screenshot() {
+        attachments.push((String::from("previous.png"), "image/png", screenshot.clone()));
+    }
+
+    if let Some(screenshot) = report.latest_screenshot() {
+        attachments.push((String::from("current.png"), "image/png", screenshot.clone()));
+    }
+
+    attachments
+}
+
 impl EmailSMTPData {
     pub fn new(smtp_server: String, username: String, password: String, port: Option<i64>,
                from_name: String, from_email: String) -> Self {
@@ -101,45 +195,120 @@ impl EmailSMTPData {
 }
 
 impl<T> CommunicationMethod<T> for EmailCommunicator where T: Display {
+    fn name(&self) -> &str {
+        "Email"
+    }
+
     fn matches(&self, comm: &CommData) -> bool {
-        return match comm { CommData::Email(_) => { true } };
+        matches!(comm, CommData::Email(_))
     }
 
-    fn send_report_to(&self, user: &User, comm_method: &UserCommunication, tracked_page: &TrackedPage,
-                      stored_dom: &StoredDom<T>, latest_dom: &T) -> Result<String, String> {
-        return match comm_method.communication() {
+    fn send_notification_to(&self, user: &User, comm_method: &UserCommunication, notification: &Notification<T>) -> Result<String, String> {
+        match comm_method.communication() {
             CommData::Email(email) => {
-                return self.send_mail_to(format!("{} <{}>", self.smtp_data().from_name(), self.smtp_data().from_email()).as_str(),
-                                         format!("{} <{}>", user.user(), email).as_str(),
-                                         format!("Defacement detected in tracked page {} with ID {}",
-                                                 tracked_page.page_url(), tracked_page.page_id()).as_str(),
-                                         format!("The previous body was: \n{}\n The current body is: \n{}\n",
-                                                 stored_dom.dom(), latest_dom).as_str(),
-                );
+                let mail = self.notification_mail(user, notification)?;
+
+                self.send_rendered_mail_to(format!("{} <{}>", self.smtp_data().from_name(), self.smtp_data().from_email()).as_str(),
+                                           format!("{} <{}>", user.user(), email).as_str(),
+                                           &mail)
             }
             _ => {
                 Err(String::from("There is no email registered to that communication method."))
             }
-        };
+        }
     }
 }
 
 #[cfg(test)]
 mod email_tests {
-    use crate::communication::CommunicationMethod;
-    use crate::communication::email::EmailCommunicator;
+    use crate::communication::{DefacementReport, Notification, RecoveryReport};
+    use crate::communication::email::{EmailCommunicator, EmailContent};
+    use crate::communication::test_fixtures::test_templates;
+    use crate::comparators::{ComparatorResult, CompareResult, Verdict};
+    use crate::comparators::aggregation::AggregatedResult;
+    use crate::databases::{Incident, IncidentResolution, StoredDom, TrackedPage, TrackedPageType, User};
 
     #[test]
     fn test_send_mail() {
         let config_file = include_str!("../../resources/email.toml");
 
-        let communicator = EmailCommunicator::new(config_file);
+        let communicator = EmailCommunicator::new(config_file, test_templates());
 
-        let result_str = communicator.send_mail_to(
+        let mail = EmailContent {
+            subject: String::from("Test subject"),
+            text: String::from("Test body"),
+            html: String::from("<p>Test body</p>"),
+            attachments: vec![(String::from("test.txt"), "text/plain", b"Test attachment".to_vec())],
+        };
+
+        communicator.send_rendered_mail_to(
             "Nuno Neto <nunonuninho2@gmail.com>",
             "Nuno Neto <nuno.neto.g@gmail.com>",
-            "Test subject",
-            "Test body",
+            &mail,
         ).unwrap();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_recovery_mail() {
+        let page = TrackedPage::new(3, String::from("https://example.org"), 1, 0, 0, 0, 0, 5, false,
+                                    TrackedPageType::Static);
+
+        let incident = Incident::new(7, 3, 1, 0, Some(150_000), Vec::new(), String::new(), vec![1],
+                                     Some(IncidentResolution::Recovered));
+
+        let communicator = EmailCommunicator::new(include_str!("../../resources/email.toml"), test_templates());
+
+        let mail = communicator.notification_mail::<String>(&User::new(1, String::from("owner")),
+                                                         &Notification::Recovery(RecoveryReport::new(&page, &incident))).unwrap();
+
+        assert!(mail.subject.contains("https://example.org") && !mail.subject.contains('\n'));
+        assert!(mail.text.contains("incident 7") && mail.text.contains("2m 30s"));
+        assert!(mail.html.contains("1970-01-01 00:02:30 UTC"));
+        assert!(mail.attachments.is_empty());
+    }
+
+    #[test]
+    fn test_defacement_mail() {
+        let page = TrackedPage::new(3, String::from("https://example.org"), 1, 0, 0, 0, 0, 5, false,
+                                    TrackedPageType::Static);
+
+        let stored: Vec<String> = (1..=40).map(|line| format!("<p>Line {}</p>", line)).collect();
+
+        let mut current = stored.clone();
+
+        current[20] = String::from("<h1>Hacked by <script>alert(1)</script></h1>");
+
+        let mut stored_dom = StoredDom::new(1, 3, stored.join("\n"));
+
+        stored_dom.set_screenshot(Some(vec![1, 2, 3]));
+
+        let current_dom = current.join("\n");
+
+        let result = AggregatedResult::new(String::from("AnyDefaced"), Verdict::Defaced, 1.0,
+                                           vec![ComparatorResult::new("Diff", CompareResult::defaced(1.0, String::from("2.50% of the static page changed")).with_score(2.5)),
+                                                ComparatorResult::new("Checksum", CompareResult::maybe_defaced("The checksum changed"))]);
+
+        let report = DefacementReport::new(&page, &stored_dom, &current_dom, None, &result).with_detected_time(86_400_000);
+
+        let communicator = EmailCommunicator::new(include_str!("../../resources/email.toml"), test_templates());
+
+        let mail = communicator.notification_mail(&User::new(1, String::from("owner")), &Notification::Defacement(report)).unwrap();
+
+        assert!(mail.subject.contains("https://example.org with ID 3"));
+
+        //Only the changed region is shown, with the configured lines around it
+        assert!(mail.text.contains("@@ -18,7 +18,7 @@"));
+        assert!(mail.text.contains("- <p>Line 21</p>") && mail.text.contains("+ <h1>Hacked by"));
+        assert!(!mail.text.contains("<p>Line 1</p>") && !mail.text.contains("<p>Line 40</p>"));
+
+        assert!(mail.html.contains("1970-01-02 00:00:00 UTC"));
+        assert!(mail.html.contains("<td>Diff</td>") && mail.html.contains("2.5"));
+        //The doms are escaped in the HTML body
+        assert!(mail.html.contains("&lt;script&gt;") && !mail.html.contains("<script>"));
+
+        let attached: Vec<&str> = mail.attachments.iter().map(|(file_name, _, _)| file_name.as_str()).collect();
+
+        assert_eq!(attached, vec!["previous_dom.html", "current_dom.html", "previous.png"]);
+        assert_eq!(mail.attachments[1].2, current_dom.into_bytes());
+    }
+}
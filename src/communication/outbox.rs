@@ -0,0 +1,123 @@
+use std::time::Duration;
+use toml::Value;
+
+/*
+The notifications are not sent when they are created, they are written to the outbox of the database
+And delivered by a worker that retries the failed deliveries with an exponential backoff.
+A notification that keeps failing is given up on after the max attempts and kept as a dead letter,
+So it can still be looked at (And retried) instead of being lost.
+ */
+
+///How the worker delivers the notifications of the outbox
+#[derive(PartialEq, Debug, Clone)]
+pub struct OutboxPolicy {
+    //Time between the runs of the worker
+    poll_interval: Duration,
+    //Maximum amount of notifications delivered in a run
+    batch_size: u32,
+    //Time before the first retry of a failed delivery, doubled after every failed attempt
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_attempts: u32,
+    //How long a claimed notification is reserved for the worker delivering it
+    lease: Duration,
+    //How long the delivered notifications are kept in the outbox
+    delivered_retention: Duration,
+}
+
+impl OutboxPolicy {
+    pub fn new(poll_interval: Duration, batch_size: u32, initial_backoff: Duration, max_backoff: Duration,
+               max_attempts: u32, lease: Duration, delivered_retention: Duration) -> Self {
+        Self { poll_interval, batch_size, initial_backoff, max_backoff, max_attempts, lease, delivered_retention }
+    }
+
+    pub fn from_config(config_file: &str) -> Result<Self, String> {
+        let value = match config_file.parse::<Value>() {
+            Ok(value) => { value }
+            Err(e) => { return Err(e.to_string()); }
+        };
+
+        let read = |key: &str| -> Result<u64, String> {
+            match value.get(key).and_then(|value| value.as_integer()) {
+                Some(value) if value >= 0 => { Ok(value as u64) }
+                _ => { Err(format!("The outbox setting {} is missing or is not a positive number", key)) }
+            }
+        };
+
+        let max_attempts = read("max_attempts")?;
+
+        if max_attempts == 0 {
+            return Err(String::from("The outbox needs at least one delivery attempt"));
+        }
+
+        let poll_interval = read("poll_interval_secs")?;
+
+        if poll_interval == 0 {
+            return Err(String::from("The worker of the outbox needs at least a second between its runs"));
+        }
+
+        Ok(Self::new(Duration::from_secs(poll_interval), read("batch_size")? as u32,
+                     Duration::from_secs(read("initial_backoff_secs")?), Duration::from_secs(read("max_backoff_secs")?),
+                     max_attempts as u32, Duration::from_secs(read("lease_secs")?),
+                     Duration::from_secs(read("delivered_retention_days")?.saturating_mul(24 * 60 * 60))))
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+    pub fn batch_size(&self) -> u32 {
+        self.batch_size
+    }
+    pub fn initial_backoff(&self) -> Duration {
+        self.initial_backoff
+    }
+    pub fn max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+    pub fn lease(&self) -> Duration {
+        self.lease
+    }
+    pub fn delivered_retention(&self) -> Duration {
+        self.delivered_retention
+    }
+
+    ///How long to wait before the next attempt, after the given amount of failed attempts
+    pub fn backoff_for(&self, attempts: u32) -> Duration {
+        let doublings = attempts.saturating_sub(1).min(31);
+
+        self.initial_backoff.saturating_mul(1 << doublings).min(self.max_backoff)
+    }
+
+    ///Whether a notification that failed the given amount of attempts should be dead lettered
+    pub fn is_exhausted(&self, attempts: u32) -> bool {
+        attempts >= self.max_attempts
+    }
+}
+
+#[cfg(test)]
+mod outbox_tests {
+    use std::time::Duration;
+    use crate::communication::outbox::OutboxPolicy;
+
+    #[test]
+    fn test_backoff() {
+        let policy = OutboxPolicy::from_config(include_str!("../../resources/outbox.toml")).unwrap();
+
+        assert_eq!(policy.backoff_for(1), Duration::from_secs(30));
+        assert_eq!(policy.backoff_for(2), Duration::from_secs(60));
+        assert_eq!(policy.backoff_for(4), Duration::from_secs(240));
+        //Capped at the max backoff, even after more attempts than fit in the shift
+        assert_eq!(policy.backoff_for(8), Duration::from_secs(3600));
+        assert_eq!(policy.backoff_for(u32::MAX), Duration::from_secs(3600));
+
+        assert!(!policy.is_exhausted(9));
+        assert!(policy.is_exhausted(10));
+
+        assert_eq!(policy.delivered_retention(), Duration::from_secs(7 * 24 * 60 * 60));
+
+        assert!(OutboxPolicy::from_config("max_attempts = 0").is_err());
+    }
+}
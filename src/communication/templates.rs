@@ -0,0 +1,351 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use log::debug;
+use serde_json::{json, Value as JsonValue};
+use tera::{Context, Tera};
+use toml::Value;
+
+use crate::communication::{DefacementReport, format_duration, format_timestamp, Notification, RecoveryReport};
+use crate::comparators::{Verdict, verdict_to_str};
+use crate::comparators::diff_comparator::{diff_hunks, DiffLineKind, summarise_diff};
+use crate::databases::User;
+
+/*
+Every communication method renders its messages from the templates in the templates directory (Tera syntax),
+So the wording can be changed without touching the code. The directory has a directory for every locale
+(en, pt...) with a directory for every channel (email, chat, webhook) holding the templates of every event.
+All the templates are rendered with the same context (See notification_context), the users get the templates
+Of their locale and the templates missing from it are taken from the default locale.
+Rendering doesn't send anything, so the templates can be previewed and tested on their own
+ */
+
+pub struct NotificationTemplates {
+    //The templates of every locale, by the name of the locale
+    locales: BTreeMap<String, Tera>,
+    default_locale: String,
+    //Unchanged lines shown around every changed region of the diff
+    diff_context_lines: usize,
+    max_diff_hunks: usize,
+    max_diff_line_length: usize,
+    //Amount of added and removed lines in the short excerpt of the diff used by the chat messages
+    excerpt_lines: usize,
+    excerpt_line_length: usize,
+    //Prefix of the link to the archived versions of the page, followed by the URL of the page
+    archive_url: String,
+}
+
+impl NotificationTemplates {
+    ///The templates directory of the config file is relative to the given resources directory
+    pub fn new(config_file: &str, resources_dir: &Path) -> Result<Self, String> {
+        let value = match config_file.parse::<Value>() {
+            Ok(value) => { value }
+            Err(e) => { return Err(e.to_string()); }
+        };
+
+        let templates_dir = resources_dir.join(value.get("templates_dir")
+            .and_then(|dir| dir.as_str())
+            .unwrap_or("templates"));
+
+        let entries = match fs::read_dir(&templates_dir) {
+            Ok(entries) => { entries }
+            Err(e) => { return Err(format!("Failed to read the templates directory {}. {}", templates_dir.display(), e)); }
+        };
+
+        let mut locales = BTreeMap::new();
+
+        for entry in entries.flatten() {
+            if !entry.path().is_dir() {
+                continue;
+            }
+
+            let locale = entry.file_name().to_string_lossy().to_string();
+
+            let templates = match Tera::new(format!("{}/**/*", templates_dir.join(&locale).display()).as_str()) {
+                Ok(templates) => { templates }
+                Err(e) => {
+                    return Err(format!("Failed to load the templates of the locale {}. {} {}", locale, e,
+                                       e.source().map(|source| source.to_string()).unwrap_or_default()));
+                }
+            };
+
+            locales.insert(locale, templates);
+        }
+
+        let default_locale = String::from(value.get("default_locale").and_then(|locale| locale.as_str()).unwrap_or("en"));
+
+        if !locales.contains_key(&default_locale) {
+            return Err(format!("There are no templates for the default locale {} in {}", default_locale, templates_dir.display()));
+        }
+
+        let read_size = |key: &str, default: usize| {
+            value.get(key).and_then(|size| size.as_integer()).map(|size| size as usize).unwrap_or(default)
+        };
+
+        Ok(Self {
+            locales,
+            default_locale,
+            diff_context_lines: read_size("diff_context_lines", 3),
+            max_diff_hunks: read_size("max_diff_hunks", 20),
+            max_diff_line_length: read_size("max_diff_line_length", 300),
+            excerpt_lines: read_size("excerpt_lines", 5),
+            excerpt_line_length: read_size("excerpt_line_length", 120),
+            archive_url: String::from(value.get("archive_url").and_then(|url| url.as_str())
+                .unwrap_or("https://web.archive.org/web/*/")),
+        })
+    }
+
+    ///Render the template of the channel for the notification, in the locale of the user
+    /// The template is named after the event of the notification (defacement.txt, recovery.txt...)
+    pub fn render_notification<T>(&self, user: &User, channel: &str, notification: &Notification<T>) -> Result<String, String>
+        where T: Display {
+        let template = format!("{}/{}.txt", channel, notification_event(notification));
+
+        self.render(user.locale(), template.as_str(), &self.notification_context(user, notification))
+    }
+
+    ///Render the template with the context, the template is looked up in the locale and then in the default locale
+    pub fn render(&self, locale: Option<&str>, template: &str, context: &JsonValue) -> Result<String, String> {
+        let templates = self.templates_for(locale, template);
+
+        let context = match Context::from_value(context.clone()) {
+            Ok(context) => { context }
+            Err(e) => { return Err(e.to_string()); }
+        };
+
+        match templates.render(template, &context) {
+            Ok(rendered) => { Ok(rendered) }
+            Err(e) => {
+                //The cause of the error (The line of the template, the missing variable...) is in its source
+                Err(format!("Failed to render the template {}. {} {}", template, e,
+                            e.source().map(|source| source.to_string()).unwrap_or_default()))
+            }
+        }
+    }
+
+    ///The templates of the locale that has the template, pt-BR falls back to pt and then to the default locale
+    fn templates_for(&self, locale: Option<&str>, template: &str) -> &Tera {
+        let default = &self.locales[&self.default_locale];
+
+        let locale = match locale {
+            Some(locale) => { locale }
+            None => { return default; }
+        };
+
+        let language = locale.split(['-', '_']).next().unwrap_or(locale);
+
+        for candidate in [locale, language] {
+            if let Some(templates) = self.locales.get(candidate) {
+                if templates.get_template_names().any(|name| name == template) {
+                    return templates;
+                }
+            }
+        }
+
+        debug!("There is no template {} for the locale {}, using the default locale {}", template, locale, self.default_locale);
+
+        default
+    }
+
+    ///The context every template of the notification is rendered with, the same for every channel
+    pub fn notification_context<T>(&self, user: &User, notification: &Notification<T>) -> JsonValue where T: Display {
+        let (page, details) = match notification {
+            Notification::Defacement(report) => { (report.tracked_page(), self.defacement_context(report)) }
+            Notification::Recovery(report) => { (report.tracked_page(), recovery_context(report)) }
+        };
+
+        let mut context = json!({
+            "event": notification_event(notification),
+            "user": {
+                "id": user.user_id(),
+                "name": user.user(),
+                "locale": user.locale().unwrap_or(self.default_locale.as_str()),
+            },
+            "page": { "id": page.page_id(), "url": page.page_url() },
+            "links": {
+                "page": page.page_url(),
+                "archive": format!("{}{}", self.archive_url, page.page_url()),
+            },
+            "incident": null,
+        });
+
+        if let (Some(context), JsonValue::Object(details)) = (context.as_object_mut(), details) {
+            context.extend(details);
+        }
+
+        context
+    }
+
+    fn defacement_context<T>(&self, report: &DefacementReport<T>) -> JsonValue where T: Display {
+        let comparators: Vec<JsonValue> = report.result().breakdown().iter().map(|comparator_result| {
+            let result = comparator_result.result();
+
+            json!({
+                "name": comparator_result.comparator(),
+                "verdict": verdict_to_str(&result.verdict()),
+                "defaced": result.verdict() == Verdict::Defaced,
+                "confidence": result.confidence(),
+                "score": result.score(),
+                "explanation": result.explanation(),
+            })
+        }).collect();
+
+        json!({
+            "detected_at": format_timestamp(report.detected_time()),
+            "verdict": verdict_to_str(&report.result().verdict()),
+            "policy": report.result().policy(),
+            "score": report.result().score(),
+            "comparators": comparators,
+            "diff": self.diff_context(report.stored_dom().dom().to_string().as_str(), report.latest_dom().to_string().as_str()),
+        })
+    }
+
+    ///The changed regions of the page as unified diff hunks, with a short excerpt of the added and removed lines
+    fn diff_context(&self, stored_dom: &str, latest_dom: &str) -> JsonValue {
+        let summary = summarise_diff(stored_dom, latest_dom, self.excerpt_lines);
+
+        let hunks = diff_hunks(stored_dom, latest_dom, self.diff_context_lines);
+
+        let shown_hunks: Vec<JsonValue> = hunks.iter().take(self.max_diff_hunks).map(|hunk| {
+            let old_lines = hunk.lines().iter().filter(|(kind, _)| *kind != DiffLineKind::Added).count();
+            let new_lines = hunk.lines().iter().filter(|(kind, _)| *kind != DiffLineKind::Removed).count();
+
+            let lines: Vec<JsonValue> = hunk.lines().iter().map(|(kind, line)| {
+                let (kind, marker) = match kind {
+                    DiffLineKind::Context => { ("context", " ") }
+                    DiffLineKind::Added => { ("added", "+") }
+                    DiffLineKind::Removed => { ("removed", "-") }
+                };
+
+                json!({ "kind": kind, "marker": marker, "text": cut_line(line, self.max_diff_line_length) })
+            }).collect();
+
+            json!({
+                "header": format!("@@ -{},{} +{},{} @@", hunk.old_start(), old_lines, hunk.new_start(), new_lines),
+                "lines": lines,
+            })
+        }).collect();
+
+        let excerpt = |lines: &Vec<String>| -> Vec<String> {
+            lines.iter().map(|line| cut_line(line, self.excerpt_line_length)).collect()
+        };
+
+        json!({
+            "changed_percent": summary.changed_percent(),
+            "added_count": summary.added_count(),
+            "removed_count": summary.removed_count(),
+            "hunks": shown_hunks,
+            "omitted_hunks": hunks.len().saturating_sub(self.max_diff_hunks),
+            "excerpt": {
+                "added": excerpt(summary.added()),
+                "removed": excerpt(summary.removed()),
+            },
+        })
+    }
+
+    pub fn locales(&self) -> Vec<&str> {
+        self.locales.keys().map(|locale| locale.as_str()).collect()
+    }
+    pub fn default_locale(&self) -> &str {
+        &self.default_locale
+    }
+}
+
+fn recovery_context(report: &RecoveryReport) -> JsonValue {
+    let incident = report.incident();
+
+    json!({
+        "incident": {
+            "id": incident.incident_id(),
+            "started_at": format_timestamp(incident.start_time()),
+            "ended_at": format_timestamp(incident.end_time().unwrap_or_else(|| incident.start_time())),
+            "duration": format_duration(report.breach_duration()),
+        },
+    })
+}
+
+///The name of the event of the notification, the templates of every channel are named after it
+pub fn notification_event<T>(notification: &Notification<T>) -> &'static str {
+    match notification {
+        Notification::Defacement(_) => { "defacement" }
+        Notification::Recovery(_) => { "recovery" }
+    }
+}
+
+///Lines longer than the limit are cut, minified pages can have the whole dom in a single line
+fn cut_line(line: &str, max_length: usize) -> String {
+    match line.char_indices().nth(max_length) {
+        Some((cut, _)) => { format!("{}...", &line[..cut]) }
+        None => { String::from(line) }
+    }
+}
+
+#[cfg(test)]
+mod templates_tests {
+    use crate::communication::{DefacementReport, Notification, RecoveryReport};
+    use crate::comparators::{ComparatorResult, CompareResult, Verdict};
+    use crate::comparators::aggregation::AggregatedResult;
+    use crate::communication::test_fixtures::{recovered_incident, test_owner, test_page, test_templates};
+    use crate::databases::StoredDom;
+
+    #[test]
+    fn test_notification_context() {
+        let templates = test_templates();
+
+        let page = test_page();
+
+        let stored_dom = StoredDom::new(1, 4, String::from("<h1>Shop</h1>\n<p>Welcome</p>"));
+        let current_dom = String::from("<h1>Hacked</h1>\n<p>Welcome</p>");
+
+        let result = AggregatedResult::new(String::from("AnyDefaced"), Verdict::Defaced, 1.0,
+                                           vec![ComparatorResult::new("Signature", CompareResult::defaced(1.0, String::from("Matched Hacked")))]);
+
+        let notification = Notification::Defacement(DefacementReport::new(&page, &stored_dom, &current_dom, None, &result));
+
+        let context = templates.notification_context(&test_owner(), &notification);
+
+        assert_eq!(context["event"], "defacement");
+        assert_eq!(context["user"]["locale"], "en");
+        assert_eq!(context["page"]["id"], 4);
+        assert_eq!(context["links"]["archive"], "https://web.archive.org/web/*/https://example.org");
+        assert_eq!(context["comparators"][0]["defaced"], true);
+        assert_eq!(context["diff"]["excerpt"]["added"][0], "<h1>Hacked</h1>");
+        assert_eq!(context["diff"]["hunks"][0]["header"], "@@ -1,2 +1,2 @@");
+        assert!(context["incident"].is_null());
+    }
+
+    #[test]
+    fn test_render_in_locale() {
+        let templates = test_templates();
+
+        let (page, incident) = (test_page(), recovered_incident());
+
+        let notification: Notification<String> = Notification::Recovery(RecoveryReport::new(&page, &incident));
+
+        let user = test_owner();
+
+        let english = templates.render_notification(&user, "chat", &notification).unwrap();
+
+        assert!(english.contains("is back to normal") && english.contains("lasted 1m 0s"));
+
+        //Regional locales use the templates of their language
+        let portuguese = templates.render_notification(&user.clone().with_locale(Some(String::from("pt-BR"))),
+                                                       "chat", &notification).unwrap();
+
+        assert!(portuguese.contains("voltou ao normal") && portuguese.contains("durou 1m 0s"));
+
+        //The templates missing from the locale, or unknown locales, are taken from the default locale
+        let webhook = templates.render_notification(&user.clone().with_locale(Some(String::from("pt"))),
+                                                    "webhook", &notification).unwrap();
+
+        assert!(webhook.contains("is back to normal after 1m 0s"));
+
+        let unknown = templates.render_notification(&user.with_locale(Some(String::from("xx"))), "chat", &notification).unwrap();
+
+        assert_eq!(unknown, english);
+
+        assert!(templates.render(None, "chat/missing.txt", &serde_json::json!({})).is_err());
+    }
+}
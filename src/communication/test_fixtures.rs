@@ -0,0 +1,82 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use tiny_http::{Response, Server};
+use crate::communication::templates::NotificationTemplates;
+use crate::databases::{Incident, IncidentResolution, TrackedPage, TrackedPageType, User};
+
+/*
+What the tests of the communication methods have in common: the page and incident the notifications are about,
+And a local HTTP server standing in for the services the notifications are sent to.
+ */
+
+///A request received by the test server
+pub struct ReceivedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl ReceivedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+///The page the test notifications are about, with ID 4 and owned by the user 2
+pub fn test_page() -> TrackedPage {
+    TrackedPage::new(4, String::from("https://example.org"), 2, 0, 0, 0, 0, 5, false, TrackedPageType::Static)
+}
+
+///Incident 9 of the test page, notified to the contact 1 and recovered after a minute
+pub fn recovered_incident() -> Incident {
+    Incident::new(9, 4, 2, 0, Some(60_000), Vec::new(), String::new(), vec![1], Some(IncidentResolution::Recovered))
+}
+
+pub fn test_owner() -> User {
+    User::new(2, String::from("owner"))
+}
+
+pub fn test_templates() -> Arc<NotificationTemplates> {
+    Arc::new(NotificationTemplates::new(include_str!("../../resources/templates.toml"),
+                                        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/resources"))).unwrap())
+}
+
+///
+/// Answer a request with every one of the given statuses and bodies, in order.
+/// Returns the address of the server (http://host:port) and the thread that returns the received requests
+pub fn serve_responses(responses: Vec<(u16, &'static str)>) -> (String, thread::JoinHandle<Vec<ReceivedRequest>>) {
+    let server = Server::http("127.0.0.1:0").unwrap();
+
+    let address = format!("http://{}", server.server_addr());
+
+    let handle = thread::spawn(move || {
+        let mut received = Vec::new();
+
+        for (status, body) in responses {
+            let mut request = server.recv().unwrap();
+
+            let mut request_body = String::new();
+
+            request.as_reader().read_to_string(&mut request_body).unwrap();
+
+            received.push(ReceivedRequest {
+                method: request.method().to_string(),
+                url: String::from(request.url()),
+                headers: request.headers().iter()
+                    .map(|header| (header.field.to_string(), header.value.to_string()))
+                    .collect(),
+                body: request_body,
+            });
+
+            request.respond(Response::from_string(body).with_status_code(status)).unwrap();
+        }
+
+        received
+    });
+
+    (address, handle)
+}
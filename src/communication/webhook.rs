@@ -0,0 +1,259 @@
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value as JsonValue};
+use sha2::Sha256;
+use toml::Value;
+use ureq::{Agent, AgentBuilder};
+
+use crate::communication::{CommData, CommunicationMethod, DefacementReport, Notification, RecoveryReport, UserCommunication};
+use crate::communication::templates::NotificationTemplates;
+use crate::comparators::diff_comparator::summarise_diff;
+use crate::comparators::verdict_to_str;
+use crate::databases::User;
+
+/*
+Sends the notifications as JSON payloads to a URL chosen by every contact, so they can be plugged into
+Whatever the users already run (Incident management, chat bots, ticketing...).
+When the contact has a secret, the payload is signed with HMAC-SHA256 so the receiver can verify it came from us.
+Besides the data, the payload has a short message rendered from the webhook templates, for the receivers that only relay text.
+ */
+
+//Amount of added and removed lines of the diff that are sent in the payload
+const DIFF_LINES: usize = 10;
+
+pub struct WebhookCommunicator {
+    agent: Agent,
+    //The header that carries the signature of the payload
+    signature_header: String,
+    templates: Arc<NotificationTemplates>,
+}
+
+impl WebhookCommunicator {
+    pub fn new(config_file: &str, templates: Arc<NotificationTemplates>) -> Self {
+        let value = config_file.parse::<Value>().unwrap();
+
+        let agent = AgentBuilder::new()
+            .user_agent(value["user_agent"].as_str().unwrap())
+            .timeout(Duration::from_secs(value["timeout_secs"].as_integer().unwrap() as u64))
+            .build();
+
+        Self {
+            agent,
+            signature_header: String::from(value["signature_header"].as_str().unwrap()),
+            templates,
+        }
+    }
+
+    ///POST the payload to the webhook. Failed deliveries are retried by the outbox, with its backoff
+    fn post_payload(&self, url: &str, secret: Option<&str>, payload: &str) -> Result<String, String> {
+        let mut request = self.agent.post(url)
+            .set("Content-Type", "application/json");
+
+        if let Some(secret) = secret {
+            request = request.set(self.signature_header.as_str(), sign_payload(secret, payload).as_str());
+        }
+
+        match request.send_string(payload) {
+            Ok(response) => {
+                Ok(format!("Webhook answered with status {}", response.status()))
+            }
+            Err(ureq::Error::Status(status, _)) => {
+                Err(format!("Webhook {} rejected the notification with status {}", url, status))
+            }
+            Err(e) => {
+                Err(format!("Failed to deliver the notification to webhook {}. {}", url, e))
+            }
+        }
+    }
+
+    pub fn signature_header(&self) -> &str {
+        &self.signature_header
+    }
+}
+
+///The hex encoded HMAC-SHA256 of the payload, prefixed by the algorithm (sha256=...)
+pub fn sign_payload(secret: &str, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any size");
+
+    mac.update(payload.as_bytes());
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+///The JSON payload that is sent to the webhooks for the notification
+pub fn notification_payload<T>(user: &User, notification: &Notification<T>) -> JsonValue where T: Display {
+    match notification {
+        Notification::Defacement(report) => { defacement_payload(user, report) }
+        Notification::Recovery(report) => { recovery_payload(user, report) }
+    }
+}
+
+fn defacement_payload<T>(user: &User, report: &DefacementReport<T>) -> JsonValue where T: Display {
+    let page = report.tracked_page();
+
+    let breakdown: Vec<JsonValue> = report.result().breakdown().iter().map(|comparator_result| {
+        let result = comparator_result.result();
+
+        json!({
+            "comparator": comparator_result.comparator(),
+            "verdict": verdict_to_str(&result.verdict()),
+            "confidence": result.confidence(),
+            "explanation": result.explanation(),
+            "score": result.score(),
+        })
+    }).collect();
+
+    let diff = summarise_diff(report.stored_dom().dom().to_string().as_str(),
+                              report.latest_dom().to_string().as_str(), DIFF_LINES);
+
+    json!({
+        "event": "defacement",
+        "page": { "id": page.page_id(), "url": page.page_url() },
+        "user": { "id": user.user_id(), "username": user.user() },
+        "verdict": verdict_to_str(&report.result().verdict()),
+        "policy": report.result().policy(),
+        "score": report.result().score(),
+        "breakdown": breakdown,
+        "diff": {
+            "changed_percent": diff.changed_percent(),
+            "added_lines": diff.added_count(),
+            "removed_lines": diff.removed_count(),
+            "added": diff.added(),
+            "removed": diff.removed(),
+        },
+    })
+}
+
+fn recovery_payload(user: &User, report: &RecoveryReport) -> JsonValue {
+    let page = report.tracked_page();
+
+    json!({
+        "event": "recovery",
+        "page": { "id": page.page_id(), "url": page.page_url() },
+        "user": { "id": user.user_id(), "username": user.user() },
+        "incident": {
+            "id": report.incident().incident_id(),
+            "start_time": report.incident().start_time() as u64,
+            "end_time": report.incident().end_time().map(|end_time| end_time as u64),
+        },
+        "breach_duration_secs": report.breach_duration().as_secs(),
+    })
+}
+
+impl<T> CommunicationMethod<T> for WebhookCommunicator where T: Display {
+    fn name(&self) -> &str {
+        "Webhook"
+    }
+
+    fn matches(&self, comm: &CommData) -> bool {
+        matches!(comm, CommData::Webhook { .. })
+    }
+
+    fn send_notification_to(&self, user: &User, comm_method: &UserCommunication, notification: &Notification<T>) -> Result<String, String> {
+        match comm_method.communication() {
+            CommData::Webhook { url, secret } => {
+                let mut payload = notification_payload(user, notification);
+
+                payload["message"] = json!(self.templates.render_notification(user, "webhook", notification)?.trim());
+
+                self.post_payload(url, secret.as_deref(), payload.to_string().as_str())
+            }
+            _ => {
+                Err(String::from("There is no webhook registered to that communication method."))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod webhook_tests {
+    use std::thread;
+    use crate::communication::{CommData, CommunicationMethod, DefacementReport, Notification, UserCommunication};
+    use crate::communication::test_fixtures::{ReceivedRequest, serve_responses, test_owner, test_page, test_templates};
+    use crate::communication::webhook::{sign_payload, WebhookCommunicator};
+    use crate::comparators::{ComparatorResult, CompareResult, Verdict};
+    use crate::comparators::aggregation::AggregatedResult;
+    use crate::databases::StoredDom;
+
+    const CONFIG: &str = "user_agent = \"defacement-monitor-test\"\ntimeout_secs = 5\n\
+    signature_header = \"X-Defacement-Signature\"";
+
+    ///Answers the requests with the given statuses
+    fn serve_statuses(statuses: Vec<u16>) -> (String, thread::JoinHandle<Vec<ReceivedRequest>>) {
+        let (address, server) = serve_responses(statuses.into_iter().map(|status| (status, "")).collect());
+
+        (format!("{}/hook", address), server)
+    }
+
+    fn communicator() -> WebhookCommunicator {
+        WebhookCommunicator::new(CONFIG, test_templates())
+    }
+
+    fn send_defacement(communicator: &WebhookCommunicator, url: String, secret: Option<String>) -> Result<String, String> {
+        let page = test_page();
+
+        let stored_dom = StoredDom::new(1, 4, String::from("<p>Shop</p>"));
+        let current_dom = String::from("<p>Hacked</p>");
+
+        let result = AggregatedResult::new(String::from("AnyDefaced"), Verdict::Defaced, 1.0,
+                                           vec![ComparatorResult::new("Diff", CompareResult::defaced(1.0, String::from("Changed")).with_score(80.0))]);
+
+        let contact = UserCommunication::new(1, 2, CommData::Webhook { url, secret });
+
+        let notification = Notification::Defacement(DefacementReport::new(&page, &stored_dom, &current_dom, None, &result));
+
+        communicator.send_notification_to(&test_owner(), &contact, &notification)
+    }
+
+    #[test]
+    fn test_signed_payload() {
+        let (address, server) = serve_statuses(vec![200]);
+
+        let communicator = communicator();
+
+        assert!(send_defacement(&communicator, address, Some(String::from("s3cret"))).is_ok());
+
+        let received = server.join().unwrap();
+
+        let body = &received[0].body;
+
+        assert_eq!(received[0].header("X-Defacement-Signature"), Some(sign_payload("s3cret", body).as_str()));
+
+        let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+
+        assert_eq!(payload["event"], "defacement");
+        assert_eq!(payload["page"]["id"], 4);
+        assert_eq!(payload["user"]["username"], "owner");
+        assert_eq!(payload["breakdown"][0]["score"], 80.0);
+        assert_eq!(payload["diff"]["added"][0], "<p>Hacked</p>");
+        assert_eq!(payload["message"], "Defacement detected in tracked page https://example.org (ID 4): Defaced with the AnyDefaced policy");
+    }
+
+    #[test]
+    fn test_single_attempt() {
+        let communicator = communicator();
+
+        let (address, server) = serve_statuses(vec![200]);
+
+        assert!(send_defacement(&communicator, address, None).is_ok());
+
+        let received = server.join().unwrap();
+
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].header("X-Defacement-Signature"), None);
+
+        //Failed deliveries are left to the backoff of the outbox, so the webhook is only called once
+        let (address, server) = serve_statuses(vec![503]);
+
+        assert!(send_defacement(&communicator, address, None).is_err());
+        assert_eq!(server.join().unwrap().len(), 1);
+
+        let (address, server) = serve_statuses(vec![400]);
+
+        assert!(send_defacement(&communicator, address, None).is_err());
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+}
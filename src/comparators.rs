@@ -1,7 +1,15 @@
 use crate::databases::TrackedPage;
 
+pub mod aggregation;
+pub mod baseline;
 pub mod checksum_comparator;
 pub mod diff_comparator;
+pub mod domain_comparator;
+pub mod script_comparator;
+pub mod signature_comparator;
+pub mod text_comparator;
+pub mod tree_comparator;
+pub mod visual_comparator;
 
 /// There are various kinds of comparators,
 /// Comparators made for static websites that register if there has been any kind of change
@@ -15,10 +23,41 @@ pub trait Comparator<T> : Send + Sync {
 
     fn compare_between(&self, page: &TrackedPage, dom_1: &T, dom_2: &T) -> CompareResult;
 
+    /// Called when a dynamic page is analysed, with the samples of the page that were collected.
+    /// This allows the comparator to learn what is normal for the page (for example its own threshold).
+    /// The returned data is stored with the page and can be read with TrackedPage::comparator_data
+    fn learn_from_samples(&self, _page: &TrackedPage, _samples: &[T]) -> Option<String> {
+        None
+    }
+
+    /// Called after a check that was not considered defaced, for the pages with an adaptive baseline.
+    /// This allows the comparator to move what it learned from the samples towards the page as it is now.
+    /// The returned data replaces the comparator data of the page
+    fn adapt_to_check(&self, _page: &TrackedPage, _dom_1: &T, _dom_2: &T, _weight: f64) -> Option<String> {
+        None
+    }
+
+    /// Describe what was found when the comparator considered the page defaced,
+    /// So it can be shown to the users in the notification
+    fn explain(&self, page: &TrackedPage, dom_1: &T, dom_2: &T) -> Option<String> {
+        let result = self.compare_between(page, dom_1, dom_2);
+
+        match result.verdict() {
+            Verdict::Defaced => { Some(String::from(result.explanation())) }
+            _ => { None }
+        }
+    }
+
+    /// Reload any configuration the comparator reads from outside the program (rule files...)
+    fn reload_configuration(&self) -> Result<(), String> {
+        Ok(())
+    }
+
 }
 
-//The possible results for a given comparator
-pub enum CompareResult {
+//The possible verdicts for a given comparator
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Verdict {
 
     //Not defaced means that it is 100% sure that the webpage was not defaced
     NotDefaced,
@@ -30,4 +69,102 @@ pub enum CompareResult {
     //This is for when we are 100% sure that the webpage was defaced
     Defaced
 
-}
\ No newline at end of file
+}
+
+///The result of a comparator, with how confident it is in its verdict (0 to 1)
+///And an explanation of what it found, which is shown to the users
+#[derive(PartialEq, Debug, Clone)]
+pub struct CompareResult {
+    verdict: Verdict,
+    confidence: f64,
+    explanation: String,
+    //The raw difference the comparator measured (Diff percentage, amount of new domains...)
+    //Kept in the check log so the trends can be graphed and the thresholds tuned
+    score: Option<f64>,
+}
+
+///The result of a single comparator, as part of the breakdown of a check
+#[derive(PartialEq, Debug, Clone)]
+pub struct ComparatorResult {
+    comparator: String,
+    result: CompareResult,
+}
+
+impl CompareResult {
+    pub fn new(verdict: Verdict, confidence: f64, explanation: String) -> Self {
+        Self { verdict, confidence: confidence.clamp(0.0, 1.0), explanation, score: None }
+    }
+
+    pub fn with_score(mut self, score: f64) -> Self {
+        self.score = Some(score);
+
+        self
+    }
+
+    pub fn not_defaced(confidence: f64, explanation: &str) -> Self {
+        Self::new(Verdict::NotDefaced, confidence, String::from(explanation))
+    }
+
+    ///Inconclusive results carry no confidence
+    pub fn maybe_defaced(explanation: &str) -> Self {
+        Self::new(Verdict::MaybeDefaced, 0.0, String::from(explanation))
+    }
+
+    pub fn defaced(confidence: f64, explanation: String) -> Self {
+        Self::new(Verdict::Defaced, confidence, explanation)
+    }
+
+    pub fn verdict(&self) -> Verdict {
+        self.verdict
+    }
+    pub fn confidence(&self) -> f64 {
+        self.confidence
+    }
+    pub fn explanation(&self) -> &str {
+        &self.explanation
+    }
+    pub fn score(&self) -> Option<f64> {
+        self.score
+    }
+}
+
+impl ComparatorResult {
+    pub fn new(comparator: &str, result: CompareResult) -> Self {
+        Self { comparator: String::from(comparator), result }
+    }
+
+    pub fn comparator(&self) -> &str {
+        &self.comparator
+    }
+    pub fn result(&self) -> &CompareResult {
+        &self.result
+    }
+}
+
+pub fn verdict_to_str(verdict: &Verdict) -> &'static str {
+    match verdict {
+        Verdict::NotDefaced => { "NotDefaced" }
+        Verdict::MaybeDefaced => { "MaybeDefaced" }
+        Verdict::Defaced => { "Defaced" }
+    }
+}
+
+pub fn verdict_from_str(verdict: &str) -> Verdict {
+    if verdict.eq_ignore_ascii_case("NotDefaced") {
+        Verdict::NotDefaced
+    } else if verdict.eq_ignore_ascii_case("Defaced") {
+        Verdict::Defaced
+    } else {
+        Verdict::MaybeDefaced
+    }
+}
+
+///How confident we are that a page is defaced when a measured difference is above the threshold.
+///Just above the threshold is 50%, twice the threshold (or more) is 100%
+pub fn confidence_above_threshold(value: f64, threshold: f64) -> f64 {
+    if threshold <= 0.0 {
+        return 1.0;
+    }
+
+    (0.5 + (value - threshold) / (2.0 * threshold)).clamp(0.5, 1.0)
+}
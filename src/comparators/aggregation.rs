@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use toml::Value;
+use crate::comparators::{ComparatorResult, Verdict};
+
+/*
+This mod combines the results of every comparator into a single verdict.
+Every comparator is always run, so the order in which they are registered does not
+decide the outcome and the evidence of all of them is kept in the breakdown.
+ */
+
+//Weight of the comparators that have no weight configured for the weighted vote
+const DEFAULT_WEIGHT: f64 = 1.0;
+
+pub enum AggregationPolicy {
+    //Defaced as soon as a single comparator says it is, with at least the given confidence
+    AnyDefaced { min_confidence: f64 },
+    //Every conclusive comparator votes with its weight times its confidence, defaced votes count
+    //Positively and not defaced votes negatively. The page is defaced when the total reaches the threshold
+    WeightedVote { weights: BTreeMap<String, f64>, threshold: f64 },
+    //Defaced when at least quorum comparators say it is, with at least the given confidence
+    Quorum { quorum: u32, min_confidence: f64 },
+}
+
+///The combined verdict of all the comparators for a check, which is either Defaced or NotDefaced
+#[derive(PartialEq, Debug, Clone)]
+pub struct AggregatedResult {
+    //The policy that produced this result
+    policy: String,
+    verdict: Verdict,
+    //The value the policy based its verdict on (Max confidence, vote total or amount of defaced verdicts)
+    score: f64,
+    breakdown: Vec<ComparatorResult>,
+}
+
+impl AggregationPolicy {
+    pub fn from_config(config_file: &str) -> Result<Self, String> {
+        let value = match config_file.parse::<Value>() {
+            Ok(value) => { value }
+            Err(e) => { return Err(e.to_string()); }
+        };
+
+        let min_confidence = value.get("min_confidence")
+            .and_then(|confidence| confidence.as_float())
+            .unwrap_or(0.5);
+
+        match value.get("policy").and_then(|policy| policy.as_str()).unwrap_or("any_defaced") {
+            "any_defaced" => {
+                Ok(AggregationPolicy::AnyDefaced { min_confidence })
+            }
+            "weighted_vote" => {
+                let mut weights = BTreeMap::new();
+
+                if let Some(configured) = value.get("weights").and_then(|weights| weights.as_table()) {
+                    for (comparator, weight) in configured {
+                        let weight = weight.as_float()
+                            .or_else(|| weight.as_integer().map(|weight| weight as f64));
+
+                        match weight {
+                            Some(weight) => { weights.insert(comparator.clone(), weight); }
+                            None => { return Err(format!("The weight of comparator {} is not a number", comparator)); }
+                        }
+                    }
+                }
+
+                let threshold = value.get("threshold")
+                    .and_then(|threshold| threshold.as_float())
+                    .unwrap_or(1.0);
+
+                Ok(AggregationPolicy::WeightedVote { weights, threshold })
+            }
+            "quorum" => {
+                let quorum = value.get("quorum")
+                    .and_then(|quorum| quorum.as_integer())
+                    .unwrap_or(2);
+
+                Ok(AggregationPolicy::Quorum { quorum: quorum as u32, min_confidence })
+            }
+            policy => {
+                Err(format!("Unknown aggregation policy {}, expected any_defaced, weighted_vote or quorum", policy))
+            }
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            AggregationPolicy::AnyDefaced { .. } => { "AnyDefaced" }
+            AggregationPolicy::WeightedVote { .. } => { "WeightedVote" }
+            AggregationPolicy::Quorum { .. } => { "Quorum" }
+        }
+    }
+
+    ///
+    /// Combine the results of every comparator into a single verdict
+    pub fn aggregate(&self, breakdown: Vec<ComparatorResult>) -> AggregatedResult {
+        let (defaced, score) = match self {
+            AggregationPolicy::AnyDefaced { min_confidence } => {
+                let max_confidence = breakdown.iter()
+                    .filter(|result| result.result().verdict() == Verdict::Defaced)
+                    .map(|result| result.result().confidence())
+                    .fold(0.0, f64::max);
+
+                (breakdown.iter().any(|result| is_defaced(result, *min_confidence)), max_confidence)
+            }
+            AggregationPolicy::WeightedVote { weights, threshold } => {
+                let mut total = 0.0;
+
+                for result in &breakdown {
+                    let weight = weights.get(result.comparator()).copied().unwrap_or(DEFAULT_WEIGHT);
+
+                    let vote = weight * result.result().confidence();
+
+                    match result.result().verdict() {
+                        Verdict::Defaced => { total += vote; }
+                        Verdict::NotDefaced => { total -= vote; }
+                        Verdict::MaybeDefaced => {}
+                    }
+                }
+
+                (total >= *threshold, total)
+            }
+            AggregationPolicy::Quorum { quorum, min_confidence } => {
+                let votes = breakdown.iter()
+                    .filter(|result| is_defaced(result, *min_confidence))
+                    .count();
+
+                (votes >= *quorum as usize, votes as f64)
+            }
+        };
+
+        let verdict = if defaced { Verdict::Defaced } else { Verdict::NotDefaced };
+
+        AggregatedResult::new(String::from(self.name()), verdict, score, breakdown)
+    }
+}
+
+fn is_defaced(result: &ComparatorResult, min_confidence: f64) -> bool {
+    result.result().verdict() == Verdict::Defaced && result.result().confidence() >= min_confidence
+}
+
+impl AggregatedResult {
+    pub fn new(policy: String, verdict: Verdict, score: f64, breakdown: Vec<ComparatorResult>) -> Self {
+        Self { policy, verdict, score, breakdown }
+    }
+
+    pub fn policy(&self) -> &str {
+        &self.policy
+    }
+    pub fn verdict(&self) -> Verdict {
+        self.verdict
+    }
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+    pub fn breakdown(&self) -> &Vec<ComparatorResult> {
+        &self.breakdown
+    }
+
+    pub fn is_defaced(&self) -> bool {
+        self.verdict == Verdict::Defaced
+    }
+
+    ///The results of the comparators that said the page was defaced
+    pub fn defaced_results(&self) -> Vec<&ComparatorResult> {
+        self.breakdown.iter()
+            .filter(|result| result.result().verdict() == Verdict::Defaced)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod aggregation_tests {
+    use crate::comparators::{ComparatorResult, CompareResult};
+    use crate::comparators::aggregation::AggregationPolicy;
+
+    fn breakdown() -> Vec<ComparatorResult> {
+        vec![ComparatorResult::new("Checksum", CompareResult::maybe_defaced("Changed")),
+             ComparatorResult::new("Domain", CompareResult::defaced(0.75, String::from("New domain"))),
+             ComparatorResult::new("Text", CompareResult::defaced(0.5, String::from("Text changed"))),
+             ComparatorResult::new("Tree", CompareResult::not_defaced(1.0, "Same structure"))]
+    }
+
+    #[test]
+    fn test_any_defaced() {
+        let policy = AggregationPolicy::from_config("policy = \"any_defaced\"\nmin_confidence = 0.6").unwrap();
+
+        let result = policy.aggregate(breakdown());
+
+        assert!(result.is_defaced());
+        assert_eq!(result.score(), 0.75);
+        assert_eq!(result.breakdown().len(), 4);
+
+        let policy = AggregationPolicy::from_config("policy = \"any_defaced\"\nmin_confidence = 0.8").unwrap();
+
+        assert!(!policy.aggregate(breakdown()).is_defaced());
+    }
+
+    #[test]
+    fn test_weighted_vote() {
+        let policy = AggregationPolicy::from_config("policy = \"weighted_vote\"\nthreshold = 0.5\n\
+        [weights]\nDomain = 2\nTree = 0.5").unwrap();
+
+        //2 * 0.75 + 1 * 0.5 - 0.5 * 1
+        let result = policy.aggregate(breakdown());
+
+        assert_eq!(result.score(), 1.5);
+        assert!(result.is_defaced());
+
+        let policy = AggregationPolicy::from_config("policy = \"weighted_vote\"\nthreshold = 0.5\n\
+        [weights]\nTree = 2").unwrap();
+
+        assert!(!policy.aggregate(breakdown()).is_defaced());
+    }
+
+    #[test]
+    fn test_quorum() {
+        let policy = AggregationPolicy::from_config("policy = \"quorum\"\nquorum = 2").unwrap();
+
+        assert!(policy.aggregate(breakdown()).is_defaced());
+
+        let policy = AggregationPolicy::from_config("policy = \"quorum\"\nquorum = 3").unwrap();
+
+        assert!(!policy.aggregate(breakdown()).is_defaced());
+
+        assert!(AggregationPolicy::from_config("policy = \"majority\"").is_err());
+    }
+}
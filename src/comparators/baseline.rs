@@ -0,0 +1,431 @@
+use std::fmt::{Display, Formatter};
+use serde_json::{json, Value};
+use crate::databases::{TrackedPage, TrackedPageType};
+
+/*
+This mod models what is normal for a dynamic page.
+While the page is analysed we compare every pair of samples, and instead of reducing those
+differences to a single average we keep their distribution (mean, standard deviation and percentiles),
+so the threshold above which the page is considered defaced can be chosen relative to it.
+ */
+
+//The percentiles of the differences that are kept in the model
+const STORED_PERCENTILES: [f64; 8] = [0.0, 25.0, 50.0, 75.0, 90.0, 95.0, 99.0, 100.0];
+
+///How the threshold is calculated from the model
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ThresholdPolicy {
+    //The mean plus this amount of standard deviations
+    StdDevs(f64),
+    //This percentile (0 to 100) of the differences seen during the analysis
+    Percentile(f64),
+    //A fixed threshold, used for the pages that were analysed before the model existed
+    Fixed(f64),
+}
+
+pub const DEFAULT_THRESHOLD_POLICY: ThresholdPolicy = ThresholdPolicy::StdDevs(3.0);
+
+//How much each check moves an adaptive baseline, small so a slow defacement can't teach the model
+pub const ADAPTIVE_BASELINE_WEIGHT: f64 = 0.05;
+
+///The distribution of the differences between the samples of a page
+#[derive(PartialEq, Debug, Clone)]
+pub struct BaselineModel {
+    policy: ThresholdPolicy,
+    mean: f64,
+    std_dev: f64,
+    //(percentile, difference) sorted by percentile
+    percentiles: Vec<(f64, f64)>,
+    //The amount of differences the model was built from, 0 when the page was not analysed yet
+    sample_count: u32,
+    //The amount of checks that were fed into the model since it was built (Adaptive baselines only)
+    adaptive_updates: u32,
+}
+
+impl ThresholdPolicy {
+    ///
+    /// Parse a policy written as "3sd" (standard deviations), "p95" (percentile) or "12.5" (fixed threshold)
+    pub fn parse(policy: &str) -> Result<Self, String> {
+        let policy = policy.trim().to_ascii_lowercase();
+
+        let parsed = if let Some(std_devs) = policy.strip_suffix("sd") {
+            std_devs.trim().parse::<f64>().map(ThresholdPolicy::StdDevs)
+        } else if let Some(percentile) = policy.strip_prefix('p') {
+            percentile.trim().parse::<f64>().map(ThresholdPolicy::Percentile)
+        } else {
+            policy.parse::<f64>().map(ThresholdPolicy::Fixed)
+        };
+
+        match parsed {
+            Ok(ThresholdPolicy::Percentile(percentile)) if !(0.0..=100.0).contains(&percentile) => {
+                Err(format!("The percentile {} must be between 0 and 100", percentile))
+            }
+            Ok(policy) => { Ok(policy) }
+            Err(e) => { Err(format!("Invalid threshold policy {}. {}", policy, e)) }
+        }
+    }
+}
+
+pub fn threshold_policy_to_str(policy: &ThresholdPolicy) -> String {
+    match policy {
+        ThresholdPolicy::StdDevs(std_devs) => { format!("{}sd", std_devs) }
+        ThresholdPolicy::Percentile(percentile) => { format!("p{}", percentile) }
+        ThresholdPolicy::Fixed(threshold) => { format!("{}", threshold) }
+    }
+}
+
+impl Display for ThresholdPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThresholdPolicy::StdDevs(std_devs) => { write!(f, "mean + {} standard deviations", std_devs) }
+            ThresholdPolicy::Percentile(percentile) => { write!(f, "percentile {}", percentile) }
+            ThresholdPolicy::Fixed(threshold) => { write!(f, "fixed at {}", threshold) }
+        }
+    }
+}
+
+///The value at the given percentile of sorted values, interpolating between the closest ones
+fn percentile_of(sorted: &[f64], percentile: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let rank = percentile.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f64;
+
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+impl BaselineModel {
+    ///A model for a page that was not analysed yet
+    pub fn unlearned(policy: ThresholdPolicy) -> Self {
+        Self { policy, mean: 0.0, std_dev: 0.0, percentiles: Vec::new(), sample_count: 0, adaptive_updates: 0 }
+    }
+
+    ///
+    /// Build the model from the differences seen between the samples of the page
+    pub fn from_differences(differences: &[f64], policy: ThresholdPolicy) -> Option<Self> {
+        if differences.is_empty() {
+            return None;
+        }
+
+        let mut sorted = differences.to_vec();
+
+        sorted.sort_by(|diff, diff_2| diff.total_cmp(diff_2));
+
+        let count = sorted.len() as f64;
+
+        let mean = sorted.iter().sum::<f64>() / count;
+
+        let variance = sorted.iter().map(|diff| (diff - mean).powi(2)).sum::<f64>() / count;
+
+        let percentiles = STORED_PERCENTILES.iter()
+            .map(|percentile| (*percentile, percentile_of(&sorted, *percentile)))
+            .collect();
+
+        Some(Self {
+            policy,
+            mean,
+            std_dev: variance.sqrt(),
+            percentiles,
+            sample_count: sorted.len() as u32,
+            adaptive_updates: 0,
+        })
+    }
+
+    ///
+    /// Compare every pair of samples with the difference function and build the model from the results
+    pub fn learn<S, F>(samples: &[S], difference: F, policy: ThresholdPolicy) -> Option<Self>
+        where F: Fn(&S, &S) -> f64 {
+        let mut differences = Vec::new();
+
+        for (sample_ind, sample) in samples.iter().enumerate() {
+            for (sample_ind_2, sample_2) in samples.iter().enumerate() {
+                if sample_ind == sample_ind_2 { continue; }
+
+                differences.push(difference(sample, sample_2));
+            }
+        }
+
+        Self::from_differences(&differences, policy)
+    }
+
+    ///
+    /// Parse the model from the database, pages that were analysed before the model existed
+    /// only stored the threshold, which is kept as a fixed threshold
+    pub fn parse(data: &str) -> Result<Self, String> {
+        if let Ok(threshold) = data.trim().parse::<f64>() {
+            return Ok(Self::unlearned(ThresholdPolicy::Fixed(threshold)));
+        }
+
+        let value: Value = match serde_json::from_str(data) {
+            Ok(value) => { value }
+            Err(e) => { return Err(e.to_string()); }
+        };
+
+        if !value.is_object() {
+            return Err(format!("{} is not a baseline model", data));
+        }
+
+        let policy = match value["policy"].as_str() {
+            Some(policy) => { ThresholdPolicy::parse(policy)? }
+            None => { DEFAULT_THRESHOLD_POLICY }
+        };
+
+        let percentiles = value["percentiles"].as_array()
+            .map(|percentiles| {
+                percentiles.iter()
+                    .filter_map(|entry| Some((entry[0].as_f64()?, entry[1].as_f64()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            policy,
+            mean: value["mean"].as_f64().unwrap_or(0.0),
+            std_dev: value["std_dev"].as_f64().unwrap_or(0.0),
+            percentiles,
+            sample_count: value["sample_count"].as_u64().unwrap_or(0) as u32,
+            adaptive_updates: value["adaptive_updates"].as_u64().unwrap_or(0) as u32,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        json!({
+            "policy": threshold_policy_to_str(&self.policy),
+            "mean": self.mean,
+            "std_dev": self.std_dev,
+            "percentiles": self.percentiles.iter().map(|(percentile, diff)| json!([percentile, diff])).collect::<Vec<Value>>(),
+            "sample_count": self.sample_count,
+            "adaptive_updates": self.adaptive_updates,
+        }).to_string()
+    }
+
+    ///
+    /// Feed the difference seen in a check into the model as an exponentially weighted moving average,
+    /// the weight is how much the new difference counts (0 to 1).
+    /// The percentiles are moved along with the mean, as we can't know the shape of the distribution from one value
+    pub fn update(&mut self, difference: f64, weight: f64) {
+        let weight = weight.clamp(0.0, 1.0);
+
+        let deviation = difference - self.mean;
+
+        let mean = self.mean + weight * deviation;
+
+        self.std_dev = ((1.0 - weight) * (self.std_dev.powi(2) + weight * deviation.powi(2))).sqrt();
+
+        for (_, percentile_diff) in &mut self.percentiles {
+            *percentile_diff = (*percentile_diff + mean - self.mean).max(0.0);
+        }
+
+        self.mean = mean;
+        self.adaptive_updates += 1;
+    }
+
+    ///The value at the given percentile of the differences, interpolated between the stored percentiles
+    pub fn percentile(&self, percentile: f64) -> f64 {
+        let percentile = percentile.clamp(0.0, 100.0);
+
+        for window in self.percentiles.windows(2) {
+            let (lower, lower_diff) = window[0];
+            let (upper, upper_diff) = window[1];
+
+            if percentile >= lower && percentile <= upper {
+                if upper == lower {
+                    return upper_diff;
+                }
+
+                return lower_diff + (upper_diff - lower_diff) * (percentile - lower) / (upper - lower);
+            }
+        }
+
+        self.percentiles.last().map(|(_, diff)| *diff).unwrap_or(0.0)
+    }
+
+    ///
+    /// The difference above which the page is considered defaced.
+    /// A page that was not analysed yet has a threshold of 0, so any change is flagged
+    pub fn threshold(&self) -> f64 {
+        match self.policy {
+            ThresholdPolicy::Fixed(threshold) => { threshold }
+            _ if self.sample_count == 0 => { 0.0 }
+            ThresholdPolicy::StdDevs(std_devs) => { self.mean + std_devs * self.std_dev }
+            ThresholdPolicy::Percentile(percentile) => { self.percentile(percentile) }
+        }
+    }
+
+    pub fn policy(&self) -> ThresholdPolicy {
+        self.policy
+    }
+    pub fn set_policy(&mut self, policy: ThresholdPolicy) {
+        self.policy = policy;
+    }
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+    pub fn std_dev(&self) -> f64 {
+        self.std_dev
+    }
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+    pub fn adaptive_updates(&self) -> u32 {
+        self.adaptive_updates
+    }
+}
+
+impl Display for BaselineModel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.sample_count == 0 {
+            return write!(f, "threshold {:.2} ({})", self.threshold(), self.policy);
+        }
+
+        write!(f, "threshold {:.2} ({}), mean {:.2}, std dev {:.2}, p50 {:.2}, p95 {:.2}, max {:.2} from {} differences",
+               self.threshold(), self.policy, self.mean, self.std_dev, self.percentile(50.0),
+               self.percentile(95.0), self.percentile(100.0), self.sample_count)?;
+
+        if self.adaptive_updates > 0 {
+            write!(f, ", adapted by {} checks", self.adaptive_updates)?;
+        }
+
+        Ok(())
+    }
+}
+
+///The threshold policy chosen for the page, the default one when the page is not dynamic
+pub fn threshold_policy_for(page: &TrackedPage) -> ThresholdPolicy {
+    match page.tracked_page_type() {
+        TrackedPageType::Dynamic(model) => { model.policy() }
+        TrackedPageType::Static => { DEFAULT_THRESHOLD_POLICY }
+    }
+}
+
+///
+/// Build the model of a comparator from the samples of the page, using the page's threshold policy.
+/// Returns the model to store as the comparator data of the page
+pub fn learn_comparator_model<S, F>(page: &TrackedPage, samples: &[S], difference: F) -> Option<String>
+    where F: Fn(&S, &S) -> f64 {
+    BaselineModel::learn(samples, difference, threshold_policy_for(page)).map(|model| model.to_json())
+}
+
+///
+/// Feed the difference seen in a check into the model the comparator learned for the page.
+/// Returns the model to store as the comparator data of the page, None when there is no learned model to adapt
+pub fn adapt_comparator_model(page: &TrackedPage, comparator: &str, difference: f64, weight: f64) -> Option<String> {
+    let mut model = BaselineModel::parse(page.comparator_data(comparator)?).ok()?;
+
+    //Old models that only stored a threshold have no distribution to move
+    if model.sample_count() == 0 {
+        return None;
+    }
+
+    model.update(difference, weight);
+
+    Some(model.to_json())
+}
+
+///The model a comparator learned for the page, stored in its comparator data
+pub fn learned_comparator_model(page: &TrackedPage, comparator: &str) -> Option<BaselineModel> {
+    let mut model = BaselineModel::parse(page.comparator_data(comparator)?).ok()?;
+
+    //The policy chosen for the page applies to every comparator, old models that only stored a threshold keep it
+    if model.sample_count() > 0 {
+        model.set_policy(threshold_policy_for(page));
+    }
+
+    Some(model)
+}
+
+#[cfg(test)]
+mod baseline_tests {
+    use crate::comparators::baseline::{adapt_comparator_model, BaselineModel, learned_comparator_model, ThresholdPolicy};
+    use crate::databases::{TrackedPage, TrackedPageType};
+
+    #[test]
+    fn test_model_statistics() {
+        let model = BaselineModel::from_differences(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0],
+                                                    ThresholdPolicy::StdDevs(2.0)).unwrap();
+
+        assert_eq!(model.mean(), 5.0);
+        assert_eq!(model.std_dev(), 2.0);
+        assert_eq!(model.threshold(), 9.0);
+        assert_eq!(model.percentile(0.0), 2.0);
+        assert_eq!(model.percentile(100.0), 9.0);
+        assert_eq!(model.percentile(50.0), 4.5);
+
+        let mut model = model;
+
+        model.set_policy(ThresholdPolicy::Percentile(100.0));
+
+        assert_eq!(model.threshold(), 9.0);
+    }
+
+    #[test]
+    fn test_model_storage() {
+        let model = BaselineModel::learn(&[1.0, 3.0, 6.0], |a: &f64, b: &f64| (a - b).abs(),
+                                         ThresholdPolicy::Percentile(90.0)).unwrap();
+
+        assert_eq!(model.sample_count(), 6);
+        assert_eq!(BaselineModel::parse(model.to_json().as_str()).unwrap(), model);
+
+        //Pages analysed before the model existed only stored the threshold
+        let legacy = BaselineModel::parse("12.5").unwrap();
+
+        assert_eq!(legacy.policy(), ThresholdPolicy::Fixed(12.5));
+        assert_eq!(legacy.threshold(), 12.5);
+    }
+
+    #[test]
+    fn test_adaptive_update() {
+        let mut model = BaselineModel::from_differences(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0],
+                                                        ThresholdPolicy::StdDevs(2.0)).unwrap();
+
+        //A check that matches the mean only narrows the distribution
+        model.update(5.0, 0.5);
+
+        assert_eq!(model.mean(), 5.0);
+        assert_eq!(model.std_dev(), 2.0_f64.sqrt());
+
+        //The page drifting makes the model follow it slowly
+        model.update(15.0, 0.1);
+
+        assert_eq!(model.mean(), 6.0);
+        assert_eq!(model.percentile(100.0), 10.0);
+        assert_eq!(model.adaptive_updates(), 2);
+        assert_eq!(BaselineModel::parse(model.to_json().as_str()).unwrap().adaptive_updates(), 2);
+    }
+
+    #[test]
+    fn test_adapt_comparator_model() {
+        let mut page = TrackedPage::new(1, String::from("https://example.org"), 1, 0, 0, 0, 0, 5, false,
+                                        TrackedPageType::Dynamic(BaselineModel::unlearned(ThresholdPolicy::StdDevs(2.0))));
+
+        let model = BaselineModel::from_differences(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0],
+                                                    ThresholdPolicy::StdDevs(2.0)).unwrap();
+
+        page.set_comparator_data("Tree", model.to_json());
+        page.set_comparator_data("Legacy", String::from("12.5"));
+
+        page.set_comparator_data("Tree", adapt_comparator_model(&page, "Tree", 15.0, 0.1).unwrap());
+
+        let adapted = learned_comparator_model(&page, "Tree").unwrap();
+
+        assert_eq!(adapted.mean(), 6.0);
+        assert_eq!(adapted.adaptive_updates(), 1);
+
+        //Fixed thresholds and comparators that learned nothing are left alone
+        assert_eq!(adapt_comparator_model(&page, "Legacy", 15.0, 0.1), None);
+        assert_eq!(adapt_comparator_model(&page, "Text", 15.0, 0.1), None);
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!(ThresholdPolicy::parse("3sd"), Ok(ThresholdPolicy::StdDevs(3.0)));
+        assert_eq!(ThresholdPolicy::parse("p95"), Ok(ThresholdPolicy::Percentile(95.0)));
+        assert_eq!(ThresholdPolicy::parse("12.5"), Ok(ThresholdPolicy::Fixed(12.5)));
+        assert!(ThresholdPolicy::parse("p150").is_err());
+        assert!(ThresholdPolicy::parse("lots").is_err());
+    }
+}
@@ -1,5 +1,4 @@
 use crate::comparators::{Comparator, CompareResult};
-use crate::comparators::CompareResult::{Defaced, MaybeDefaced, NotDefaced};
 use crate::databases::{TrackedPage, TrackedPageType};
 
 /**
@@ -39,17 +38,17 @@ impl Comparator<String> for ChecksumComparator {
         return match page.tracked_page_type() {
             TrackedPageType::Static => {
                 if comp_doms(dom_1, dom_2) {
-                    NotDefaced
+                    CompareResult::not_defaced(1.0, "The page is identical to the stored page")
                 } else {
-                    Defaced
+                    CompareResult::defaced(1.0, String::from("The checksum of the static page changed"))
                 }
             }
             TrackedPageType::Dynamic(_) => {
                 if comp_doms(dom_1, dom_2) {
                     //If the docs are equal then it can't have been defaced.
-                    NotDefaced
+                    CompareResult::not_defaced(1.0, "The page is identical to the stored page")
                 } else {
-                    MaybeDefaced
+                    CompareResult::maybe_defaced("The checksum of the dynamic page changed")
                 }
             }
         };
@@ -1,30 +1,41 @@
 use std::time::Duration;
-use difference::Changeset;
+use difference::{Changeset, Difference};
 use log::{debug, error, trace, warn};
 use tokio::time;
-use crate::comparators::{Comparator, CompareResult};
-use crate::comparators::CompareResult::{Defaced, MaybeDefaced, NotDefaced};
+use crate::comparators::{Comparator, CompareResult, confidence_above_threshold};
+use crate::comparators::baseline::{BaselineModel, threshold_policy_for};
+use crate::comparators::tree_comparator::learn_volatility_mask;
 use crate::databases::{TrackedPage, TrackedPageType};
+use crate::normalisation::{mask_volatile_regions, Normaliser};
 use crate::parsers::Parser;
 
-const ANALYSE_TIME_INTERVAL: Duration = Duration::from_millis(1 * 1000);
-//2 times per minute (every 30 secs) for 5 minutes
-const DYNAMIC_CHECK_COUNT: u32 = 2 * 5;
+///Collects samples of the page over a period of time (As configured for the page) and builds the model
+///Of the differences between them, from which we get the threshold above which the page is considered defaced.
+///The samples are normalised before they are compared, so the volatile values don't inflate the threshold.
+///The regions that changed between the samples are learned as the volatility mask of the page, without the selectors
+///The user removed from it and along with the ones they pinned to it, and removed from the samples before building the model.
+///Returns the model, the volatility mask and the masked samples, so other comparators can learn from them
+pub async fn analyse_dynamic_page<T>(parser: &T, normaliser: &Normaliser, page: &TrackedPage)
+                                     -> Result<(BaselineModel, Vec<String>, Vec<String>), String>
+    where T: Parser<String> + ?Sized {
+    //The mask is learned again from scratch, so the previous one can't hide the regions from the samples
+    let mut unmasked_page = page.clone();
 
-pub async fn analyse_dynamic_page<T>(parser: &T, page: &TrackedPage) -> Result<f64, String>
-    where T: Parser<String> {
-    let mut time_period = time::interval(ANALYSE_TIME_INTERVAL);
+    unmasked_page.set_volatility_mask(Vec::new());
+
+    //The interval can't be 0, pages that were stored with it take the samples as fast as possible
+    let mut time_period = time::interval(Duration::from_millis(page.sample_interval().max(1) as u64));
 
     let mut checks = 0;
 
-    let mut received_doms = Vec::with_capacity(DYNAMIC_CHECK_COUNT as usize);
+    let mut received_doms = Vec::with_capacity(page.sample_count() as usize);
 
-    while checks < DYNAMIC_CHECK_COUNT {
+    while checks < page.sample_count() {
         let dom_res = parser.parse_page(page);
 
         match dom_res {
             Ok(dom) => {
-                received_doms.push(dom);
+                received_doms.push(normaliser.normalise(&unmasked_page, dom.as_str()));
             }
             Err(e) => {
                 error!("Failed to read dom for page {} with ID {}. {}", page.page_url(), page.page_id(), e);
@@ -35,30 +46,34 @@ pub async fn analyse_dynamic_page<T>(parser: &T, page: &TrackedPage) -> Result<f
         checks += 1;
     }
 
-    let mut diff_threshold_avg: f64 = 0.0;
-    let mut comparisons: u32 = 0;
-
-    for dom_ind in 0..received_doms.len() {
-        for dom_ind_2 in 0..received_doms.len() {
-            if dom_ind == dom_ind_2 { continue; }
+    let mut volatility_mask = learn_volatility_mask(&received_doms);
 
-            let diff = compare_dom_with_diff(received_doms[dom_ind].as_str(),
-                                             received_doms[dom_ind_2].as_str()) as f64;
+    volatility_mask.retain(|selector| !page.excluded_volatility_mask().contains(selector));
 
-            diff_threshold_avg += diff;
-            comparisons += 1;
+    for selector in page.pinned_volatility_mask() {
+        if !volatility_mask.contains(selector) {
+            volatility_mask.push(selector.clone());
         }
     }
 
-    diff_threshold_avg /= comparisons as f64;
-    //Give some leeway to the defacement calculator as to not register too many false positives
-    diff_threshold_avg *= 1.3;
+    let received_doms: Vec<String> = received_doms.iter()
+        .map(|dom| mask_volatile_regions(&volatility_mask, dom))
+        .collect();
 
-    warn!("After calculating the difference between 10 samples of the website,\
-         the difference threshold for the page {} with ID {} is {}",
-             page.page_url(), page.page_id(), diff_threshold_avg);
+    let model = match BaselineModel::learn(&received_doms, |dom, dom_2| compare_dom_with_diff(dom, dom_2),
+                                           threshold_policy_for(page)) {
+        Some(model) => { model }
+        None => {
+            return Err(format!("Could not collect enough samples of page {} with ID {}",
+                               page.page_url(), page.page_id()));
+        }
+    };
 
-    return Ok(diff_threshold_avg);
+    warn!("After calculating the difference between {} samples of the website,\
+         the baseline for the page {} with ID {} is {}, with {} volatile regions masked",
+             received_doms.len(), page.page_url(), page.page_id(), model, volatility_mask.len());
+
+    return Ok((model, volatility_mask, received_doms));
 }
 
 
@@ -77,6 +92,163 @@ pub fn compare_dom_with_diff(dom: &str, dom_2: &str) -> f64 {
     distance_percent
 }
 
+///A summary of the lines that changed between two doms, to show the users what changed in the page
+#[derive(PartialEq, Debug, Clone)]
+pub struct DiffSummary {
+    changed_percent: f64,
+    //The first added and removed lines, up to the requested amount
+    added: Vec<String>,
+    removed: Vec<String>,
+    //The total amount of added and removed lines
+    added_count: usize,
+    removed_count: usize,
+}
+
+///
+/// Summarise the lines that changed from the stored dom to the current one, keeping at most max_lines
+/// of the added and of the removed lines
+pub fn summarise_diff(dom: &str, dom_2: &str, max_lines: usize) -> DiffSummary {
+    let changes = Changeset::new(dom, dom_2, "\n");
+
+    let mut summary = DiffSummary {
+        changed_percent: compare_dom_with_diff(dom, dom_2),
+        added: Vec::new(),
+        removed: Vec::new(),
+        added_count: 0,
+        removed_count: 0,
+    };
+
+    for difference in &changes.diffs {
+        let (lines, kept, count) = match difference {
+            Difference::Same(_) => { continue; }
+            Difference::Add(added) => { (added, &mut summary.added, &mut summary.added_count) }
+            Difference::Rem(removed) => { (removed, &mut summary.removed, &mut summary.removed_count) }
+        };
+
+        for line in lines.split('\n').map(str::trim).filter(|line| !line.is_empty()) {
+            *count += 1;
+
+            if kept.len() < max_lines {
+                kept.push(String::from(line));
+            }
+        }
+    }
+
+    summary
+}
+
+impl DiffSummary {
+    pub fn changed_percent(&self) -> f64 {
+        self.changed_percent
+    }
+    pub fn added(&self) -> &Vec<String> {
+        &self.added
+    }
+    pub fn removed(&self) -> &Vec<String> {
+        &self.removed
+    }
+    pub fn added_count(&self) -> usize {
+        self.added_count
+    }
+    pub fn removed_count(&self) -> usize {
+        self.removed_count
+    }
+}
+
+///Whether a line of a diff hunk was kept, added to or removed from the page
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+///A region of the page that changed, with the lines around it, as in a unified diff
+#[derive(PartialEq, Debug, Clone)]
+pub struct DiffHunk {
+    //The lines of the stored and of the current dom where the hunk starts, counting from 1
+    old_start: usize,
+    new_start: usize,
+    lines: Vec<(DiffLineKind, String)>,
+}
+
+///
+/// The regions that changed from the stored dom to the current one, each with up to context unchanged lines
+/// before and after it. Regions closer than that are merged into the same hunk
+pub fn diff_hunks(dom: &str, dom_2: &str, context: usize) -> Vec<DiffHunk> {
+    let changes = Changeset::new(dom, dom_2, "\n");
+
+    //Every line of both doms with the line it is at in the stored and in the current dom
+    let mut lines = Vec::new();
+
+    let (mut old_line, mut new_line) = (1, 1);
+
+    for difference in &changes.diffs {
+        let (kind, text) = match difference {
+            Difference::Same(text) => { (DiffLineKind::Context, text) }
+            Difference::Add(text) => { (DiffLineKind::Added, text) }
+            Difference::Rem(text) => { (DiffLineKind::Removed, text) }
+        };
+
+        for line in text.split('\n') {
+            lines.push((kind, old_line, new_line, line));
+
+            match kind {
+                DiffLineKind::Context => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+                DiffLineKind::Added => { new_line += 1; }
+                DiffLineKind::Removed => { old_line += 1; }
+            }
+        }
+    }
+
+    let changed: Vec<usize> = lines.iter().enumerate()
+        .filter(|(_, (kind, _, _, _))| *kind != DiffLineKind::Context)
+        .map(|(index, _)| index)
+        .collect();
+
+    let mut hunks = Vec::new();
+
+    let mut current = 0;
+
+    while current < changed.len() {
+        let start = changed[current].saturating_sub(context);
+
+        let mut end = changed[current];
+
+        while current + 1 < changed.len() && changed[current + 1] <= end + 2 * context + 1 {
+            current += 1;
+            end = changed[current];
+        }
+
+        let end = (end + context).min(lines.len() - 1);
+
+        hunks.push(DiffHunk {
+            old_start: lines[start].1,
+            new_start: lines[start].2,
+            lines: lines[start..=end].iter().map(|(kind, _, _, line)| (*kind, String::from(*line))).collect(),
+        });
+
+        current += 1;
+    }
+
+    hunks
+}
+
+impl DiffHunk {
+    pub fn old_start(&self) -> usize {
+        self.old_start
+    }
+    pub fn new_start(&self) -> usize {
+        self.new_start
+    }
+    pub fn lines(&self) -> &Vec<(DiffLineKind, String)> {
+        &self.lines
+    }
+}
+
 pub struct DiffComparator {}
 
 impl DiffComparator {
@@ -91,25 +263,76 @@ impl Comparator<String> for DiffComparator {
     }
 
     fn compare_between(&self, page: &TrackedPage, dom_1: &String, dom_2: &String) -> CompareResult {
-        return match page.tracked_page_type() {
+        let diff = compare_dom_with_diff(dom_1, dom_2);
+
+        let result = match page.tracked_page_type() {
             TrackedPageType::Static => {
-                if compare_dom_with_diff(dom_1, dom_2) > 0.0 {
-                    Defaced
+                if diff > 0.0 {
+                    CompareResult::defaced(1.0, format!("{:.2}% of the static page changed", diff))
                 } else {
-                    NotDefaced
+                    CompareResult::not_defaced(1.0, "The static page did not change")
                 }
             }
-            TrackedPageType::Dynamic(threshold) => {
-                let diff = compare_dom_with_diff(dom_1, dom_2);
+            TrackedPageType::Dynamic(model) => {
+                let threshold = model.threshold();
 
-                trace!("Distance calculated is {}, needs to be below {}", diff, *threshold);
+                trace!("Distance calculated is {}, needs to be below {}", diff, threshold);
 
-                if diff > *threshold {
-                    Defaced
+                if diff > threshold {
+                    CompareResult::defaced(confidence_above_threshold(diff, threshold),
+                                           format!("{:.2}% of the page changed, above the threshold of {:.2}%", diff, threshold))
                 } else {
-                    MaybeDefaced
+                    CompareResult::maybe_defaced("The changes are below the threshold of the page")
                 }
             }
         };
+
+        result.with_score(diff)
+    }
+}
+
+#[cfg(test)]
+mod diff_comparator_tests {
+    use crate::comparators::diff_comparator::{diff_hunks, DiffLineKind, summarise_diff};
+
+    #[test]
+    fn test_summarise_diff() {
+        let stored = "<html>\n<h1>Shop</h1>\n<p>Welcome</p>\n</html>";
+        let current = "<html>\n<h1>Hacked by someone</h1>\n<p>Welcome</p>\n<marquee>owned</marquee>\n</html>";
+
+        let summary = summarise_diff(stored, current, 1);
+
+        assert_eq!(summary.removed(), &vec![String::from("<h1>Shop</h1>")]);
+        assert_eq!(summary.added(), &vec![String::from("<h1>Hacked by someone</h1>")]);
+        assert_eq!(summary.added_count(), 2);
+        assert_eq!(summary.removed_count(), 1);
+        assert!(summary.changed_percent() > 0.0);
+
+        assert_eq!(summarise_diff(stored, stored, 5).added_count(), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_diff_hunks() {
+        let stored: Vec<String> = (1..=20).map(|line| format!("<p>{}</p>", line)).collect();
+
+        let mut current = stored.clone();
+
+        current[2] = String::from("<h1>Hacked</h1>");
+        current.insert(15, String::from("<marquee>owned</marquee>"));
+
+        let hunks = diff_hunks(stored.join("\n").as_str(), current.join("\n").as_str(), 2);
+
+        //The changes are too far apart to share a hunk
+        assert_eq!(hunks.len(), 2);
+
+        assert_eq!((hunks[0].old_start(), hunks[0].new_start()), (1, 1));
+        assert_eq!(hunks[0].lines().len(), 6);
+        assert!(hunks[0].lines().contains(&(DiffLineKind::Removed, String::from("<p>3</p>"))));
+        assert!(hunks[0].lines().contains(&(DiffLineKind::Added, String::from("<h1>Hacked</h1>"))));
+
+        assert_eq!((hunks[1].old_start(), hunks[1].new_start()), (14, 14));
+        assert_eq!(hunks[1].lines()[2], (DiffLineKind::Added, String::from("<marquee>owned</marquee>")));
+
+        assert!(diff_hunks(stored.join("\n").as_str(), stored.join("\n").as_str(), 2).is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeSet;
+use log::{error, warn};
+use scraper::{Html, Selector};
+use url::Url;
+use crate::comparators::{Comparator, CompareResult};
+use crate::databases::TrackedPage;
+
+/*
+This mod looks at the domains the page links to and loads resources from.
+Injected SEO spam and malicious redirects usually show up as new external domains
+in the links, scripts, iframes and stylesheets of the page, even when the visible
+page barely changes.
+ */
+
+//A new domain is strong evidence, but legitimate widgets and CDNs are also added to pages
+const NEW_DOMAIN_CONFIDENCE: f64 = 0.75;
+
+//The elements and the attribute that holds the referenced URL
+const REFERENCING_ELEMENTS: [(&str, &str); 4] = [("a", "href"), ("script", "src"),
+    ("iframe", "src"), ("link", "href")];
+
+///
+/// Extract the hosts of every http(s) URL referenced by the page, relative URLs are
+/// resolved against the page URL
+pub fn extract_referenced_domains(page_url: &str, dom: &str) -> BTreeSet<String> {
+    let base = Url::parse(page_url).ok();
+
+    let document = Html::parse_document(dom);
+
+    let mut domains = BTreeSet::new();
+
+    for (element, attribute) in REFERENCING_ELEMENTS {
+        let selector = Selector::parse(format!("{}[{}]", element, attribute).as_str()).unwrap();
+
+        for found in document.select(&selector) {
+            let reference = found.value().attr(attribute).unwrap().trim();
+
+            let url = match &base {
+                Some(base) => { base.join(reference) }
+                None => { Url::parse(reference) }
+            };
+
+            if let Ok(url) = url {
+                if url.scheme() != "http" && url.scheme() != "https" {
+                    continue;
+                }
+
+                if let Some(host) = url.host_str() {
+                    domains.insert(host.to_ascii_lowercase());
+                }
+            }
+        }
+    }
+
+    domains
+}
+
+fn matches_domain(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(format!(".{}", domain).as_str())
+}
+
+///A domain is first party when it is the page's own host or one of its subdomains
+fn is_first_party(page_host: &str, host: &str) -> bool {
+    let site = page_host.strip_prefix("www.").unwrap_or(page_host);
+
+    matches_domain(host, site)
+}
+
+///
+/// Get the third party domains that are referenced by the current dom but were not referenced by
+/// the stored dom and are not in the page's allow list
+pub fn find_new_domains(page: &TrackedPage, stored_dom: &str, current_dom: &str) -> Vec<String> {
+    let page_host = match Url::parse(page.page_url()) {
+        Ok(url) => { url.host_str().map(|host| host.to_ascii_lowercase()).unwrap_or_default() }
+        Err(e) => {
+            error!("Failed to parse the URL of page {} with ID {}. {}", page.page_url(), page.page_id(), e);
+
+            String::new()
+        }
+    };
+
+    let allowed: Vec<String> = page.allowed_domains().iter()
+        .map(|domain| domain.trim().to_ascii_lowercase())
+        .collect();
+
+    let stored_domains = extract_referenced_domains(page.page_url(), stored_dom);
+
+    extract_referenced_domains(page.page_url(), current_dom).into_iter()
+        .filter(|domain| !stored_domains.contains(domain))
+        .filter(|domain| !is_first_party(page_host.as_str(), domain))
+        .filter(|domain| !allowed.iter().any(|allowed| matches_domain(domain, allowed)))
+        .collect()
+}
+
+#[derive(Default)]
+pub struct DomainComparator {}
+
+impl DomainComparator {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Comparator<String> for DomainComparator {
+    fn name(&self) -> &str {
+        "Domain"
+    }
+
+    fn compare_between(&self, page: &TrackedPage, dom_1: &String, dom_2: &String) -> CompareResult {
+        let new_domains = find_new_domains(page, dom_1, dom_2);
+
+        if new_domains.is_empty() {
+            return CompareResult::maybe_defaced("No new third party domains are referenced").with_score(0.0);
+        }
+
+        warn!("Page {} with ID {} references new third party domains {:?}", page.page_url(),
+            page.page_id(), new_domains);
+
+        CompareResult::defaced(NEW_DOMAIN_CONFIDENCE,
+                               format!("New third party domains referenced by the page: {}", new_domains.join(", ")))
+            .with_score(new_domains.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod domain_comparator_tests {
+    use std::collections::BTreeSet;
+    use crate::comparators::domain_comparator::{extract_referenced_domains, find_new_domains};
+    use crate::databases::{TrackedPage, TrackedPageType};
+
+    const PAGE: &str = "<html><head><link rel=\"stylesheet\" href=\"/style.css\">\
+    <script src=\"https://cdn.jsdelivr.net/lib.js\"></script></head>\
+    <body><a href=\"about.html\">About</a><a href=\"https://static.example.com/img.png\">Image</a>\
+    <a href=\"mailto:shop@example.com\">Mail</a></body></html>";
+
+    fn test_page() -> TrackedPage {
+        TrackedPage::new(1, String::from("https://www.example.com/shop/"), 1, 0, 0, 0, 0, 5, false,
+                         TrackedPageType::Static)
+    }
+
+    #[test]
+    fn test_extract_referenced_domains() {
+        let domains = extract_referenced_domains("https://www.example.com/shop/", PAGE);
+
+        assert_eq!(domains, BTreeSet::from([String::from("cdn.jsdelivr.net"),
+            String::from("static.example.com"), String::from("www.example.com")]));
+    }
+
+    #[test]
+    fn test_find_new_domains() {
+        let mut page = test_page();
+
+        let injected = PAGE.replace("</body>", "<a href=\"https://cheap-pills.biz/buy\">Pills</a>\
+        <iframe src=\"//evil.example.net/x\"></iframe><script src=\"https://fonts.googleapis.com/f.js\"></script></body>");
+
+        assert!(find_new_domains(&page, PAGE, PAGE).is_empty());
+        assert_eq!(find_new_domains(&page, PAGE, injected.as_str()),
+                   vec![String::from("cheap-pills.biz"), String::from("evil.example.net"),
+                        String::from("fonts.googleapis.com")]);
+
+        page.set_allowed_domains(vec![String::from("googleapis.com")]);
+
+        assert_eq!(find_new_domains(&page, PAGE, injected.as_str()),
+                   vec![String::from("cheap-pills.biz"), String::from("evil.example.net")]);
+    }
+}
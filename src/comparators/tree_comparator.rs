@@ -0,0 +1,577 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use log::{debug, trace};
+use scraper::{ElementRef, Html, Node};
+use crate::comparators::{Comparator, CompareResult, confidence_above_threshold};
+use crate::comparators::baseline::{adapt_comparator_model, learn_comparator_model, learned_comparator_model};
+use crate::databases::{TrackedPage, TrackedPageType};
+
+/*
+This mod compares the structure of the pages instead of their text.
+Both doms are parsed into element trees and the elements are matched against each other,
+so the result does not depend on how the page is formatted (A minified page that has
+a single line changed is not 100% different, and re indenting the page changes nothing)
+ */
+
+//The max amount of changed nodes that are written to the logs
+const MAX_LOGGED_CHANGES: usize = 10;
+
+//The max size of the LCS table used to match the children of a node, larger lists are matched by their keys
+const MAX_LCS_CELLS: usize = 1 << 20;
+
+//Regions closer to the root than this (The document, head and body) are never masked, as that would hide the whole page
+const MIN_MASKED_DEPTH: usize = 3;
+
+///A simplified element of the dom, only keeping what matters for the comparison
+struct TreeNode {
+    tag: String,
+    id: Option<String>,
+    //Sorted, so the order in which the attributes are written does not matter
+    attributes: Vec<(String, String)>,
+    //The text directly inside this element, with the whitespace collapsed
+    text: String,
+    children: Vec<TreeNode>,
+}
+
+///The changes between two element trees.
+///Each changed node is identified by a CSS selector that leads to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+    //Amount of nodes that were added, removed or changed (including the children of added and removed nodes)
+    distance: usize,
+    //Amount of nodes in the largest of the trees
+    node_count: usize,
+}
+
+impl TreeNode {
+    fn from_element(element: ElementRef) -> Self {
+        let value = element.value();
+
+        let mut attributes: Vec<(String, String)> = value.attrs()
+            .map(|(name, value)| (String::from(name), String::from(value)))
+            .collect();
+
+        attributes.sort();
+
+        let mut text = String::new();
+        let mut children = Vec::new();
+
+        for child in element.children() {
+            match child.value() {
+                Node::Text(child_text) => {
+                    for word in child_text.split_whitespace() {
+                        if !text.is_empty() {
+                            text.push(' ');
+                        }
+
+                        text.push_str(word);
+                    }
+                }
+                Node::Element(_) => {
+                    children.push(TreeNode::from_element(ElementRef::wrap(child).unwrap()));
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            tag: String::from(value.name()),
+            id: value.id().map(String::from),
+            attributes,
+            text,
+            children,
+        }
+    }
+
+    fn parse(dom: &str) -> Self {
+        TreeNode::from_element(Html::parse_document(dom).root_element())
+    }
+
+    fn size(&self) -> usize {
+        1 + self.children.iter().map(|child| child.size()).sum::<usize>()
+    }
+
+    ///Elements with the same key are considered to be the same element in both trees
+    fn key(&self) -> (&str, Option<&str>) {
+        (self.tag.as_str(), self.id.as_deref())
+    }
+
+    ///The selector for this node, position is the 1 based index of the node in its parent
+    fn selector(&self, position: usize) -> String {
+        match &self.id {
+            Some(id) if is_css_identifier(id) => { format!("{}#{}", self.tag, id) }
+            _ => { format!("{}:nth-child({})", self.tag, position) }
+        }
+    }
+}
+
+fn is_css_identifier(id: &str) -> bool {
+    match id.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        }
+        _ => { false }
+    }
+}
+
+///Match the children of two nodes using the longest common subsequence of their keys,
+///So a node that is inserted in the middle of the children does not shift all the other ones
+fn match_children(old: &[TreeNode], new: &[TreeNode]) -> Vec<(Option<usize>, Option<usize>)> {
+    //The children around the change are usually the same, only what is between them needs to be matched
+    let prefix = old.iter().zip(new.iter()).take_while(|(old, new)| old.key() == new.key()).count();
+
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old.key() == new.key()).count();
+
+    let (old_middle, new_middle) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut matches = Vec::with_capacity(std::cmp::max(old.len(), new.len()));
+
+    matches.extend((0..prefix).map(|ind| (Some(ind), Some(ind))));
+
+    let middle_matches = if old_middle.len().saturating_mul(new_middle.len()) <= MAX_LCS_CELLS {
+        match_children_lcs(old_middle, new_middle)
+    } else {
+        trace!("Matching {} and {} children by their keys, as they are too many for the LCS", old_middle.len(), new_middle.len());
+
+        match_children_by_key(old_middle, new_middle)
+    };
+
+    matches.extend(middle_matches.into_iter()
+        .map(|(old_ind, new_ind)| (old_ind.map(|ind| ind + prefix), new_ind.map(|ind| ind + prefix))));
+
+    matches.extend((0..suffix).map(|ind| (Some(old.len() - suffix + ind), Some(new.len() - suffix + ind))));
+
+    matches
+}
+
+fn match_children_lcs(old: &[TreeNode], new: &[TreeNode]) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+
+    for old_ind in (0..old.len()).rev() {
+        for new_ind in (0..new.len()).rev() {
+            lcs[old_ind][new_ind] = if old[old_ind].key() == new[new_ind].key() {
+                lcs[old_ind + 1][new_ind + 1] + 1
+            } else {
+                std::cmp::max(lcs[old_ind + 1][new_ind], lcs[old_ind][new_ind + 1])
+            };
+        }
+    }
+
+    let mut matches = Vec::with_capacity(std::cmp::max(old.len(), new.len()));
+
+    let (mut old_ind, mut new_ind) = (0, 0);
+
+    while old_ind < old.len() && new_ind < new.len() {
+        if old[old_ind].key() == new[new_ind].key() {
+            matches.push((Some(old_ind), Some(new_ind)));
+            old_ind += 1;
+            new_ind += 1;
+        } else if lcs[old_ind + 1][new_ind] >= lcs[old_ind][new_ind + 1] {
+            matches.push((Some(old_ind), None));
+            old_ind += 1;
+        } else {
+            matches.push((None, Some(new_ind)));
+            new_ind += 1;
+        }
+    }
+
+    matches.extend((old_ind..old.len()).map(|ind| (Some(ind), None)));
+    matches.extend((new_ind..new.len()).map(|ind| (None, Some(ind))));
+
+    matches
+}
+
+///The n-th child with a key is matched with the n-th new child with the same key, ignoring the order of the keys.
+///Only the counts of the keys matter, so moved children are not reported, but it runs in linear time and memory
+fn match_children_by_key(old: &[TreeNode], new: &[TreeNode]) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut new_by_key: HashMap<(&str, Option<&str>), VecDeque<usize>> = HashMap::new();
+
+    for (new_ind, child) in new.iter().enumerate() {
+        new_by_key.entry(child.key()).or_default().push_back(new_ind);
+    }
+
+    let mut matched_new = vec![false; new.len()];
+
+    let mut matches = Vec::with_capacity(std::cmp::max(old.len(), new.len()));
+
+    for (old_ind, child) in old.iter().enumerate() {
+        match new_by_key.get_mut(&child.key()).and_then(VecDeque::pop_front) {
+            Some(new_ind) => {
+                matched_new[new_ind] = true;
+                matches.push((Some(old_ind), Some(new_ind)));
+            }
+            None => { matches.push((Some(old_ind), None)); }
+        }
+    }
+
+    matches.extend((0..new.len()).filter(|ind| !matched_new[*ind]).map(|ind| (None, Some(ind))));
+
+    matches
+}
+
+fn diff_nodes(old: &TreeNode, new: &TreeNode, path: &str, diff: &mut TreeDiff) {
+    if old.attributes != new.attributes || old.text != new.text {
+        diff.changed.push(String::from(path));
+        diff.distance += 1;
+    }
+
+    for (old_child, new_child) in match_children(&old.children, &new.children) {
+        match (old_child, new_child) {
+            (Some(old_ind), Some(new_ind)) => {
+                let child_path = format!("{} > {}", path, new.children[new_ind].selector(new_ind + 1));
+
+                diff_nodes(&old.children[old_ind], &new.children[new_ind], child_path.as_str(), diff);
+            }
+            (Some(old_ind), None) => {
+                let removed = &old.children[old_ind];
+
+                diff.removed.push(format!("{} > {}", path, removed.selector(old_ind + 1)));
+                diff.distance += removed.size();
+            }
+            (None, Some(new_ind)) => {
+                let added = &new.children[new_ind];
+
+                diff.added.push(format!("{} > {}", path, added.selector(new_ind + 1)));
+                diff.distance += added.size();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+fn diff_trees(old: &TreeNode, new: &TreeNode) -> TreeDiff {
+    let mut diff = TreeDiff {
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+        distance: 0,
+        node_count: std::cmp::max(old.size(), new.size()),
+    };
+
+    if old.key() != new.key() {
+        //Completely different documents
+        diff.removed.push(old.tag.clone());
+        diff.added.push(new.tag.clone());
+        diff.distance = old.size() + new.size();
+
+        return diff;
+    }
+
+    diff_nodes(old, new, new.tag.as_str(), &mut diff);
+
+    diff
+}
+
+///
+/// Parse both doms into element trees and calculate which nodes were added, removed or changed
+pub fn compare_dom_trees(dom: &str, dom_2: &str) -> TreeDiff {
+    diff_trees(&TreeNode::parse(dom), &TreeNode::parse(dom_2))
+}
+
+impl TreeDiff {
+    pub fn added(&self) -> &Vec<String> {
+        &self.added
+    }
+    pub fn removed(&self) -> &Vec<String> {
+        &self.removed
+    }
+    pub fn changed(&self) -> &Vec<String> {
+        &self.changed
+    }
+    pub fn distance(&self) -> usize {
+        self.distance
+    }
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.distance == 0
+    }
+
+    ///The percentage of nodes that were changed
+    pub fn difference_percent(&self) -> f64 {
+        if self.node_count == 0 {
+            return 0.0;
+        }
+
+        std::cmp::min(self.distance, self.node_count) as f64 * 100.0 / self.node_count as f64
+    }
+}
+
+///The selector of the parent of the element, if the parent can be masked
+fn maskable_parent(selector: &str) -> Option<&str> {
+    selector.rsplit_once(" > ")
+        .map(|(parent, _)| parent)
+        .filter(|parent| parent.split(" > ").count() >= MIN_MASKED_DEPTH)
+}
+
+///Start the selector at the closest element with an ID, so it keeps pointing to the same element
+///When the elements before that one are added or moved
+fn anchor_selector(selector: &str) -> &str {
+    match selector.rfind('#') {
+        Some(id_ind) => {
+            let start = selector[..id_ind].rfind(" > ").map(|ind| ind + 3).unwrap_or(0);
+
+            &selector[start..]
+        }
+        None => { selector }
+    }
+}
+
+///
+/// Learn which regions of the page change between the samples of a dynamic page.
+/// Returns the selectors of the outermost changed elements and of the parents of the added and removed elements, sorted.
+/// The parents are masked as a whole because the positions of their children are not stable (a list that grew
+/// While it was sampled can grow past the positions that were seen). The selectors start at the closest element
+/// With an ID, so they are only positional when there is no ID to anchor them to
+pub fn learn_volatility_mask(samples: &[String]) -> Vec<String> {
+    let trees: Vec<TreeNode> = samples.iter().map(|sample| TreeNode::parse(sample)).collect();
+
+    let mut volatile = BTreeSet::new();
+
+    for pair in trees.windows(2) {
+        let diff = diff_trees(&pair[0], &pair[1]);
+
+        let resized = diff.added.into_iter()
+            .chain(diff.removed)
+            .map(|selector| maskable_parent(selector.as_str()).map(String::from).unwrap_or(selector));
+
+        volatile.extend(resized.chain(diff.changed)
+            .filter(|selector| selector.split(" > ").count() >= MIN_MASKED_DEPTH));
+    }
+
+    let mut outermost: Vec<String> = Vec::new();
+
+    //Sorted, so the parents always come before their children
+    for selector in volatile {
+        if !outermost.iter().any(|parent| selector.starts_with(format!("{} > ", parent).as_str())) {
+            outermost.push(selector);
+        }
+    }
+
+    let mut mask: Vec<String> = Vec::new();
+
+    for selector in &outermost {
+        let anchored = String::from(anchor_selector(selector));
+
+        if !mask.contains(&anchored) {
+            mask.push(anchored);
+        }
+    }
+
+    mask.sort();
+
+    mask
+}
+
+#[derive(Default)]
+pub struct TreeComparator {}
+
+impl TreeComparator {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+fn log_tree_diff(page: &TrackedPage, diff: &TreeDiff) {
+    debug!("Tree diff for page {} with ID {}: {} added, {} removed, {} changed nodes ({}%)",
+        page.page_url(), page.page_id(), diff.added().len(), diff.removed().len(),
+        diff.changed().len(), diff.difference_percent());
+
+    for added in diff.added().iter().take(MAX_LOGGED_CHANGES) {
+        trace!("Added node {}", added);
+    }
+
+    for removed in diff.removed().iter().take(MAX_LOGGED_CHANGES) {
+        trace!("Removed node {}", removed);
+    }
+
+    for changed in diff.changed().iter().take(MAX_LOGGED_CHANGES) {
+        trace!("Changed node {}", changed);
+    }
+}
+
+///A short description of the diff for the users, with the first changed nodes
+fn describe_tree_diff(diff: &TreeDiff) -> String {
+    let mut description = format!("{:.2}% of the elements changed ({} added, {} removed, {} changed)",
+                                  diff.difference_percent(), diff.added().len(), diff.removed().len(),
+                                  diff.changed().len());
+
+    let nodes: Vec<&String> = diff.added().iter()
+        .chain(diff.removed().iter())
+        .chain(diff.changed().iter())
+        .take(MAX_LOGGED_CHANGES)
+        .collect();
+
+    if !nodes.is_empty() {
+        description.push_str(format!(": {}", nodes.iter().map(|node| node.as_str()).collect::<Vec<&str>>().join(", ")).as_str());
+    }
+
+    description
+}
+
+impl Comparator<String> for TreeComparator {
+    fn name(&self) -> &str {
+        "Tree"
+    }
+
+    fn compare_between(&self, page: &TrackedPage, dom_1: &String, dom_2: &String) -> CompareResult {
+        let diff = compare_dom_trees(dom_1, dom_2);
+
+        log_tree_diff(page, &diff);
+
+        let result = match page.tracked_page_type() {
+            TrackedPageType::Static => {
+                if diff.is_empty() {
+                    CompareResult::not_defaced(1.0, "The structure of the static page did not change")
+                } else {
+                    CompareResult::defaced(1.0, describe_tree_diff(&diff))
+                }
+            }
+            //The volatile regions were already removed, what is left should not change at all
+            TrackedPageType::Dynamic(_) if !page.volatility_mask().is_empty() => {
+                if diff.is_empty() {
+                    CompareResult::not_defaced(1.0, "The stable regions of the page did not change")
+                } else {
+                    CompareResult::defaced(1.0, format!("Stable regions changed. {}", describe_tree_diff(&diff)))
+                }
+            }
+            TrackedPageType::Dynamic(_) => {
+                let threshold = learned_comparator_model(page, self.name())
+                    .map(|model| model.threshold());
+
+                match threshold {
+                    Some(threshold) if diff.difference_percent() > threshold => {
+                        CompareResult::defaced(confidence_above_threshold(diff.difference_percent(), threshold),
+                                               describe_tree_diff(&diff))
+                    }
+                    Some(_) => { CompareResult::maybe_defaced("The structural changes are below the threshold") }
+                    None => { CompareResult::maybe_defaced("No structural threshold was learned for the page") }
+                }
+            }
+        };
+
+        result.with_score(diff.difference_percent())
+    }
+
+    fn learn_from_samples(&self, page: &TrackedPage, samples: &[String]) -> Option<String> {
+        let trees: Vec<TreeNode> = samples.iter().map(|sample| TreeNode::parse(sample)).collect();
+
+        learn_comparator_model(page, &trees, |tree, tree_2| diff_trees(tree, tree_2).difference_percent())
+    }
+
+    fn adapt_to_check(&self, page: &TrackedPage, dom_1: &String, dom_2: &String, weight: f64) -> Option<String> {
+        adapt_comparator_model(page, self.name(), compare_dom_trees(dom_1, dom_2).difference_percent(), weight)
+    }
+}
+
+#[cfg(test)]
+mod tree_comparator_tests {
+    use crate::comparators::tree_comparator::{compare_dom_trees, learn_volatility_mask};
+    use crate::normalisation::mask_volatile_regions;
+
+    const PAGE: &str = "<html><head><title>Test</title></head><body><div id=\"main\"><p>First</p><p>Second</p></div><footer>Footer</footer></body></html>";
+
+    #[test]
+    fn test_reformatting_is_not_a_change() {
+        let reformatted = "<html>\n  <head>\n    <title>Test</title>\n  </head>\n  <body>\n    <div id=\"main\">\n      <p>First</p>\n      <p>Second</p>\n    </div>\n    <footer>Footer</footer>\n  </body>\n</html>";
+
+        assert!(compare_dom_trees(PAGE, reformatted).is_empty());
+    }
+
+    #[test]
+    fn test_single_change_in_minified_page() {
+        let changed = PAGE.replace("Second", "Hacked");
+
+        let diff = compare_dom_trees(PAGE, changed.as_str());
+
+        assert_eq!(diff.changed(), &vec![String::from("html > body:nth-child(2) > div#main > p:nth-child(2)")]);
+        assert!(diff.added().is_empty() && diff.removed().is_empty());
+        assert!(diff.difference_percent() < 15.0);
+    }
+
+    #[test]
+    fn test_added_and_removed_nodes() {
+        let changed = PAGE.replace("<p>First</p>", "<p>First</p><marquee>Injected</marquee>")
+            .replace("<footer>Footer</footer>", "");
+
+        let diff = compare_dom_trees(PAGE, changed.as_str());
+
+        assert_eq!(diff.added(), &vec![String::from("html > body:nth-child(2) > div#main > marquee:nth-child(2)")]);
+        assert_eq!(diff.removed(), &vec![String::from("html > body:nth-child(2) > footer:nth-child(2)")]);
+        assert_eq!(diff.distance(), 2);
+    }
+
+    #[test]
+    fn test_many_children() {
+        //The children that are not around the change are too many for the LCS, so they are matched by their keys
+        let items: String = (0..1500).map(|item| format!("<li>{}</li>", item)).collect();
+
+        let page = format!("<html><body><ul><h1>Top</h1>{}<h2>Bottom</h2></ul></body></html>", items);
+        let changed = format!("<html><body><ul><h2>Bottom</h2>{}<li>Injected</li><h1>Top</h1></ul></body></html>", items);
+
+        let diff = compare_dom_trees(page.as_str(), changed.as_str());
+
+        assert_eq!(diff.added(), &vec![String::from("html > body:nth-child(2) > ul:nth-child(1) > li:nth-child(1502)")]);
+        assert!(diff.removed().is_empty());
+    }
+
+    #[test]
+    fn test_learn_volatility_mask() {
+        let samples: Vec<String> = ["12:00", "12:01", "12:02"].iter()
+            .map(|time| PAGE.replace("<p>Second</p>", format!("<p>Second</p><p>{}<b>now</b></p>", time).as_str()))
+            .collect();
+
+        let mask = learn_volatility_mask(&samples);
+
+        assert_eq!(mask, vec![String::from("div#main > p:nth-child(3)")]);
+
+        let masked: Vec<String> = samples.iter().map(|sample| mask_volatile_regions(&mask, sample)).collect();
+
+        assert!(compare_dom_trees(&masked[0], &masked[2]).is_empty());
+
+        let defaced = mask_volatile_regions(&mask, samples[2].replace("First", "Hacked").as_str());
+
+        assert!(!compare_dom_trees(&masked[0], defaced.as_str()).is_empty());
+    }
+
+    #[test]
+    fn test_mask_lists_that_change_size() {
+        let page_with_items = |items: usize| {
+            let items: String = (0..items).map(|item| format!("<li>News {}</li>", item)).collect();
+
+            format!("<html><body><div><p>About</p><ul>{}</ul></div><footer>Footer</footer></body></html>", items)
+        };
+
+        let samples: Vec<String> = (2..5).map(page_with_items).collect();
+
+        //The whole list is masked, not the items that were added while it was sampled
+        let mask = learn_volatility_mask(&samples);
+
+        assert_eq!(mask, vec![String::from("html > body:nth-child(2) > div:nth-child(1) > ul:nth-child(2)")]);
+
+        let longer = mask_volatile_regions(&mask, page_with_items(8).as_str());
+
+        assert!(compare_dom_trees(&mask_volatile_regions(&mask, &samples[0]), longer.as_str()).is_empty());
+    }
+
+    #[test]
+    fn test_mask_is_anchored_on_ids() {
+        let samples: Vec<String> = ["12:00", "12:01"].iter()
+            .map(|time| PAGE.replace("<footer>Footer</footer>", format!("<footer><span id=\"clock\">{}</span></footer>", time).as_str()))
+            .collect();
+
+        let mask = learn_volatility_mask(&samples);
+
+        assert_eq!(mask, vec![String::from("span#clock")]);
+
+        //A banner added before the footer does not move the mask to another element
+        let with_banner = samples[1].replace("<footer>", "<div>Banner</div><footer>");
+
+        assert!(!mask_volatile_regions(&mask, with_banner.as_str()).contains("12:01"));
+    }
+}
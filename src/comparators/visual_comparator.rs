@@ -0,0 +1,196 @@
+use std::io::Cursor;
+use image::imageops::FilterType;
+use image::{GrayImage, ImageOutputFormat, Rgba};
+use log::{error, trace};
+use crate::comparators::{Comparator, CompareResult, confidence_above_threshold};
+use crate::databases::TrackedPage;
+
+/*
+This mod compares screenshots of the rendered pages, so we can catch defacements
+that don't show up in the DOM text, like CSS only changes or images being swapped.
+ */
+
+//Both screenshots are scaled down to this width before comparing them,
+//Which smooths out anti aliasing and sub pixel rendering differences between renders
+const COMPARISON_WIDTH: u32 = 320;
+//How much the brightness of a pixel has to change (0-255) for it to count as changed
+const PIXEL_TOLERANCE: u8 = 24;
+
+///A rectangle of a screenshot, in pixels
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ScreenshotRegion {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl ScreenshotRegion {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+}
+
+///
+/// Paint the regions of the PNG screenshot black, so what is shown in them is never compared.
+/// Regions that go past the edges of the screenshot are cut to fit it
+pub fn blank_regions(screenshot: &[u8], regions: &[ScreenshotRegion]) -> Result<Vec<u8>, String> {
+    let mut image = match image::load_from_memory(screenshot) {
+        Ok(image) => { image.to_rgba8() }
+        Err(e) => { return Err(e.to_string()); }
+    };
+
+    for region in regions {
+        let right = region.x.saturating_add(region.width).min(image.width());
+        let bottom = region.y.saturating_add(region.height).min(image.height());
+
+        for y in region.y..bottom {
+            for x in region.x..right {
+                image.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+            }
+        }
+    }
+
+    let mut png = Cursor::new(Vec::new());
+
+    match image.write_to(&mut png, ImageOutputFormat::Png) {
+        Ok(_) => { Ok(png.into_inner()) }
+        Err(e) => { Err(e.to_string()) }
+    }
+}
+
+fn load_screenshot(screenshot: &[u8]) -> Result<GrayImage, String> {
+    match image::load_from_memory(screenshot) {
+        Ok(image) => { Ok(image.to_luma8()) }
+        Err(e) => { Err(e.to_string()) }
+    }
+}
+
+///
+/// Compare two PNG screenshots and return the percentage of pixels that changed
+/// The second screenshot is scaled to the size of the first, so pages that
+/// grew or shrank can still be compared
+pub fn compare_screenshots(screenshot: &[u8], screenshot_2: &[u8]) -> Result<f64, String> {
+    let first = load_screenshot(screenshot)?;
+    let second = load_screenshot(screenshot_2)?;
+
+    if first.width() == 0 || first.height() == 0 {
+        return Err(String::from("The stored screenshot is empty"));
+    }
+
+    let width = std::cmp::min(first.width(), COMPARISON_WIDTH);
+    let height = std::cmp::max((first.height() as u64 * width as u64 / first.width() as u64) as u32, 1);
+
+    let first = image::imageops::resize(&first, width, height, FilterType::Triangle);
+    let second = image::imageops::resize(&second, width, height, FilterType::Triangle);
+
+    let changed_pixels = first.pixels().zip(second.pixels())
+        .filter(|(pixel, pixel_2)| pixel.0[0].abs_diff(pixel_2.0[0]) > PIXEL_TOLERANCE)
+        .count();
+
+    Ok((changed_pixels as f64 * 100.0) / ((width * height) as f64))
+}
+
+#[derive(Default)]
+pub struct VisualComparator {}
+
+impl VisualComparator {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Comparator<Vec<u8>> for VisualComparator {
+    fn name(&self) -> &str {
+        "Visual"
+    }
+
+    fn compare_between(&self, page: &TrackedPage, screenshot: &Vec<u8>, screenshot_2: &Vec<u8>) -> CompareResult {
+        let threshold = match page.screenshot_threshold() {
+            Some(threshold) => { threshold }
+            None => { return CompareResult::maybe_defaced("Visual comparison is disabled for the page"); }
+        };
+
+        match compare_screenshots(screenshot, screenshot_2) {
+            Ok(diff) => {
+                trace!("Screenshot difference calculated is {}, needs to be below {}", diff, threshold);
+
+                //Even if the page looks the same, there might be changes that can't be seen
+                //So we can never be sure that it was not defaced
+                let result = if diff > threshold {
+                    CompareResult::defaced(confidence_above_threshold(diff, threshold),
+                                           format!("{:.2}% of the screenshot changed, above the threshold of {:.2}%",
+                                                   diff, threshold))
+                } else {
+                    CompareResult::maybe_defaced("The screenshot changes are below the threshold")
+                };
+
+                result.with_score(diff)
+            }
+            Err(e) => {
+                error!("Failed to compare screenshots for page {} with ID {}. {}", page.page_url(), page.page_id(), e);
+
+                CompareResult::maybe_defaced("Failed to compare the screenshots")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod visual_comparator_tests {
+    use std::io::Cursor;
+    use image::{ImageOutputFormat, Rgb, RgbImage};
+    use crate::comparators::visual_comparator::{blank_regions, compare_screenshots, ScreenshotRegion};
+
+    fn encode_png(image: &RgbImage) -> Vec<u8> {
+        let mut png = Cursor::new(Vec::new());
+
+        image.write_to(&mut png, ImageOutputFormat::Png).unwrap();
+
+        png.into_inner()
+    }
+
+    #[test]
+    fn test_compare_screenshots() {
+        let white = RgbImage::from_pixel(640, 480, Rgb([255, 255, 255]));
+
+        let mut banner = white.clone();
+
+        //Paint the top quarter of the page black
+        for y in 0..120 {
+            for x in 0..640 {
+                banner.put_pixel(x, y, Rgb([0, 0, 0]));
+            }
+        }
+
+        assert_eq!(compare_screenshots(&encode_png(&white), &encode_png(&white)).unwrap(), 0.0);
+
+        let diff = compare_screenshots(&encode_png(&white), &encode_png(&banner)).unwrap();
+
+        assert!(diff > 20.0 && diff < 30.0, "Diff was {}", diff);
+
+        assert!(compare_screenshots(&encode_png(&white), &[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_blank_regions() {
+        let white = RgbImage::from_pixel(640, 480, Rgb([255, 255, 255]));
+
+        let mut ticker = white.clone();
+
+        for y in 10..40 {
+            for x in 0..640 {
+                ticker.put_pixel(x, y, Rgb([200, 0, 0]));
+            }
+        }
+
+        //The ticker is blanked in both screenshots, the second region goes past the edges
+        let regions = [ScreenshotRegion::new(0, 10, 640, 30), ScreenshotRegion::new(600, 470, 100, 100)];
+
+        let blanked = blank_regions(&encode_png(&white), &regions).unwrap();
+        let blanked_ticker = blank_regions(&encode_png(&ticker), &regions).unwrap();
+
+        assert_eq!(compare_screenshots(&blanked, &blanked_ticker).unwrap(), 0.0);
+        assert!(compare_screenshots(&encode_png(&white), &encode_png(&ticker)).unwrap() > 0.0);
+    }
+}
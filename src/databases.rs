@@ -1,12 +1,19 @@
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display};
 use std::time::Duration;
 use crate::communication::{CommData, UserCommunication};
+use crate::comparators::{ComparatorResult, Verdict};
+use crate::comparators::aggregation::AggregatedResult;
+use crate::comparators::baseline::BaselineModel;
 
 pub mod sqlitedb;
 
 const DEFAULT_DEFACEMENT_THRESHOLD: u32 = 5;
 pub const DEFAULT_INDEXING_INTERVAL: u64 = Duration::from_secs(60 * 30).as_millis() as u64;
+//How many samples are collected when a dynamic page is analysed, and how far apart
+pub const DEFAULT_SAMPLE_COUNT: u32 = 10;
+pub const DEFAULT_SAMPLE_INTERVAL: u64 = Duration::from_secs(1).as_millis() as u64;
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct TrackedPage {
@@ -20,15 +27,50 @@ pub struct TrackedPage {
     defacement_count: u32,
     defacement_threshold: u32,
     notified_of_current_breach: bool,
+    parser_type: ParserType,
+    //The % of the screenshot that is allowed to change before the page is considered defaced
+    //When None, no screenshots are captured for this page
+    screenshot_threshold: Option<f64>,
+    //What each comparator learned about the page during the dynamic analysis, by comparator name
+    comparator_data: BTreeMap<String, String>,
+    //Third party domains the page is allowed to reference (Subdomains included)
+    allowed_domains: Vec<String>,
+    //Normalisation rules applied to the doms of this page, see normalisation::NormalisationRule
+    normalisation_rules: Vec<String>,
+    //CSS selectors of the regions of the page that are never compared (news tickers, comment boxes...)
+    ignored_selectors: Vec<String>,
+    //How many samples of the page are collected during the dynamic analysis
+    sample_count: u32,
+    //Time between the samples of the dynamic analysis, in millis
+    sample_interval: u128,
+    //Whether the checks that are not considered defaced keep feeding the baseline of the dynamic page
+    adaptive_baseline: bool,
+    //CSS selectors of the regions that changed between the samples of the dynamic analysis,
+    //They are removed before comparing so the rest of the page can be compared strictly
+    volatility_mask: Vec<String>,
+    //Selectors the user added to the mask by hand, they are kept in the mask when it is learned again
+    pinned_volatility_mask: Vec<String>,
+    //Learned selectors the user removed from the mask, they are left out of it when it is learned again
+    excluded_volatility_mask: Vec<String>,
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum TrackedPageType {
     Static,
     /*
-    Stores the threshold at which we start to notify that the page has been defaced
+    Stores the model of the changes seen while analysing the page,
+    from which we get the threshold at which we start to notify that the page has been defaced
      */
-    Dynamic(f64),
+    Dynamic(BaselineModel),
+}
+
+///Which kind of parser should be used to fetch a tracked page
+#[derive(PartialEq, Debug, Clone)]
+pub enum ParserType {
+    //Render the page in a browser, running JS and expanding CSS
+    Browser,
+    //Fetch the page directly over HTTP, for static pages that don't need JS
+    Http,
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -36,12 +78,136 @@ pub struct StoredDom<T> {
     dom_id: u32,
     owning_page_id: u32,
     dom: T,
+    //PNG screenshot of the page taken when the dom was stored
+    screenshot: Option<Vec<u8>>,
+    //Accepted doms are legitimate versions of the page (A/B tests, rotating banners...)
+    //A check passes when the current dom matches any of them
+    accepted: bool,
+}
+
+///A change of the page that was approved as legitimate, the approved dom became the page's baseline
+#[derive(PartialEq, Debug, Clone)]
+pub struct Approval {
+    approval_id: u32,
+    page_id: u32,
+    //The incident whose change was approved
+    incident_id: u32,
+    //The dom that was stored as the new baseline
+    dom_id: u32,
+    approver: String,
+    approval_time: u128,
+    //The defacement count of the page when the change was approved
+    defacement_count: u32,
+}
+
+///A compact record of a single check of a page, kept to report on its uptime and on the trends of the scores
+#[derive(PartialEq, Debug, Clone)]
+pub struct CheckRecord {
+    check_id: u32,
+    page_id: u32,
+    check_time: u128,
+    //How long it took to fetch the page, in millis
+    fetch_duration: u128,
+    //None when the parser can't see the response of the server
+    http_status: Option<u16>,
+    //None when the page could not be fetched
+    verdict: Option<Verdict>,
+    //The raw score of every comparator that measured one (Diff percentage, amount of new domains...)
+    scores: BTreeMap<String, f64>,
+}
+
+///How an incident ended
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum IncidentResolution {
+    //The page went back to its baseline on its own (Or was restored by its owners)
+    Recovered,
+    //The change was approved as legitimate and became the new baseline
+    Accepted,
+    //The page was never defaced, the comparators were wrong
+    FalsePositive,
+}
+
+///A suspected defacement of a page, from the first check that considered it defaced until it was resolved
+#[derive(PartialEq, Debug, Clone)]
+pub struct Incident {
+    incident_id: u32,
+    page_id: u32,
+    //The owner of the page when the incident started
+    user_id: u32,
+    start_time: u128,
+    //None while the incident is still open
+    end_time: Option<u128>,
+    //The verdicts of the comparators that considered the page defaced in the check that started the incident
+    triggering_results: Vec<ComparatorResult>,
+    //The dom and screenshot of the page when the incident started, the snapshot its notifications are built from
+    offending_dom: String,
+    offending_screenshot: Option<Vec<u8>>,
+    //The IDs of the contacts that were notified of the incident
+    notified_contacts: Vec<u32>,
+    resolution: Option<IncidentResolution>,
+}
+
+///Which incidents should be listed, every condition that is set must match
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct IncidentFilter {
+    //Only the open (true) or the resolved (false) incidents
+    open: Option<bool>,
+    resolution: Option<IncidentResolution>,
+    //Only the incidents that started in this time range (millis since the epoch)
+    started_after: Option<u128>,
+    started_before: Option<u128>,
+}
+
+///What a notification in the outbox tells the contact about
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum NotificationKind {
+    Defacement,
+    Recovery,
+}
+
+///Where a notification of the outbox is in its delivery
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum OutboxStatus {
+    //Waiting for its next delivery attempt
+    Pending,
+    Delivered,
+    //Every delivery attempt failed, it is kept so it can be looked at and retried by hand
+    DeadLetter,
+}
+
+///A notification to a contact through a single channel. It is stored before it is sent, with what
+///Is needed to build it again, so it is not lost when the channel is down or the program restarts.
+///The doms are not copied into every notification, they are read from the stored doms and the incident
+#[derive(PartialEq, Debug, Clone)]
+pub struct OutboxEntry {
+    entry_id: u32,
+    kind: NotificationKind,
+    page_id: u32,
+    user_id: u32,
+    incident_id: Option<u32>,
+    contact_id: u32,
+    //The name of the communication method that delivers it
+    channel: String,
+    //The ID of the dom the page was compared against and the verdict of the check, the dom and screenshot
+    //Of the defaced page are the snapshot of the incident. Only for the defacement notifications
+    stored_dom_id: Option<u32>,
+    result: Option<AggregatedResult>,
+    status: OutboxStatus,
+    //Amount of failed delivery attempts
+    attempts: u32,
+    created_time: u128,
+    //The notification is not delivered before this time (millis since the epoch)
+    next_attempt: u128,
+    //The error of the last failed attempt
+    last_error: Option<String>,
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct User {
     user_id: u32,
     user: String,
+    //The language the notifications are written in, the default locale of the templates when it's not chosen
+    locale: Option<String>,
 }
 
 /// T is the dom type
@@ -70,6 +236,10 @@ pub trait WebsiteDefacementDB<T>: Send + Sync where T: Display + Debug + Send +
 
     fn update_tracking_type_for_page(&self, page: &TrackedPage) -> Result<bool, String>;
 
+    ///Only store the baseline model of the dynamic page and the models its comparators learned,
+    ///Without touching the time it was last indexed
+    fn update_baseline_for_page(&self, page: &TrackedPage) -> Result<(), String>;
+
     ///Should also set the value of the object we were passed as the correct
     /// Value that is stored in the database
     ///notified is whether the system notified the user (If he had already been notified, this will be false)
@@ -84,11 +254,90 @@ pub trait WebsiteDefacementDB<T>: Send + Sync where T: Display + Debug + Send +
 
     fn read_latest_dom_for_page(&self, page: &TrackedPage) -> Result<StoredDom<T>, String>;
 
+    fn read_dom_for_id(&self, dom_id: u32) -> Result<StoredDom<T>, String>;
+
     fn insert_dom_for_page(&self, page: &TrackedPage, page_dom: T) -> Result<StoredDom<T>, String>;
 
     fn update_dom_for_page(&self, page: &TrackedPage, dom: &mut StoredDom<T>, page_dom: T) -> Result<(), String>;
 
+    ///Should also set the screenshot of the object we were passed
+    fn update_screenshot_for_dom(&self, dom: &mut StoredDom<T>, screenshot: Vec<u8>) -> Result<(), String>;
+
     fn delete_dom_for_page(&self, page: &TrackedPage, dom: StoredDom<T>) -> Result<bool, String>;
+
+    fn read_accepted_doms_for_page(&self, page: &TrackedPage) -> Result<Vec<StoredDom<T>>, String>;
+
+    ///Add or remove the dom from the accepted doms of its page
+    ///Should also set the value of the object we were passed
+    fn set_dom_accepted(&self, dom: &mut StoredDom<T>, accepted: bool) -> Result<(), String>;
+
+    ///Store the combined verdict of a check of the page, along with the result of every comparator.
+    ///Only the latest verdict of the page is kept, it replaces the previous one
+    ///Returns the ID of the stored verdict
+    fn insert_verdict_for_page(&self, page: &TrackedPage, result: &AggregatedResult) -> Result<u32, String>;
+
+    fn read_latest_verdict_for_page(&self, page: &TrackedPage) -> Result<AggregatedResult, String>;
+
+    ///Record that the change of the page that started the incident was approved by the approver,
+    ///The dom is the one that was stored as the new baseline
+    fn insert_approval_for_page(&self, page: &TrackedPage, incident: &Incident, dom: &StoredDom<T>,
+                                approver: &str) -> Result<Approval, String>;
+
+    ///The approvals of the page, the most recent first
+    fn list_approvals_for_page(&self, page: &TrackedPage) -> Result<Vec<Approval>, String>;
+
+    ///Start a new incident for the page, from the check that considered it defaced
+    fn open_incident_for_page(&self, page: &TrackedPage, result: &AggregatedResult, offending_dom: &T,
+                              offending_screenshot: Option<&Vec<u8>>) -> Result<Incident, String>;
+
+    ///The incident of the page that was not resolved yet, if there is one
+    fn read_open_incident_for_page(&self, page: &TrackedPage) -> Result<Option<Incident>, String>;
+
+    fn read_incident_for_id(&self, incident_id: u32) -> Result<Incident, String>;
+
+    ///Should also add the contact to the object we were passed
+    fn add_notified_contact_to_incident(&self, incident: &mut Incident, contact: &UserCommunication) -> Result<(), String>;
+
+    ///Close the incident with the given resolution. Resolved incidents can be resolved again
+    ///To correct their resolution (For example, to mark them as a false positive), keeping their end time
+    ///Should also set the values of the object we were passed
+    fn resolve_incident(&self, incident: &mut Incident, resolution: IncidentResolution) -> Result<(), String>;
+
+    ///The incidents of the page that match the filter, the most recent first
+    fn list_incidents_for_page(&self, page: &TrackedPage, filter: &IncidentFilter) -> Result<Vec<Incident>, String>;
+
+    ///Append a check of the page to its check log. The result is None when the page could not be fetched
+    fn insert_check_record_for_page(&self, page: &TrackedPage, fetch_duration: u128, http_status: Option<u16>,
+                                    result: Option<&AggregatedResult>) -> Result<CheckRecord, String>;
+
+    ///The checks of the page since the given time (millis since the epoch), the oldest first
+    fn list_check_records_for_page(&self, page: &TrackedPage, since: u128) -> Result<Vec<CheckRecord>, String>;
+
+    ///Remove the checks of the page that are older than the given time (millis since the epoch)
+    ///Returns the amount of removed checks
+    fn delete_check_records_older_than(&self, page: &TrackedPage, time: u128) -> Result<usize, String>;
+
+    ///Store the notification in the outbox, due right away
+    ///Should also set the ID, the creation time and the next attempt of the object we were passed
+    fn enqueue_notification(&self, entry: &mut OutboxEntry) -> Result<(), String>;
+
+    /// When this method is executed, the database should assume that this client
+    /// Will be responsible for delivering these notifications, and their next attempt
+    /// Should be pushed back by the lease (in millis) accordingly, to avoid another
+    /// Instance of this program delivering them at the same time. A notification that
+    /// Is not updated before the lease ends (The program stopped) will be due again
+    ///Returns at most limit pending notifications that are due, the oldest first
+    fn claim_due_notifications(&self, limit: u32, lease: u128) -> Result<Vec<OutboxEntry>, String>;
+
+    ///Store the status, attempts, next attempt and last error of the notification
+    fn update_outbox_entry(&self, entry: &OutboxEntry) -> Result<(), String>;
+
+    ///The notifications of the outbox with the given status, the oldest first
+    fn list_outbox_entries(&self, status: OutboxStatus) -> Result<Vec<OutboxEntry>, String>;
+
+    ///Remove the notifications with the given status that were created before the given time (millis since the epoch)
+    ///Returns the amount of removed notifications
+    fn delete_outbox_entries_older_than(&self, status: OutboxStatus, time: u128) -> Result<usize, String>;
 }
 
 pub trait UserDB: Send + Sync {
@@ -98,6 +347,9 @@ pub trait UserDB: Send + Sync {
 
     fn get_user_info_for_id(&self, user_id: u32) -> Result<User, String>;
 
+    ///Should also set the locale of the object we were passed, None goes back to the default locale
+    fn update_locale_for_user(&self, user: &mut User, locale: Option<&str>) -> Result<(), String>;
+
     fn delete_user(&self, user: User) -> Result<bool, String>;
 
     fn insert_contact_for(&self, user: &User, comm: CommData) -> Result<UserCommunication, String>;
@@ -107,6 +359,9 @@ pub trait UserDB: Send + Sync {
     fn get_contact_for_id(&self, contact_id: u32) -> Result<UserCommunication, String>;
 
     fn delete_contact(&self, comm: UserCommunication) -> Result<bool, String>;
+
+    ///The incidents of every page owned by the user that match the filter, the most recent first
+    fn list_incidents_for_user(&self, user: &User, filter: &IncidentFilter) -> Result<Vec<Incident>, String>;
 }
 
 impl TrackedPage {
@@ -125,6 +380,18 @@ impl TrackedPage {
             defacement_threshold,
             notified_of_current_breach: notified_of_current,
             tracked_page_type: tracked_type,
+            parser_type: ParserType::Browser,
+            screenshot_threshold: None,
+            comparator_data: BTreeMap::new(),
+            allowed_domains: Vec::new(),
+            normalisation_rules: Vec::new(),
+            ignored_selectors: Vec::new(),
+            sample_count: DEFAULT_SAMPLE_COUNT,
+            sample_interval: DEFAULT_SAMPLE_INTERVAL as u128,
+            adaptive_baseline: false,
+            volatility_mask: Vec::new(),
+            pinned_volatility_mask: Vec::new(),
+            excluded_volatility_mask: Vec::new(),
         }
     }
 
@@ -168,11 +435,89 @@ impl TrackedPage {
     pub fn set_index_interval(&mut self, index_interval: u128) {
         self.index_interval = index_interval;
     }
+    pub fn parser_type(&self) -> &ParserType {
+        &self.parser_type
+    }
+    pub fn set_parser_type(&mut self, parser_type: ParserType) {
+        self.parser_type = parser_type;
+    }
+    pub fn screenshot_threshold(&self) -> Option<f64> {
+        self.screenshot_threshold
+    }
+    pub fn set_screenshot_threshold(&mut self, screenshot_threshold: Option<f64>) {
+        self.screenshot_threshold = screenshot_threshold;
+    }
+    pub fn comparator_data(&self, comparator: &str) -> Option<&str> {
+        self.comparator_data.get(comparator).map(|data| data.as_str())
+    }
+    pub fn all_comparator_data(&self) -> &BTreeMap<String, String> {
+        &self.comparator_data
+    }
+    pub fn set_comparator_data(&mut self, comparator: &str, data: String) {
+        self.comparator_data.insert(String::from(comparator), data);
+    }
+    pub fn set_all_comparator_data(&mut self, comparator_data: BTreeMap<String, String>) {
+        self.comparator_data = comparator_data;
+    }
+    pub fn allowed_domains(&self) -> &Vec<String> {
+        &self.allowed_domains
+    }
+    pub fn set_allowed_domains(&mut self, allowed_domains: Vec<String>) {
+        self.allowed_domains = allowed_domains;
+    }
+    pub fn normalisation_rules(&self) -> &Vec<String> {
+        &self.normalisation_rules
+    }
+    pub fn set_normalisation_rules(&mut self, normalisation_rules: Vec<String>) {
+        self.normalisation_rules = normalisation_rules;
+    }
+    pub fn ignored_selectors(&self) -> &Vec<String> {
+        &self.ignored_selectors
+    }
+    pub fn set_ignored_selectors(&mut self, ignored_selectors: Vec<String>) {
+        self.ignored_selectors = ignored_selectors;
+    }
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        self.sample_count = sample_count;
+    }
+    pub fn sample_interval(&self) -> u128 {
+        self.sample_interval
+    }
+    pub fn set_sample_interval(&mut self, sample_interval: u128) {
+        self.sample_interval = sample_interval;
+    }
+    pub fn adaptive_baseline(&self) -> bool {
+        self.adaptive_baseline
+    }
+    pub fn set_adaptive_baseline(&mut self, adaptive_baseline: bool) {
+        self.adaptive_baseline = adaptive_baseline;
+    }
+    pub fn volatility_mask(&self) -> &Vec<String> {
+        &self.volatility_mask
+    }
+    pub fn set_volatility_mask(&mut self, volatility_mask: Vec<String>) {
+        self.volatility_mask = volatility_mask;
+    }
+    pub fn pinned_volatility_mask(&self) -> &Vec<String> {
+        &self.pinned_volatility_mask
+    }
+    pub fn set_pinned_volatility_mask(&mut self, pinned_volatility_mask: Vec<String>) {
+        self.pinned_volatility_mask = pinned_volatility_mask;
+    }
+    pub fn excluded_volatility_mask(&self) -> &Vec<String> {
+        &self.excluded_volatility_mask
+    }
+    pub fn set_excluded_volatility_mask(&mut self, excluded_volatility_mask: Vec<String>) {
+        self.excluded_volatility_mask = excluded_volatility_mask;
+    }
 }
 
 impl<T> StoredDom<T> {
     pub fn new(dom_id: u32, owning_page_id: u32, dom: T) -> Self {
-        Self { dom_id, owning_page_id, dom }
+        Self { dom_id, owning_page_id, dom, screenshot: None, accepted: false }
     }
 
     pub fn dom_id(&self) -> u32 {
@@ -188,6 +533,275 @@ impl<T> StoredDom<T> {
     pub fn set_dom(&mut self, dom: T) {
         self.dom = dom;
     }
+
+    pub fn screenshot(&self) -> Option<&Vec<u8>> {
+        self.screenshot.as_ref()
+    }
+
+    pub fn set_screenshot(&mut self, screenshot: Option<Vec<u8>>) {
+        self.screenshot = screenshot;
+    }
+
+    pub fn accepted(&self) -> bool {
+        self.accepted
+    }
+
+    pub fn set_accepted(&mut self, accepted: bool) {
+        self.accepted = accepted;
+    }
+}
+
+impl Approval {
+    pub fn new(approval_id: u32, page_id: u32, incident_id: u32, dom_id: u32, approver: String,
+               approval_time: u128, defacement_count: u32) -> Self {
+        Self { approval_id, page_id, incident_id, dom_id, approver, approval_time, defacement_count }
+    }
+
+    pub fn approval_id(&self) -> u32 {
+        self.approval_id
+    }
+    pub fn page_id(&self) -> u32 {
+        self.page_id
+    }
+    pub fn incident_id(&self) -> u32 {
+        self.incident_id
+    }
+    pub fn dom_id(&self) -> u32 {
+        self.dom_id
+    }
+    pub fn approver(&self) -> &str {
+        &self.approver
+    }
+    pub fn approval_time(&self) -> u128 {
+        self.approval_time
+    }
+    pub fn defacement_count(&self) -> u32 {
+        self.defacement_count
+    }
+}
+
+impl CheckRecord {
+    pub fn new(check_id: u32, page_id: u32, check_time: u128, fetch_duration: u128, http_status: Option<u16>,
+               verdict: Option<Verdict>, scores: BTreeMap<String, f64>) -> Self {
+        Self { check_id, page_id, check_time, fetch_duration, http_status, verdict, scores }
+    }
+
+    pub fn check_id(&self) -> u32 {
+        self.check_id
+    }
+    pub fn page_id(&self) -> u32 {
+        self.page_id
+    }
+    pub fn check_time(&self) -> u128 {
+        self.check_time
+    }
+    pub fn fetch_duration(&self) -> u128 {
+        self.fetch_duration
+    }
+    pub fn http_status(&self) -> Option<u16> {
+        self.http_status
+    }
+    pub fn verdict(&self) -> Option<Verdict> {
+        self.verdict
+    }
+    pub fn scores(&self) -> &BTreeMap<String, f64> {
+        &self.scores
+    }
+
+    ///Whether the page could be fetched and the server did not answer with an error status
+    pub fn is_up(&self) -> bool {
+        self.verdict.is_some() && self.http_status.is_none_or(|status| status < 400)
+    }
+}
+
+impl Incident {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(incident_id: u32, page_id: u32, user_id: u32, start_time: u128, end_time: Option<u128>,
+               triggering_results: Vec<ComparatorResult>, offending_dom: String, notified_contacts: Vec<u32>,
+               resolution: Option<IncidentResolution>) -> Self {
+        Self {
+            incident_id,
+            page_id,
+            user_id,
+            start_time,
+            end_time,
+            triggering_results,
+            offending_dom,
+            offending_screenshot: None,
+            notified_contacts,
+            resolution,
+        }
+    }
+
+    pub fn with_offending_screenshot(mut self, offending_screenshot: Option<Vec<u8>>) -> Self {
+        self.offending_screenshot = offending_screenshot;
+        self
+    }
+
+    pub fn incident_id(&self) -> u32 {
+        self.incident_id
+    }
+    pub fn page_id(&self) -> u32 {
+        self.page_id
+    }
+    pub fn user_id(&self) -> u32 {
+        self.user_id
+    }
+    pub fn start_time(&self) -> u128 {
+        self.start_time
+    }
+    pub fn end_time(&self) -> Option<u128> {
+        self.end_time
+    }
+    pub fn set_end_time(&mut self, end_time: Option<u128>) {
+        self.end_time = end_time;
+    }
+    pub fn triggering_results(&self) -> &Vec<ComparatorResult> {
+        &self.triggering_results
+    }
+    pub fn offending_dom(&self) -> &str {
+        &self.offending_dom
+    }
+    pub fn offending_screenshot(&self) -> Option<&Vec<u8>> {
+        self.offending_screenshot.as_ref()
+    }
+    pub fn notified_contacts(&self) -> &Vec<u32> {
+        &self.notified_contacts
+    }
+    pub fn set_notified_contacts(&mut self, notified_contacts: Vec<u32>) {
+        self.notified_contacts = notified_contacts;
+    }
+    pub fn resolution(&self) -> Option<IncidentResolution> {
+        self.resolution
+    }
+    pub fn set_resolution(&mut self, resolution: Option<IncidentResolution>) {
+        self.resolution = resolution;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.end_time.is_none()
+    }
+}
+
+impl IncidentFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open(&self) -> Option<bool> {
+        self.open
+    }
+    pub fn set_open(&mut self, open: Option<bool>) {
+        self.open = open;
+    }
+    pub fn resolution(&self) -> Option<IncidentResolution> {
+        self.resolution
+    }
+    pub fn set_resolution(&mut self, resolution: Option<IncidentResolution>) {
+        self.resolution = resolution;
+    }
+    pub fn started_after(&self) -> Option<u128> {
+        self.started_after
+    }
+    pub fn set_started_after(&mut self, started_after: Option<u128>) {
+        self.started_after = started_after;
+    }
+    pub fn started_before(&self) -> Option<u128> {
+        self.started_before
+    }
+    pub fn set_started_before(&mut self, started_before: Option<u128>) {
+        self.started_before = started_before;
+    }
+}
+
+impl OutboxEntry {
+    pub fn new(entry_id: u32, kind: NotificationKind, page_id: u32, user_id: u32, incident_id: Option<u32>,
+               contact_id: u32, channel: String) -> Self {
+        Self {
+            entry_id,
+            kind,
+            page_id,
+            user_id,
+            incident_id,
+            contact_id,
+            channel,
+            stored_dom_id: None,
+            result: None,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            created_time: 0,
+            next_attempt: 0,
+            last_error: None,
+        }
+    }
+
+    ///Add what the defacement notification is built from
+    pub fn with_defacement(mut self, stored_dom_id: u32, result: AggregatedResult) -> Self {
+        self.stored_dom_id = Some(stored_dom_id);
+        self.result = Some(result);
+        self
+    }
+
+    pub fn entry_id(&self) -> u32 {
+        self.entry_id
+    }
+    pub fn set_entry_id(&mut self, entry_id: u32) {
+        self.entry_id = entry_id;
+    }
+    pub fn kind(&self) -> NotificationKind {
+        self.kind
+    }
+    pub fn page_id(&self) -> u32 {
+        self.page_id
+    }
+    pub fn user_id(&self) -> u32 {
+        self.user_id
+    }
+    pub fn incident_id(&self) -> Option<u32> {
+        self.incident_id
+    }
+    pub fn contact_id(&self) -> u32 {
+        self.contact_id
+    }
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+    pub fn stored_dom_id(&self) -> Option<u32> {
+        self.stored_dom_id
+    }
+    pub fn result(&self) -> Option<&AggregatedResult> {
+        self.result.as_ref()
+    }
+    pub fn status(&self) -> OutboxStatus {
+        self.status
+    }
+    pub fn set_status(&mut self, status: OutboxStatus) {
+        self.status = status;
+    }
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+    pub fn set_attempts(&mut self, attempts: u32) {
+        self.attempts = attempts;
+    }
+    pub fn created_time(&self) -> u128 {
+        self.created_time
+    }
+    pub fn set_created_time(&mut self, created_time: u128) {
+        self.created_time = created_time;
+    }
+    pub fn next_attempt(&self) -> u128 {
+        self.next_attempt
+    }
+    pub fn set_next_attempt(&mut self, next_attempt: u128) {
+        self.next_attempt = next_attempt;
+    }
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+    pub fn set_last_error(&mut self, last_error: Option<String>) {
+        self.last_error = last_error;
+    }
 }
 
 impl User {
@@ -197,9 +811,20 @@ impl User {
     pub fn user(&self) -> &str {
         &self.user
     }
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+    pub fn set_locale(&mut self, locale: Option<String>) {
+        self.locale = locale;
+    }
 
     pub fn new(user_id: u32, user: String) -> Self {
-        Self { user_id, user }
+        Self { user_id, user, locale: None }
+    }
+
+    pub fn with_locale(mut self, locale: Option<String>) -> Self {
+        self.locale = locale;
+        self
     }
 }
 
@@ -212,4 +837,69 @@ pub fn tracked_page_type_to_str(page_type: &TrackedPageType) -> &str {
             "Dynamic"
         }
     }
-}
\ No newline at end of file
+}
+
+pub fn parser_type_to_str(parser_type: &ParserType) -> &str {
+    match parser_type {
+        ParserType::Browser => {
+            "Browser"
+        }
+        ParserType::Http => {
+            "Http"
+        }
+    }
+}
+pub fn incident_resolution_to_str(resolution: &IncidentResolution) -> &str {
+    match resolution {
+        IncidentResolution::Recovered => {
+            "Recovered"
+        }
+        IncidentResolution::Accepted => {
+            "Accepted"
+        }
+        IncidentResolution::FalsePositive => {
+            "FalsePositive"
+        }
+    }
+}
+
+pub fn incident_resolution_from_str(resolution: &str) -> Option<IncidentResolution> {
+    match resolution {
+        "Recovered" => { Some(IncidentResolution::Recovered) }
+        "Accepted" => { Some(IncidentResolution::Accepted) }
+        "FalsePositive" => { Some(IncidentResolution::FalsePositive) }
+        _ => { None }
+    }
+}
+
+pub fn notification_kind_to_str(kind: &NotificationKind) -> &str {
+    match kind {
+        NotificationKind::Defacement => { "Defacement" }
+        NotificationKind::Recovery => { "Recovery" }
+    }
+}
+
+pub fn notification_kind_from_str(kind: &str) -> Option<NotificationKind> {
+    match kind {
+        "Defacement" => { Some(NotificationKind::Defacement) }
+        "Recovery" => { Some(NotificationKind::Recovery) }
+        _ => { None }
+    }
+}
+
+pub fn outbox_status_to_str(status: &OutboxStatus) -> &str {
+    match status {
+        OutboxStatus::Pending => { "Pending" }
+        OutboxStatus::Delivered => { "Delivered" }
+        OutboxStatus::DeadLetter => { "DeadLetter" }
+    }
+}
+
+pub fn outbox_status_from_str(status: &str) -> Option<OutboxStatus> {
+    match status {
+        "Pending" => { Some(OutboxStatus::Pending) }
+        "Delivered" => { Some(OutboxStatus::Delivered) }
+        "DeadLetter" => { Some(OutboxStatus::DeadLetter) }
+        _ => { None }
+    }
+}
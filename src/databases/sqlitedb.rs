@@ -2,22 +2,35 @@ extern crate r2d2;
 extern crate r2d2_sqlite;
 extern crate rusqlite;
 
+use std::collections::BTreeMap;
 use std::fmt::{Display, format};
 use std::string::ToString;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::{Error, params, Row, Rows, ToSql};
+use rusqlite::{Connection, Error, params, params_from_iter, Row, Rows, ToSql};
 use rusqlite::types::FromSql;
 
 use crate::communication::CommData::Email;
+use crate::comparators::{ComparatorResult, CompareResult, verdict_from_str, verdict_to_str};
+use crate::comparators::aggregation::AggregatedResult;
+use crate::comparators::baseline::{BaselineModel, DEFAULT_THRESHOLD_POLICY};
 use crate::databases::*;
 
 const TRACKED_PAGES_TABLE: &str = "TRACKED_PAGES";
 const TRACKED_PAGES_DOMS: &str = "PAGES";
 const USERS: &str = "USERS";
 const USER_CONTACTS: &str = "CONTACTS";
+const CHECK_VERDICTS: &str = "CHECK_VERDICTS";
+const COMPARATOR_VERDICTS: &str = "COMPARATOR_VERDICTS";
+const APPROVALS: &str = "APPROVALS";
+const INCIDENTS: &str = "INCIDENTS";
+const CHECK_LOG: &str = "CHECK_LOG";
+const OUTBOX: &str = "OUTBOX";
+//The outbox of older versions also has the copies of the doms, so its columns are always selected by name
+const OUTBOX_COLUMNS: &str = "rowid, KIND, PAGE_ID, USER_ID, INCIDENT_ID, CONTACT_ID, CHANNEL, STORED_DOM_ID, RESULT, \
+STATUS, ATTEMPTS, CREATED_TIME, NEXT_ATTEMPT, LAST_ERROR";
 const IN_MEMORY: &str = ":memory:";
 const PAGE_STORAGE: &str = "pages_db";
 
@@ -83,6 +96,88 @@ impl<T> SQLLiteDefacementDB<T> where T: Display + FromSql + ToSql {
 
         connection.execute(format!("CREATE INDEX IF NOT EXISTS USER_IND ON {}(USER_ID)",
                                    USER_CONTACTS).as_str(), params![]).unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "PARSER_TYPE",
+                              "varchar(25) NOT NULL DEFAULT 'Browser'").unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "SCREENSHOT_THRESHOLD", "REAL").unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_DOMS, "SCREENSHOT", "BLOB").unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "COMPARATOR_DATA", "TEXT").unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "ALLOWED_DOMAINS", "TEXT").unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "NORMALISATION_RULES", "TEXT").unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "IGNORED_SELECTORS", "TEXT").unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "SAMPLE_COUNT",
+                              format!("INTEGER NOT NULL DEFAULT {}", DEFAULT_SAMPLE_COUNT).as_str()).unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "SAMPLE_INTERVAL",
+                              format!("INTEGER NOT NULL DEFAULT {}", DEFAULT_SAMPLE_INTERVAL).as_str()).unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "ADAPTIVE_BASELINE", "INTEGER NOT NULL DEFAULT 0").unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "VOLATILITY_MASK", "TEXT").unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "PINNED_VOLATILITY_MASK", "TEXT").unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_TABLE, "EXCLUDED_VOLATILITY_MASK", "TEXT").unwrap();
+
+        add_column_if_missing(&connection, TRACKED_PAGES_DOMS, "ACCEPTED", "INTEGER NOT NULL DEFAULT 0").unwrap();
+
+        add_column_if_missing(&connection, USERS, "LOCALE", "varchar(25)").unwrap();
+
+        connection.execute(format!("CREATE TABLE IF NOT EXISTS {} (rowid INTEGER PRIMARY KEY, PAGE_ID INTEGER NOT NULL, \
+        CHECK_TIME INTEGER NOT NULL, POLICY varchar(25) NOT NULL, VERDICT varchar(25) NOT NULL, SCORE REAL NOT NULL)",
+                                   CHECK_VERDICTS).as_str(), []).unwrap();
+
+        connection.execute(format!("CREATE INDEX IF NOT EXISTS CHECK_PAGE_IND ON {}(PAGE_ID)",
+                                   CHECK_VERDICTS).as_str(), params![]).unwrap();
+
+        connection.execute(format!("CREATE TABLE IF NOT EXISTS {} (rowid INTEGER PRIMARY KEY, CHECK_ID INTEGER NOT NULL, \
+        COMPARATOR varchar(50) NOT NULL, VERDICT varchar(25) NOT NULL, CONFIDENCE REAL NOT NULL, EXPLANATION TEXT)",
+                                   COMPARATOR_VERDICTS).as_str(), []).unwrap();
+
+        connection.execute(format!("CREATE INDEX IF NOT EXISTS CHECK_ID_IND ON {}(CHECK_ID)",
+                                   COMPARATOR_VERDICTS).as_str(), params![]).unwrap();
+
+        connection.execute(format!("CREATE TABLE IF NOT EXISTS {} (rowid INTEGER PRIMARY KEY, PAGE_ID INTEGER NOT NULL, \
+        INCIDENT_ID INTEGER NOT NULL, DOM_ID INTEGER NOT NULL, APPROVER TEXT NOT NULL, APPROVAL_TIME INTEGER NOT NULL, DEFACEMENT_COUNT INTEGER NOT NULL)",
+                                   APPROVALS).as_str(), []).unwrap();
+
+        connection.execute(format!("CREATE INDEX IF NOT EXISTS APPROVAL_PAGE_IND ON {}(PAGE_ID)",
+                                   APPROVALS).as_str(), params![]).unwrap();
+
+        connection.execute(format!("CREATE TABLE IF NOT EXISTS {} (rowid INTEGER PRIMARY KEY, PAGE_ID INTEGER NOT NULL, \
+        USER_ID INTEGER NOT NULL, START_TIME INTEGER NOT NULL, END_TIME INTEGER, TRIGGERING_RESULTS TEXT, OFFENDING_DOM TEXT, \
+        NOTIFIED_CONTACTS TEXT, RESOLUTION varchar(25))", INCIDENTS).as_str(), []).unwrap();
+
+        connection.execute(format!("CREATE INDEX IF NOT EXISTS INCIDENT_PAGE_IND ON {}(PAGE_ID)",
+                                   INCIDENTS).as_str(), params![]).unwrap();
+
+        connection.execute(format!("CREATE INDEX IF NOT EXISTS INCIDENT_USER_IND ON {}(USER_ID)",
+                                   INCIDENTS).as_str(), params![]).unwrap();
+
+        add_column_if_missing(&connection, INCIDENTS, "OFFENDING_SCREENSHOT", "BLOB").unwrap();
+
+        connection.execute(format!("CREATE TABLE IF NOT EXISTS {} (rowid INTEGER PRIMARY KEY, PAGE_ID INTEGER NOT NULL, \
+        CHECK_TIME INTEGER NOT NULL, FETCH_DURATION INTEGER NOT NULL, HTTP_STATUS INTEGER, VERDICT varchar(25), SCORES TEXT)",
+                                   CHECK_LOG).as_str(), []).unwrap();
+
+        connection.execute(format!("CREATE INDEX IF NOT EXISTS CHECK_LOG_PAGE_TIME_IND ON {}(PAGE_ID, CHECK_TIME)",
+                                   CHECK_LOG).as_str(), params![]).unwrap();
+
+        connection.execute(format!("CREATE TABLE IF NOT EXISTS {} (rowid INTEGER PRIMARY KEY, KIND varchar(25) NOT NULL, \
+        PAGE_ID INTEGER NOT NULL, USER_ID INTEGER NOT NULL, INCIDENT_ID INTEGER, CONTACT_ID INTEGER NOT NULL, \
+        CHANNEL varchar(50) NOT NULL, STORED_DOM_ID INTEGER, RESULT TEXT, \
+        STATUS varchar(25) NOT NULL, ATTEMPTS INTEGER NOT NULL DEFAULT 0, CREATED_TIME INTEGER NOT NULL, \
+        NEXT_ATTEMPT INTEGER NOT NULL, LAST_ERROR TEXT)", OUTBOX).as_str(), []).unwrap();
+
+        connection.execute(format!("CREATE INDEX IF NOT EXISTS OUTBOX_DUE_IND ON {}(STATUS, NEXT_ATTEMPT)",
+                                   OUTBOX).as_str(), params![]).unwrap();
     }
 
     fn read_doms_for_page_id(&self, page_id: u32) -> Result<Vec<StoredDom<T>>, String> {
@@ -97,9 +192,7 @@ impl<T> SQLLiteDefacementDB<T> where T: Display + FromSql + ToSql {
                     let mut doms = Vec::new();
 
                     while let Some(row) = state.next().unwrap() {
-                        doms.push(StoredDom::new(row.get(0).unwrap(),
-                                                 row.get(1).unwrap(),
-                                                 row.get(2).unwrap()));
+                        doms.push(self.parse_dom_from_row(row));
                     }
 
                     Ok(doms)
@@ -158,17 +251,134 @@ impl<T> SQLLiteDefacementDB<T> where T: Display + FromSql + ToSql {
         let tracked_type: String = row.get(9)?;
 
         if tracked_type.eq_ignore_ascii_case("Dynamic") {
-            let diff_str: String = row.get(10)?;
+            let model_str: Option<String> = row.get(10)?;
+
+            //Pages analysed before the baseline model existed only stored the threshold, which parse accepts
+            let model = model_str.and_then(|model| BaselineModel::parse(model.as_str()).ok())
+                .unwrap_or_else(|| BaselineModel::unlearned(DEFAULT_THRESHOLD_POLICY));
+
+            tracked_page_type = TrackedPageType::Dynamic(model);
+        }
+
+        let mut page = TrackedPage::new(page_id, page_url, owning_user_id, last_time_checked as u128,
+                                        last_time_indexed as u128, index_interval as u128,
+                                        defacement_count, defacement_threshold, notified_current != 0,
+                                        tracked_page_type);
+
+        let parser_type: String = row.get(11)?;
+
+        if parser_type.eq_ignore_ascii_case("Http") {
+            page.set_parser_type(ParserType::Http);
+        }
+
+        page.set_screenshot_threshold(row.get(12)?);
+
+        let comparator_data: Option<String> = row.get(13)?;
+
+        if let Some(comparator_data) = comparator_data {
+            page.set_all_comparator_data(serde_json::from_str(comparator_data.as_str()).unwrap_or_default());
+        }
+
+        let allowed_domains: Option<String> = row.get(14)?;
+
+        if let Some(allowed_domains) = allowed_domains {
+            page.set_allowed_domains(serde_json::from_str(allowed_domains.as_str()).unwrap_or_default());
+        }
+
+        let normalisation_rules: Option<String> = row.get(15)?;
+
+        if let Some(normalisation_rules) = normalisation_rules {
+            page.set_normalisation_rules(serde_json::from_str(normalisation_rules.as_str()).unwrap_or_default());
+        }
+
+        let ignored_selectors: Option<String> = row.get(16)?;
+
+        if let Some(ignored_selectors) = ignored_selectors {
+            page.set_ignored_selectors(serde_json::from_str(ignored_selectors.as_str()).unwrap_or_default());
+        }
+
+        page.set_sample_count(row.get(17)?);
+
+        let sample_interval: u64 = row.get(18)?;
+
+        page.set_sample_interval(sample_interval as u128);
+
+        let adaptive_baseline: u32 = row.get(19)?;
+
+        page.set_adaptive_baseline(adaptive_baseline != 0);
+
+        let volatility_mask: Option<String> = row.get(20)?;
+
+        if let Some(volatility_mask) = volatility_mask {
+            page.set_volatility_mask(serde_json::from_str(volatility_mask.as_str()).unwrap_or_default());
+        }
+
+        let pinned_volatility_mask: Option<String> = row.get(21)?;
+
+        if let Some(pinned_volatility_mask) = pinned_volatility_mask {
+            page.set_pinned_volatility_mask(serde_json::from_str(pinned_volatility_mask.as_str()).unwrap_or_default());
+        }
+
+        let excluded_volatility_mask: Option<String> = row.get(22)?;
+
+        if let Some(excluded_volatility_mask) = excluded_volatility_mask {
+            page.set_excluded_volatility_mask(serde_json::from_str(excluded_volatility_mask.as_str()).unwrap_or_default());
+        }
+
+        Ok(page)
+    }
+
+    fn parse_dom_from_row(&self, row: &Row) -> StoredDom<T> {
+        let mut dom = StoredDom::new(row.get(0).unwrap(),
+                                     row.get(1).unwrap(),
+                                     row.get(2).unwrap());
+
+        dom.set_screenshot(row.get(3).unwrap());
+
+        let accepted: u32 = row.get(4).unwrap();
+
+        dom.set_accepted(accepted != 0);
+
+        dom
+    }
+
 
-            let diff: f64 = diff_str.parse::<f64>().unwrap();
+    ///List the incidents where the given column (PAGE_ID or USER_ID) has the given ID and that match the filter
+    fn list_incidents_for(&self, column: &str, id: u32, filter: &IncidentFilter) -> Result<Vec<Incident>, String> {
+        let mut conditions = vec![format!("{}=?", column)];
+        let mut values: Vec<Box<dyn ToSql>> = vec![Box::new(id)];
 
-            tracked_page_type = TrackedPageType::Dynamic(diff);
+        if let Some(open) = filter.open() {
+            conditions.push(String::from(if open { "END_TIME IS NULL" } else { "END_TIME IS NOT NULL" }));
         }
 
-        Ok(TrackedPage::new(page_id, page_url, owning_user_id, last_time_checked as u128,
-                            last_time_indexed as u128, index_interval as u128,
-                            defacement_count, defacement_threshold, notified_current != 0,
-                            tracked_page_type))
+        if let Some(resolution) = filter.resolution() {
+            conditions.push(String::from("RESOLUTION=?"));
+            values.push(Box::new(String::from(incident_resolution_to_str(&resolution))));
+        }
+
+        if let Some(started_after) = filter.started_after() {
+            conditions.push(String::from("START_TIME>=?"));
+            values.push(Box::new(started_after as u64));
+        }
+
+        if let Some(started_before) = filter.started_before() {
+            conditions.push(String::from("START_TIME<?"));
+            values.push(Box::new(started_before as u64));
+        }
+
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("SELECT * FROM {} WHERE {} ORDER BY rowid DESC", INCIDENTS,
+                                                       conditions.join(" AND ")).as_str()).unwrap();
+
+        let incidents = statement.query_map(params_from_iter(values.iter()), parse_incident_from_row)
+            .and_then(|rows| rows.collect::<Result<Vec<Incident>, Error>>());
+
+        match incidents {
+            Ok(incidents) => { Ok(incidents) }
+            Err(e) => { Err(e.to_string()) }
+        }
     }
 
     fn crawl_all_pages_in_result_set(&self, rows: &mut Rows) -> Result<Vec<TrackedPage>, Error> {
@@ -233,8 +443,29 @@ impl<T> SQLLiteDefacementDB<T> where T: Display + FromSql + ToSql {
 
         let mut comm: Option<CommData> = Option::None;
 
+        let contact: String = row.get(3).unwrap();
+
+        //The contacts that need more than one value store them as JSON
+        let values: serde_json::Value = serde_json::from_str(contact.as_str()).unwrap_or_default();
+
+        let value = |key: &str| values[key].as_str().map(String::from);
+
         if comm_type.eq("EMAIL") {
-            comm = Some(Email(row.get(3).unwrap()))
+            comm = Some(Email(contact))
+        } else if comm_type.eq("WEBHOOK") {
+            comm = value("url").map(|url| CommData::Webhook { url, secret: value("secret") });
+        } else if comm_type.eq("SLACK") {
+            comm = Some(CommData::Slack(contact))
+        } else if comm_type.eq("MATTERMOST") {
+            comm = Some(CommData::Mattermost(contact))
+        } else if comm_type.eq("MATRIX") {
+            if let (Some(homeserver), Some(room_id), Some(access_token)) = (value("homeserver"), value("room_id"), value("access_token")) {
+                comm = Some(CommData::Matrix { homeserver, room_id, access_token });
+            }
+        } else if comm_type.eq("TELEGRAM") {
+            if let (Some(bot_token), Some(chat_id)) = (value("bot_token"), value("chat_id")) {
+                comm = Some(CommData::Telegram { bot_token, chat_id });
+            }
         }
 
         return match comm {
@@ -248,6 +479,132 @@ impl<T> SQLLiteDefacementDB<T> where T: Display + FromSql + ToSql {
     }
 }
 
+fn comparator_results_to_json(results: &[ComparatorResult]) -> String {
+    serde_json::Value::Array(results.iter().map(|result| {
+        serde_json::json!({
+            "comparator": result.comparator(),
+            "verdict": verdict_to_str(&result.result().verdict()),
+            "confidence": result.result().confidence(),
+            "explanation": result.result().explanation(),
+            "score": result.result().score(),
+        })
+    }).collect()).to_string()
+}
+
+fn comparator_results_from_json(results: &str) -> Vec<ComparatorResult> {
+    let results: serde_json::Value = serde_json::from_str(results).unwrap_or_default();
+
+    results.as_array().map(|results| {
+        results.iter().map(|result| {
+            let mut compare_result = CompareResult::new(verdict_from_str(result["verdict"].as_str().unwrap_or_default()),
+                                                        result["confidence"].as_f64().unwrap_or_default(),
+                                                        String::from(result["explanation"].as_str().unwrap_or_default()));
+
+            if let Some(score) = result["score"].as_f64() {
+                compare_result = compare_result.with_score(score);
+            }
+
+            ComparatorResult::new(result["comparator"].as_str().unwrap_or_default(), compare_result)
+        }).collect()
+    }).unwrap_or_default()
+}
+
+fn aggregated_result_to_json(result: &AggregatedResult) -> String {
+    serde_json::json!({
+        "policy": result.policy(),
+        "verdict": verdict_to_str(&result.verdict()),
+        "score": result.score(),
+        "breakdown": comparator_results_to_json(result.breakdown()),
+    }).to_string()
+}
+
+fn aggregated_result_from_json(result: &str) -> Option<AggregatedResult> {
+    let result: serde_json::Value = serde_json::from_str(result).ok()?;
+
+    Some(AggregatedResult::new(String::from(result["policy"].as_str()?), verdict_from_str(result["verdict"].as_str()?),
+                               result["score"].as_f64()?,
+                               comparator_results_from_json(result["breakdown"].as_str().unwrap_or_default())))
+}
+
+///Parse the outbox entry from a row with the OUTBOX_COLUMNS
+fn parse_outbox_entry_from_row(row: &Row) -> Result<OutboxEntry, Error> {
+    let kind: String = row.get(1)?;
+    let stored_dom_id: Option<u32> = row.get(7)?;
+    let result: Option<String> = row.get(8)?;
+    let status: String = row.get(9)?;
+    let created_time: u64 = row.get(11)?;
+    let next_attempt: u64 = row.get(12)?;
+
+    let mut entry = OutboxEntry::new(row.get(0)?, notification_kind_from_str(kind.as_str()).unwrap_or(NotificationKind::Defacement),
+                                     row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?);
+
+    let result = result.and_then(|result| aggregated_result_from_json(result.as_str()));
+
+    if let (Some(stored_dom_id), Some(result)) = (stored_dom_id, result) {
+        entry = entry.with_defacement(stored_dom_id, result);
+    }
+
+    entry.set_status(outbox_status_from_str(status.as_str()).unwrap_or(OutboxStatus::DeadLetter));
+    entry.set_attempts(row.get(10)?);
+    entry.set_created_time(created_time as u128);
+    entry.set_next_attempt(next_attempt as u128);
+    entry.set_last_error(row.get(13)?);
+
+    Ok(entry)
+}
+
+fn parse_incident_from_row(row: &Row) -> Result<Incident, Error> {
+    let start_time: u64 = row.get(3)?;
+    let end_time: Option<u64> = row.get(4)?;
+    let triggering_results: Option<String> = row.get(5)?;
+    let offending_dom: Option<String> = row.get(6)?;
+    let notified_contacts: Option<String> = row.get(7)?;
+    let resolution: Option<String> = row.get(8)?;
+
+    Ok(Incident::new(row.get(0)?, row.get(1)?, row.get(2)?, start_time as u128,
+                     end_time.map(|end_time| end_time as u128),
+                     triggering_results.map(|results| comparator_results_from_json(results.as_str())).unwrap_or_default(),
+                     offending_dom.unwrap_or_default(),
+                     notified_contacts.and_then(|contacts| serde_json::from_str(contacts.as_str()).ok()).unwrap_or_default(),
+                     resolution.and_then(|resolution| incident_resolution_from_str(resolution.as_str())))
+        .with_offending_screenshot(row.get(9)?))
+}
+
+fn parse_user_from_row(row: &Row) -> User {
+    User::new(row.get(0).unwrap(), row.get(1).unwrap()).with_locale(row.get(2).unwrap())
+}
+
+fn parse_check_record_from_row(row: &Row) -> Result<CheckRecord, Error> {
+    let check_time: u64 = row.get(2)?;
+    let fetch_duration: u64 = row.get(3)?;
+    let verdict: Option<String> = row.get(5)?;
+    let scores: Option<String> = row.get(6)?;
+
+    Ok(CheckRecord::new(row.get(0)?, row.get(1)?, check_time as u128, fetch_duration as u128, row.get(4)?,
+                        verdict.map(|verdict| verdict_from_str(verdict.as_str())),
+                        scores.and_then(|scores| serde_json::from_str(scores.as_str()).ok()).unwrap_or_default()))
+}
+
+/// Tables created by older versions of the program do not have the columns that were added since,
+/// so we add them to the end of the table when they are missing
+fn add_column_if_missing(connection: &Connection, table: &str, column: &str, definition: &str) -> Result<(), Error> {
+    let mut statement = connection.prepare(format!("PRAGMA table_info({})", table).as_str())?;
+
+    let mut rows = statement.query([])?;
+
+    while let Some(row) = rows.next()? {
+        let column_name: String = row.get(1)?;
+
+        if column_name.eq_ignore_ascii_case(column) {
+            return Ok(());
+        }
+    }
+
+    connection.execute(format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition).as_str(), [])?;
+
+    Ok(())
+}
+
 impl<T> WebsiteDefacementDB<T> for SQLLiteDefacementDB<T> where T: Display + Debug + FromSql + ToSql + Send + Sync {
     fn insert_tracked_page(&self, page: &str, user_id: u32) -> Result<TrackedPage, String> {
         let write_guard = self.write_sql_conn();
@@ -350,21 +707,33 @@ impl<T> WebsiteDefacementDB<T> for SQLLiteDefacementDB<T> where T: Display + Deb
         let connection = self.get_sql_conn();
 
         let mut statement = connection.prepare(format!("UPDATE {} SET PAGE_TYPE=?,\
-         PAGE_TRACKING_DATA=?,LAST_TIME_INDEXED=?,INDEX_INTERVAL=? WHERE rowid=?", TRACKED_PAGES_TABLE).as_str()).unwrap();
+         PAGE_TRACKING_DATA=?,LAST_TIME_INDEXED=?,INDEX_INTERVAL=?,PARSER_TYPE=?,SCREENSHOT_THRESHOLD=?,\
+         COMPARATOR_DATA=?,ALLOWED_DOMAINS=?,NORMALISATION_RULES=?,\
+         IGNORED_SELECTORS=?,SAMPLE_COUNT=?,SAMPLE_INTERVAL=?,ADAPTIVE_BASELINE=?,\
+         VOLATILITY_MASK=?,PINNED_VOLATILITY_MASK=?,EXCLUDED_VOLATILITY_MASK=? WHERE rowid=?", TRACKED_PAGES_TABLE).as_str()).unwrap();
 
         let mut page_type_data = String::from("NULL");
 
         match page.tracked_page_type() {
             TrackedPageType::Static => {}
-            TrackedPageType::Dynamic(diff_threshold) => {
-                page_type_data = format!("{}", diff_threshold);
+            TrackedPageType::Dynamic(model) => {
+                page_type_data = model.to_json();
             }
         }
 
         let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
 
         return match statement.execute(params![tracked_page_type_to_str(page.tracked_page_type()),
-        page_type_data,current_time as u64, page.index_interval() as u64, page.page_id()]) {
+        page_type_data,current_time as u64, page.index_interval() as u64,
+        parser_type_to_str(page.parser_type()), page.screenshot_threshold(),
+        serde_json::to_string(page.all_comparator_data()).unwrap(),
+        serde_json::to_string(page.allowed_domains()).unwrap(),
+        serde_json::to_string(page.normalisation_rules()).unwrap(),
+        serde_json::to_string(page.ignored_selectors()).unwrap(), page.sample_count(),
+        page.sample_interval() as u64, page.adaptive_baseline(),
+        serde_json::to_string(page.volatility_mask()).unwrap(),
+        serde_json::to_string(page.pinned_volatility_mask()).unwrap(),
+        serde_json::to_string(page.excluded_volatility_mask()).unwrap(), page.page_id()]) {
             Ok(changed) => {
                 if changed > 0 {
                     Ok(true)
@@ -378,6 +747,26 @@ impl<T> WebsiteDefacementDB<T> for SQLLiteDefacementDB<T> where T: Display + Deb
         };
     }
 
+    fn update_baseline_for_page(&self, page: &TrackedPage) -> Result<(), String> {
+        let model = match page.tracked_page_type() {
+            TrackedPageType::Dynamic(model) => { model }
+            TrackedPageType::Static => {
+                return Err(format!("Page {} with ID {} is static and has no baseline", page.page_url(), page.page_id()));
+            }
+        };
+
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("UPDATE {} SET PAGE_TRACKING_DATA=?,COMPARATOR_DATA=? WHERE rowid=?",
+                                                       TRACKED_PAGES_TABLE).as_str()).unwrap();
+
+        match statement.execute(params![model.to_json(), serde_json::to_string(page.all_comparator_data()).unwrap(),
+        page.page_id()]) {
+            Ok(_) => { Ok(()) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
     fn increment_defacement_count(&self, page: &mut TrackedPage, notified: bool) -> Result<(), String> {
         let connection = self.get_sql_conn();
 
@@ -435,23 +824,36 @@ impl<T> WebsiteDefacementDB<T> for SQLLiteDefacementDB<T> where T: Display + Deb
     }
 
     fn del_tracked_page(&self, page: TrackedPage) -> Result<bool, String> {
-        let write_guard = self.get_sql_conn();
+        let mut connection = self.write_sql_conn();
 
-        let mut statement = write_guard.prepare(
-            format!("DELETE FROM {} WHERE rowid=?", TRACKED_PAGES_TABLE).as_str()).unwrap();
+        let transaction = match connection.transaction() {
+            Ok(transaction) => { transaction }
+            Err(e) => { return Err(e.to_string()); }
+        };
 
-        match statement.execute(params![page.page_id()]) {
-            Ok(state) => {
-                if state > 0 {
-                    Ok(true)
-                } else {
-                    Ok(false)
-                }
-            }
-            Err(e) => {
-                Err(e.to_string())
+        //The rows that belong to the page go with it, pending notifications included
+        if let Err(e) = transaction.execute(format!("DELETE FROM {} WHERE CHECK_ID IN (SELECT rowid FROM {} WHERE PAGE_ID=?)",
+                                                    COMPARATOR_VERDICTS, CHECK_VERDICTS).as_str(), params![page.page_id()]) {
+            return Err(e.to_string());
+        }
+
+        for table in [CHECK_VERDICTS, CHECK_LOG, APPROVALS, OUTBOX, INCIDENTS, TRACKED_PAGES_DOMS] {
+            if let Err(e) = transaction.execute(format!("DELETE FROM {} WHERE PAGE_ID=?", table).as_str(),
+                                                params![page.page_id()]) {
+                return Err(e.to_string());
             }
         }
+
+        let deleted = match transaction.execute(format!("DELETE FROM {} WHERE rowid=?", TRACKED_PAGES_TABLE).as_str(),
+                                                params![page.page_id()]) {
+            Ok(deleted) => { deleted }
+            Err(e) => { return Err(e.to_string()); }
+        };
+
+        match transaction.commit() {
+            Ok(_) => { Ok(deleted > 0) }
+            Err(e) => { Err(e.to_string()) }
+        }
     }
 
 
@@ -469,9 +871,7 @@ impl<T> WebsiteDefacementDB<T> for SQLLiteDefacementDB<T> where T: Display + Deb
                 match rows.next() {
                     Ok(row) => {
                         if let Some(row_i) = row {
-                            Ok(StoredDom::new(row_i.get(0).unwrap(),
-                                              row_i.get(1).unwrap(),
-                                              row_i.get(2).unwrap()))
+                            Ok(self.parse_dom_from_row(row_i))
                         } else {
                             Err(String::from("Could not find dom for page"))
                         }
@@ -483,6 +883,18 @@ impl<T> WebsiteDefacementDB<T> for SQLLiteDefacementDB<T> where T: Display + Deb
         };
     }
 
+    fn read_dom_for_id(&self, dom_id: u32) -> Result<StoredDom<T>, String> {
+        let conn = self.get_sql_conn();
+
+        let mut statement = conn.prepare(format!("SELECT * FROM {} WHERE rowid=?", TRACKED_PAGES_DOMS).as_str()).unwrap();
+
+        match statement.query_row(params![dom_id], |row| Ok(self.parse_dom_from_row(row))) {
+            Ok(dom) => { Ok(dom) }
+            Err(Error::QueryReturnedNoRows) => { Err(format!("Could not find the dom with ID {}", dom_id)) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
     fn insert_dom_for_page(&self, page: &TrackedPage, page_dom: T) -> Result<StoredDom<T>, String> {
         let write_guard = self.write_sql_conn();
 
@@ -523,6 +935,25 @@ impl<T> WebsiteDefacementDB<T> for SQLLiteDefacementDB<T> where T: Display + Deb
         }
     }
 
+    fn update_screenshot_for_dom(&self, dom: &mut StoredDom<T>, screenshot: Vec<u8>) -> Result<(), String> {
+        let guard = self.get_sql_conn();
+
+        let mut update = guard
+            .prepare(format!("UPDATE {} SET SCREENSHOT=? WHERE rowid=?", TRACKED_PAGES_DOMS).as_str())
+            .unwrap();
+
+        match update.execute(params![screenshot, dom.dom_id()]) {
+            Ok(_) => {
+                dom.set_screenshot(Some(screenshot));
+
+                Ok(())
+            }
+            Err(e) => {
+                Err(e.to_string())
+            }
+        }
+    }
+
     fn delete_dom_for_page(&self, _page: &TrackedPage, dom: StoredDom<T>) -> Result<bool, String> {
         let read_guard = self.get_sql_conn();
 
@@ -536,100 +967,564 @@ impl<T> WebsiteDefacementDB<T> for SQLLiteDefacementDB<T> where T: Display + Deb
             Err(e) => { Err(e.to_string()) }
         }
     }
-}
 
-impl<T> UserDB for SQLLiteDefacementDB<T> where T: Display + FromSql + ToSql + Send + Sync {
-    fn create_user(&self, user_name: &str) -> Result<User, String> {
+    fn read_accepted_doms_for_page(&self, page: &TrackedPage) -> Result<Vec<StoredDom<T>>, String> {
         let connection = self.get_sql_conn();
-        let statement = connection.execute(format!("INSERT INTO {}(USERNAME) values(LOWER(?))", USERS).as_str(),
-                                           params![user_name]);
 
-        return match statement {
-            Ok(changed_count) => {
-                if changed_count > 0 {
-                    let last_id = connection.last_insert_rowid();
+        let mut statement = connection.prepare(
+            format!("SELECT * FROM {} WHERE PAGE_ID=? AND ACCEPTED<>0", TRACKED_PAGES_DOMS).as_str()).unwrap();
 
-                    Ok(User::new(last_id as u32, String::from(user_name)))
-                } else {
-                    Err(String::from("Failed to insert user, maybe it's duplicated?"))
-                }
-            }
-            Err(e) => {
-                Err(e.to_string())
-            }
+        let mut rows = match statement.query(params![page.page_id()]) {
+            Ok(rows) => { rows }
+            Err(e) => { return Err(e.to_string()); }
         };
+
+        let mut doms = Vec::new();
+
+        while let Some(row) = rows.next().unwrap() {
+            doms.push(self.parse_dom_from_row(row));
+        }
+
+        Ok(doms)
     }
 
-    fn get_user_info_for(&self, user_name: &str) -> Result<User, String> {
+    fn set_dom_accepted(&self, dom: &mut StoredDom<T>, accepted: bool) -> Result<(), String> {
         let connection = self.get_sql_conn();
 
-        let mut statement = connection.prepare(format!("SELECT * FROM {} WHERE LOWER(USERNAME)=LOWER(?)", USERS)
-            .as_str()).unwrap();
+        let mut update = connection
+            .prepare(format!("UPDATE {} SET ACCEPTED=? WHERE rowid=?", TRACKED_PAGES_DOMS).as_str())
+            .unwrap();
 
-        return match statement.query(params![user_name]) {
-            Ok(mut rows) => {
-                if let Some(row) = rows.next().unwrap() {
-                    return Ok(User::new(row.get(0).unwrap(), row.get(1).unwrap()));
-                }
+        match update.execute(params![accepted, dom.dom_id()]) {
+            Ok(_) => {
+                dom.set_accepted(accepted);
 
-                Err(String::from("Could not find user."))
+                Ok(())
             }
-
             Err(e) => {
                 Err(e.to_string())
             }
-        };
+        }
     }
 
-    fn get_user_info_for_id(&self, user_id: u32) -> Result<User, String> {
-        let connection = self.get_sql_conn();
+    fn insert_verdict_for_page(&self, page: &TrackedPage, result: &AggregatedResult) -> Result<u32, String> {
+        let mut connection = self.write_sql_conn();
 
-        let mut statement = connection.prepare(format!("SELECT * FROM {} WHERE rowid=?", USERS)
-            .as_str()).unwrap();
+        let transaction = match connection.transaction() {
+            Ok(transaction) => { transaction }
+            Err(e) => { return Err(e.to_string()); }
+        };
 
-        return match statement.query(params![user_id]) {
-            Ok(mut rows) => {
-                if let Some(row) = rows.next().unwrap() {
-                    return Ok(User::new(row.get(0).unwrap(), row.get(1).unwrap()));
-                }
+        //Only the latest verdict is kept, the history of the checks is in the check log
+        if let Err(e) = transaction.execute(format!("DELETE FROM {} WHERE CHECK_ID IN (SELECT rowid FROM {} WHERE PAGE_ID=?)",
+                                                    COMPARATOR_VERDICTS, CHECK_VERDICTS).as_str(), params![page.page_id()]) {
+            return Err(e.to_string());
+        }
 
-                Err(String::from("Could not find user."))
-            }
-            Err(e) => {
-                Err(e.to_string())
-            }
-        };
-    }
+        if let Err(e) = transaction.execute(format!("DELETE FROM {} WHERE PAGE_ID=?", CHECK_VERDICTS).as_str(),
+                                            params![page.page_id()]) {
+            return Err(e.to_string());
+        }
 
-    fn delete_user(&self, user: User) -> Result<bool, String> {
-        let connection = self.get_sql_conn();
+        let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
 
-        let mut statement = connection.prepare(format!("DELETE FROM {} WHERE rowid=?", USERS).as_str()).unwrap();
+        if let Err(e) = transaction.execute(format!("INSERT INTO {}(PAGE_ID, CHECK_TIME, POLICY, VERDICT, SCORE) values(?, ?, ?, ?, ?)",
+                                                    CHECK_VERDICTS).as_str(),
+                                            params![page.page_id(), current_time as u64, result.policy(),
+                                            verdict_to_str(&result.verdict()), result.score()]) {
+            return Err(e.to_string());
+        }
 
-        return match statement.execute(params![user.user_id()]) {
-            Ok(count) => {
-                if count > 0 {
-                    Ok(true)
-                } else {
-                    Ok(false)
-                }
+        let check_id = transaction.last_insert_rowid() as u32;
+
+        for comparator_result in result.breakdown() {
+            let compare_result = comparator_result.result();
+
+            if let Err(e) = transaction.execute(format!("INSERT INTO {}(CHECK_ID, COMPARATOR, VERDICT, CONFIDENCE, EXPLANATION) \
+            values(?, ?, ?, ?, ?)", COMPARATOR_VERDICTS).as_str(),
+                                                params![check_id, comparator_result.comparator(),
+                                                verdict_to_str(&compare_result.verdict()), compare_result.confidence(),
+                                                compare_result.explanation()]) {
+                return Err(e.to_string());
             }
+        }
+
+        match transaction.commit() {
+            Ok(_) => { Ok(check_id) }
             Err(e) => { Err(e.to_string()) }
-        };
+        }
     }
 
-    fn insert_contact_for(&self, user: &User, comm: CommData) -> Result<UserCommunication, String> {
+    fn read_latest_verdict_for_page(&self, page: &TrackedPage) -> Result<AggregatedResult, String> {
         let connection = self.get_sql_conn();
 
-        let mut statement = connection.prepare(format!("INSERT INTO {} (USER_ID, CONTACT_TYPE, CONTACT) values(?, ?, ?)", USER_CONTACTS).as_str()).unwrap();
+        let mut statement = connection.prepare(format!("SELECT rowid, POLICY, VERDICT, SCORE FROM {} WHERE PAGE_ID=? \
+        ORDER BY rowid DESC LIMIT 1", CHECK_VERDICTS).as_str()).unwrap();
 
-        let mut final_result: Option<Result<usize, Error>> = Option::None;
+        let check = statement.query_row(params![page.page_id()], |row| {
+            let check_id: u32 = row.get(0)?;
+            let policy: String = row.get(1)?;
+            let verdict: String = row.get(2)?;
+            let score: f64 = row.get(3)?;
 
-        match &comm {
-            CommData::Email(mail) => {
-                final_result = Some(statement.execute(params![user.user_id(), "EMAIL", mail]));
-            }
-        }
+            Ok((check_id, policy, verdict, score))
+        });
+
+        let (check_id, policy, verdict, score) = match check {
+            Ok(check) => { check }
+            Err(Error::QueryReturnedNoRows) => {
+                return Err(format!("There are no verdicts for page {}", page.page_id()));
+            }
+            Err(e) => { return Err(e.to_string()); }
+        };
+
+        let mut statement = connection.prepare(format!("SELECT COMPARATOR, VERDICT, CONFIDENCE, EXPLANATION FROM {} \
+        WHERE CHECK_ID=? ORDER BY rowid", COMPARATOR_VERDICTS).as_str()).unwrap();
+
+        let breakdown = statement.query_map(params![check_id], |row| {
+            let comparator: String = row.get(0)?;
+            let verdict: String = row.get(1)?;
+            let confidence: f64 = row.get(2)?;
+            let explanation: Option<String> = row.get(3)?;
+
+            Ok(ComparatorResult::new(comparator.as_str(),
+                                     CompareResult::new(verdict_from_str(verdict.as_str()), confidence,
+                                                        explanation.unwrap_or_default())))
+        }).and_then(|rows| rows.collect::<Result<Vec<ComparatorResult>, Error>>());
+
+        match breakdown {
+            Ok(breakdown) => {
+                Ok(AggregatedResult::new(policy, verdict_from_str(verdict.as_str()), score, breakdown))
+            }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn insert_approval_for_page(&self, page: &TrackedPage, incident: &Incident, dom: &StoredDom<T>,
+                                approver: &str) -> Result<Approval, String> {
+        let connection = self.write_sql_conn();
+
+        let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        let mut insert = connection.prepare(format!("INSERT INTO {}(PAGE_ID, INCIDENT_ID, DOM_ID, APPROVER, APPROVAL_TIME, \
+        DEFACEMENT_COUNT) values(?, ?, ?, ?, ?, ?)", APPROVALS).as_str()).unwrap();
+
+        match insert.execute(params![page.page_id(), incident.incident_id(), dom.dom_id(), approver, current_time as u64,
+        page.defacement_count()]) {
+            Ok(_) => {
+                Ok(Approval::new(connection.last_insert_rowid() as u32, page.page_id(), incident.incident_id(),
+                                 dom.dom_id(), String::from(approver), current_time, page.defacement_count()))
+            }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn open_incident_for_page(&self, page: &TrackedPage, result: &AggregatedResult, offending_dom: &T,
+                              offending_screenshot: Option<&Vec<u8>>) -> Result<Incident, String> {
+        let connection = self.write_sql_conn();
+
+        let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        let triggering_results: Vec<ComparatorResult> = result.defaced_results().into_iter().cloned().collect();
+
+        let mut insert = connection.prepare(format!("INSERT INTO {}(PAGE_ID, USER_ID, START_TIME, TRIGGERING_RESULTS, \
+        OFFENDING_DOM, OFFENDING_SCREENSHOT, NOTIFIED_CONTACTS) values(?, ?, ?, ?, ?, ?, ?)", INCIDENTS).as_str()).unwrap();
+
+        match insert.execute(params![page.page_id(), page.owning_user_id(), current_time as u64,
+        comparator_results_to_json(&triggering_results), offending_dom.to_string(), offending_screenshot, "[]"]) {
+            Ok(_) => {
+                Ok(Incident::new(connection.last_insert_rowid() as u32, page.page_id(), page.owning_user_id(),
+                                 current_time, None, triggering_results, offending_dom.to_string(),
+                                 Vec::new(), None)
+                    .with_offending_screenshot(offending_screenshot.cloned()))
+            }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn read_open_incident_for_page(&self, page: &TrackedPage) -> Result<Option<Incident>, String> {
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("SELECT * FROM {} WHERE PAGE_ID=? AND END_TIME IS NULL \
+        ORDER BY rowid DESC LIMIT 1", INCIDENTS).as_str()).unwrap();
+
+        match statement.query_row(params![page.page_id()], parse_incident_from_row) {
+            Ok(incident) => { Ok(Some(incident)) }
+            Err(Error::QueryReturnedNoRows) => { Ok(None) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn read_incident_for_id(&self, incident_id: u32) -> Result<Incident, String> {
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("SELECT * FROM {} WHERE rowid=?", INCIDENTS).as_str()).unwrap();
+
+        match statement.query_row(params![incident_id], parse_incident_from_row) {
+            Ok(incident) => { Ok(incident) }
+            Err(Error::QueryReturnedNoRows) => { Err(format!("Could not find the incident with ID {}", incident_id)) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn add_notified_contact_to_incident(&self, incident: &mut Incident, contact: &UserCommunication) -> Result<(), String> {
+        let mut notified_contacts = incident.notified_contacts().clone();
+
+        if notified_contacts.contains(&contact.comm_id()) {
+            return Ok(());
+        }
+
+        notified_contacts.push(contact.comm_id());
+
+        let connection = self.get_sql_conn();
+
+        let mut update = connection.prepare(format!("UPDATE {} SET NOTIFIED_CONTACTS=? WHERE rowid=?", INCIDENTS).as_str()).unwrap();
+
+        match update.execute(params![serde_json::to_string(&notified_contacts).unwrap(), incident.incident_id()]) {
+            Ok(_) => {
+                incident.set_notified_contacts(notified_contacts);
+
+                Ok(())
+            }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn resolve_incident(&self, incident: &mut Incident, resolution: IncidentResolution) -> Result<(), String> {
+        let connection = self.get_sql_conn();
+
+        let end_time = incident.end_time()
+            .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis());
+
+        let mut update = connection.prepare(format!("UPDATE {} SET END_TIME=?, RESOLUTION=? WHERE rowid=?", INCIDENTS).as_str()).unwrap();
+
+        match update.execute(params![end_time as u64, incident_resolution_to_str(&resolution), incident.incident_id()]) {
+            Ok(_) => {
+                incident.set_end_time(Some(end_time));
+                incident.set_resolution(Some(resolution));
+
+                Ok(())
+            }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn list_incidents_for_page(&self, page: &TrackedPage, filter: &IncidentFilter) -> Result<Vec<Incident>, String> {
+        self.list_incidents_for("PAGE_ID", page.page_id(), filter)
+    }
+
+    fn list_approvals_for_page(&self, page: &TrackedPage) -> Result<Vec<Approval>, String> {
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("SELECT rowid, PAGE_ID, INCIDENT_ID, DOM_ID, APPROVER, APPROVAL_TIME, \
+        DEFACEMENT_COUNT FROM {} WHERE PAGE_ID=? ORDER BY rowid DESC", APPROVALS).as_str()).unwrap();
+
+        let approvals = statement.query_map(params![page.page_id()], |row| {
+            let approval_time: u64 = row.get(5)?;
+
+            Ok(Approval::new(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?,
+                             approval_time as u128, row.get(6)?))
+        }).and_then(|rows| rows.collect::<Result<Vec<Approval>, Error>>());
+
+        match approvals {
+            Ok(approvals) => { Ok(approvals) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn insert_check_record_for_page(&self, page: &TrackedPage, fetch_duration: u128, http_status: Option<u16>,
+                                    result: Option<&AggregatedResult>) -> Result<CheckRecord, String> {
+        let connection = self.write_sql_conn();
+
+        let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        let verdict = result.map(|result| result.verdict());
+
+        let scores: BTreeMap<String, f64> = result.map(|result| {
+            result.breakdown().iter()
+                .filter_map(|comparator_result| comparator_result.result().score()
+                    .map(|score| (String::from(comparator_result.comparator()), score)))
+                .collect()
+        }).unwrap_or_default();
+
+        let inserted = connection.execute(format!("INSERT INTO {}(PAGE_ID, CHECK_TIME, FETCH_DURATION, HTTP_STATUS, \
+        VERDICT, SCORES) values(?, ?, ?, ?, ?, ?)", CHECK_LOG).as_str(),
+                                          params![page.page_id(), current_time as u64, fetch_duration as u64, http_status,
+                                          verdict.map(|verdict| verdict_to_str(&verdict)),
+                                          serde_json::to_string(&scores).unwrap()]);
+
+        match inserted {
+            Ok(_) => {
+                Ok(CheckRecord::new(connection.last_insert_rowid() as u32, page.page_id(), current_time,
+                                    fetch_duration, http_status, verdict, scores))
+            }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn list_check_records_for_page(&self, page: &TrackedPage, since: u128) -> Result<Vec<CheckRecord>, String> {
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("SELECT rowid, PAGE_ID, CHECK_TIME, FETCH_DURATION, HTTP_STATUS, \
+        VERDICT, SCORES FROM {} WHERE PAGE_ID=? AND CHECK_TIME>=? ORDER BY CHECK_TIME, rowid", CHECK_LOG).as_str()).unwrap();
+
+        let records = statement.query_map(params![page.page_id(), since as u64], parse_check_record_from_row)
+            .and_then(|rows| rows.collect::<Result<Vec<CheckRecord>, Error>>());
+
+        match records {
+            Ok(records) => { Ok(records) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn delete_check_records_older_than(&self, page: &TrackedPage, time: u128) -> Result<usize, String> {
+        let connection = self.write_sql_conn();
+
+        match connection.execute(format!("DELETE FROM {} WHERE PAGE_ID=? AND CHECK_TIME<?", CHECK_LOG).as_str(),
+                                 params![page.page_id(), time as u64]) {
+            Ok(deleted) => { Ok(deleted) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn enqueue_notification(&self, entry: &mut OutboxEntry) -> Result<(), String> {
+        let connection = self.write_sql_conn();
+
+        let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        let inserted = connection.execute(format!("INSERT INTO {}(KIND, PAGE_ID, USER_ID, INCIDENT_ID, CONTACT_ID, CHANNEL, \
+        STORED_DOM_ID, RESULT, STATUS, ATTEMPTS, CREATED_TIME, NEXT_ATTEMPT) \
+        values(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)", OUTBOX).as_str(),
+                                          params![notification_kind_to_str(&entry.kind()), entry.page_id(), entry.user_id(),
+                                          entry.incident_id(), entry.contact_id(), entry.channel(), entry.stored_dom_id(),
+                                          entry.result().map(aggregated_result_to_json),
+                                          outbox_status_to_str(&entry.status()), entry.attempts(),
+                                          current_time as u64, current_time as u64]);
+
+        match inserted {
+            Ok(_) => {
+                entry.set_entry_id(connection.last_insert_rowid() as u32);
+                entry.set_created_time(current_time);
+                entry.set_next_attempt(current_time);
+
+                Ok(())
+            }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn claim_due_notifications(&self, limit: u32, lease: u128) -> Result<Vec<OutboxEntry>, String> {
+        let mut connection = self.write_sql_conn();
+
+        let transaction = match connection.transaction() {
+            Ok(transaction) => { transaction }
+            Err(e) => { return Err(e.to_string()); }
+        };
+
+        let current_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        let mut entries = {
+            let mut statement = transaction.prepare(format!("SELECT {} FROM {} WHERE STATUS=? AND NEXT_ATTEMPT<=? \
+            ORDER BY rowid LIMIT ?", OUTBOX_COLUMNS, OUTBOX).as_str()).unwrap();
+
+            let entries = statement.query_map(params![outbox_status_to_str(&OutboxStatus::Pending), current_time as u64, limit],
+                                              parse_outbox_entry_from_row)
+                .and_then(|rows| rows.collect::<Result<Vec<OutboxEntry>, Error>>());
+
+            match entries {
+                Ok(entries) => { entries }
+                Err(e) => { return Err(e.to_string()); }
+            }
+        };
+
+        let leased_until = current_time + lease;
+
+        for entry in &mut entries {
+            if let Err(e) = transaction.execute(format!("UPDATE {} SET NEXT_ATTEMPT=? WHERE rowid=?", OUTBOX).as_str(),
+                                                params![leased_until as u64, entry.entry_id()]) {
+                return Err(e.to_string());
+            }
+
+            entry.set_next_attempt(leased_until);
+        }
+
+        match transaction.commit() {
+            Ok(_) => { Ok(entries) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn update_outbox_entry(&self, entry: &OutboxEntry) -> Result<(), String> {
+        let connection = self.write_sql_conn();
+
+        match connection.execute(format!("UPDATE {} SET STATUS=?, ATTEMPTS=?, NEXT_ATTEMPT=?, LAST_ERROR=? WHERE rowid=?",
+                                         OUTBOX).as_str(),
+                                 params![outbox_status_to_str(&entry.status()), entry.attempts(),
+                                 entry.next_attempt() as u64, entry.last_error(), entry.entry_id()]) {
+            Ok(0) => { Err(format!("There is no notification with ID {} in the outbox", entry.entry_id())) }
+            Ok(_) => { Ok(()) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn list_outbox_entries(&self, status: OutboxStatus) -> Result<Vec<OutboxEntry>, String> {
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("SELECT {} FROM {} WHERE STATUS=? ORDER BY rowid", OUTBOX_COLUMNS,
+                                                       OUTBOX).as_str()).unwrap();
+
+        let entries = statement.query_map(params![outbox_status_to_str(&status)], parse_outbox_entry_from_row)
+            .and_then(|rows| rows.collect::<Result<Vec<OutboxEntry>, Error>>());
+
+        match entries {
+            Ok(entries) => { Ok(entries) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn delete_outbox_entries_older_than(&self, status: OutboxStatus, time: u128) -> Result<usize, String> {
+        let connection = self.write_sql_conn();
+
+        match connection.execute(format!("DELETE FROM {} WHERE STATUS=? AND CREATED_TIME<?", OUTBOX).as_str(),
+                                 params![outbox_status_to_str(&status), time as u64]) {
+            Ok(deleted) => { Ok(deleted) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+}
+
+impl<T> UserDB for SQLLiteDefacementDB<T> where T: Display + FromSql + ToSql + Send + Sync {
+    fn create_user(&self, user_name: &str) -> Result<User, String> {
+        let connection = self.get_sql_conn();
+        let statement = connection.execute(format!("INSERT INTO {}(USERNAME) values(LOWER(?))", USERS).as_str(),
+                                           params![user_name]);
+
+        return match statement {
+            Ok(changed_count) => {
+                if changed_count > 0 {
+                    let last_id = connection.last_insert_rowid();
+
+                    Ok(User::new(last_id as u32, String::from(user_name)))
+                } else {
+                    Err(String::from("Failed to insert user, maybe it's duplicated?"))
+                }
+            }
+            Err(e) => {
+                Err(e.to_string())
+            }
+        };
+    }
+
+    fn get_user_info_for(&self, user_name: &str) -> Result<User, String> {
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("SELECT rowid, USERNAME, LOCALE FROM {} WHERE LOWER(USERNAME)=LOWER(?)", USERS)
+            .as_str()).unwrap();
+
+        return match statement.query(params![user_name]) {
+            Ok(mut rows) => {
+                if let Some(row) = rows.next().unwrap() {
+                    return Ok(parse_user_from_row(row));
+                }
+
+                Err(String::from("Could not find user."))
+            }
+
+            Err(e) => {
+                Err(e.to_string())
+            }
+        };
+    }
+
+    fn get_user_info_for_id(&self, user_id: u32) -> Result<User, String> {
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("SELECT rowid, USERNAME, LOCALE FROM {} WHERE rowid=?", USERS)
+            .as_str()).unwrap();
+
+        return match statement.query(params![user_id]) {
+            Ok(mut rows) => {
+                if let Some(row) = rows.next().unwrap() {
+                    return Ok(parse_user_from_row(row));
+                }
+
+                Err(String::from("Could not find user."))
+            }
+            Err(e) => {
+                Err(e.to_string())
+            }
+        };
+    }
+
+    fn update_locale_for_user(&self, user: &mut User, locale: Option<&str>) -> Result<(), String> {
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("UPDATE {} SET LOCALE=? WHERE rowid=?", USERS).as_str()).unwrap();
+
+        match statement.execute(params![locale, user.user_id()]) {
+            Ok(count) => {
+                if count == 0 {
+                    return Err(String::from("Could not find user."));
+                }
+
+                user.set_locale(locale.map(String::from));
+
+                Ok(())
+            }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    fn delete_user(&self, user: User) -> Result<bool, String> {
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("DELETE FROM {} WHERE rowid=?", USERS).as_str()).unwrap();
+
+        return match statement.execute(params![user.user_id()]) {
+            Ok(count) => {
+                if count > 0 {
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            Err(e) => { Err(e.to_string()) }
+        };
+    }
+
+    fn insert_contact_for(&self, user: &User, comm: CommData) -> Result<UserCommunication, String> {
+        let connection = self.get_sql_conn();
+
+        let mut statement = connection.prepare(format!("INSERT INTO {} (USER_ID, CONTACT_TYPE, CONTACT) values(?, ?, ?)", USER_CONTACTS).as_str()).unwrap();
+
+        let mut final_result: Option<Result<usize, Error>> = Option::None;
+
+        match &comm {
+            CommData::Email(mail) => {
+                final_result = Some(statement.execute(params![user.user_id(), "EMAIL", mail]));
+            }
+            CommData::Webhook { url, secret } => {
+                let webhook = serde_json::json!({ "url": url, "secret": secret });
+
+                final_result = Some(statement.execute(params![user.user_id(), "WEBHOOK", webhook.to_string()]));
+            }
+            CommData::Slack(webhook_url) => {
+                final_result = Some(statement.execute(params![user.user_id(), "SLACK", webhook_url]));
+            }
+            CommData::Mattermost(webhook_url) => {
+                final_result = Some(statement.execute(params![user.user_id(), "MATTERMOST", webhook_url]));
+            }
+            CommData::Matrix { homeserver, room_id, access_token } => {
+                let matrix = serde_json::json!({ "homeserver": homeserver, "room_id": room_id, "access_token": access_token });
+
+                final_result = Some(statement.execute(params![user.user_id(), "MATRIX", matrix.to_string()]));
+            }
+            CommData::Telegram { bot_token, chat_id } => {
+                let telegram = serde_json::json!({ "bot_token": bot_token, "chat_id": chat_id });
+
+                final_result = Some(statement.execute(params![user.user_id(), "TELEGRAM", telegram.to_string()]));
+            }
+        }
 
         match final_result.unwrap() {
             Ok(changed_rows) => {
@@ -675,10 +1570,14 @@ impl<T> UserDB for SQLLiteDefacementDB<T> where T: Display + FromSql + ToSql + S
         Ok(contacts)
     }
 
+    fn list_incidents_for_user(&self, user: &User, filter: &IncidentFilter) -> Result<Vec<Incident>, String> {
+        self.list_incidents_for("USER_ID", user.user_id(), filter)
+    }
+
     fn get_contact_for_id(&self, contact_id: u32) -> Result<UserCommunication, String> {
         let conn = self.get_sql_conn();
 
-        let mut statement = conn.prepare(format!("SELECT * FROM {} WHERE rowid=?", contact_id).as_str()).unwrap();
+        let mut statement = conn.prepare(format!("SELECT * FROM {} WHERE rowid=?", USER_CONTACTS).as_str()).unwrap();
 
         return match statement.query(params![contact_id]) {
             Ok(mut rows) => {
@@ -711,9 +1610,14 @@ impl<T> UserDB for SQLLiteDefacementDB<T> where T: Display + FromSql + ToSql + S
 
 #[cfg(test)]
 mod sqlite_tests {
+    use crate::comparators::{ComparatorResult, CompareResult, Verdict};
+    use crate::comparators::aggregation::AggregatedResult;
+    use crate::comparators::baseline::{ADAPTIVE_BASELINE_WEIGHT, BaselineModel, DEFAULT_THRESHOLD_POLICY};
     use crate::communication::CommData::Email;
-    use crate::databases::{UserDB, WebsiteDefacementDB};
-    use crate::databases::sqlitedb::SQLLiteDefacementDB;
+    use crate::communication::{CommData, UserCommunication};
+    use crate::databases::{IncidentFilter, IncidentResolution, NotificationKind, OutboxEntry, OutboxStatus, TrackedPageType, User, UserDB, WebsiteDefacementDB};
+    use crate::databases::sqlitedb::{APPROVALS, CHECK_LOG, CHECK_VERDICTS, COMPARATOR_VERDICTS, INCIDENTS, OUTBOX, SQLLiteDefacementDB,
+                                     TRACKED_PAGES_DOMS};
 
     #[test]
     fn test_sqlite_tracked_page() {
@@ -740,6 +1644,317 @@ mod sqlite_tests {
         assert!(db.read_page_id_for_page(page).is_err())
     }
 
+    #[test]
+    fn test_sqlite_page_settings() {
+        let db: SQLLiteDefacementDB<String> = SQLLiteDefacementDB::new();
+
+        let mut page = db.insert_tracked_page("https://example.org/settings", 0).unwrap();
+
+        page.set_allowed_domains(vec![String::from("googleapis.com")]);
+        page.set_normalisation_rules(vec![String::from("strip data-slot")]);
+        page.set_ignored_selectors(vec![String::from("#ticker"), String::from("aside.comments")]);
+        page.set_sample_count(20);
+        page.set_sample_interval(5000);
+        page.set_adaptive_baseline(true);
+        page.set_volatility_mask(vec![String::from("div#ads")]);
+        page.set_pinned_volatility_mask(vec![String::from("div.clock")]);
+        page.set_excluded_volatility_mask(vec![String::from("div#main > p:nth-child(2)")]);
+        page.set_tracked_page_type(TrackedPageType::Dynamic(
+            BaselineModel::from_differences(&[1.0, 2.5, 4.0], DEFAULT_THRESHOLD_POLICY).unwrap()));
+
+        assert!(db.update_tracking_type_for_page(&page).unwrap());
+
+        let stored_page = db.get_information_for_tracked_page(page.page_id()).unwrap();
+
+        assert_eq!(stored_page.allowed_domains(), page.allowed_domains());
+        assert_eq!(stored_page.normalisation_rules(), page.normalisation_rules());
+        assert_eq!(stored_page.ignored_selectors(), page.ignored_selectors());
+        assert_eq!(stored_page.sample_count(), 20);
+        assert_eq!(stored_page.sample_interval(), 5000);
+        assert!(stored_page.adaptive_baseline());
+        assert_eq!(stored_page.volatility_mask(), page.volatility_mask());
+        assert_eq!(stored_page.pinned_volatility_mask(), page.pinned_volatility_mask());
+        assert_eq!(stored_page.excluded_volatility_mask(), page.excluded_volatility_mask());
+
+        match (stored_page.tracked_page_type(), page.tracked_page_type()) {
+            (TrackedPageType::Dynamic(stored_model), TrackedPageType::Dynamic(model)) => {
+                assert_eq!(stored_model.sample_count(), model.sample_count());
+                assert!((stored_model.threshold() - model.threshold()).abs() < 1e-9);
+            }
+            _ => { panic!("The page should be dynamic") }
+        }
+
+        let mut stored_page = stored_page;
+
+        if let TrackedPageType::Dynamic(model) = stored_page.tracked_page_type() {
+            let mut model = model.clone();
+
+            model.update(3.0, ADAPTIVE_BASELINE_WEIGHT);
+
+            stored_page.set_tracked_page_type(TrackedPageType::Dynamic(model));
+        }
+
+        stored_page.set_comparator_data("Tree", String::from("{\"mean\": 1.0, \"sample_count\": 3, \"adaptive_updates\": 1}"));
+
+        db.update_baseline_for_page(&stored_page).unwrap();
+
+        let adapted_page = db.get_information_for_tracked_page(page.page_id()).unwrap();
+
+        match adapted_page.tracked_page_type() {
+            TrackedPageType::Dynamic(model) => { assert_eq!(model.adaptive_updates(), 1); }
+            _ => { panic!("The page should be dynamic") }
+        }
+
+        //The models learned by the comparators are adapted along with the baseline
+        assert_eq!(adapted_page.comparator_data("Tree"), stored_page.comparator_data("Tree"));
+
+        db.del_tracked_page(stored_page).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_verdicts() {
+        let db: SQLLiteDefacementDB<String> = SQLLiteDefacementDB::new();
+
+        let page = db.insert_tracked_page("https://example.org/verdicts", 0).unwrap();
+
+        assert!(db.read_latest_verdict_for_page(&page).is_err());
+
+        let result = AggregatedResult::new(String::from("AnyDefaced"), Verdict::Defaced, 0.75,
+                                           vec![ComparatorResult::new("Checksum", CompareResult::maybe_defaced("Changed")),
+                                                ComparatorResult::new("Domain", CompareResult::defaced(0.75, String::from("New domain")))]);
+
+        db.insert_verdict_for_page(&page, &result).unwrap();
+
+        assert_eq!(db.read_latest_verdict_for_page(&page).unwrap(), result);
+
+        let latest = AggregatedResult::new(String::from("AnyDefaced"), Verdict::NotDefaced, 0.0,
+                                           vec![ComparatorResult::new("Checksum", CompareResult::not_defaced(1.0, "Unchanged"))]);
+
+        let check_id = db.insert_verdict_for_page(&page, &latest).unwrap();
+
+        assert_eq!(db.read_latest_verdict_for_page(&page).unwrap(), latest);
+
+        //The previous verdict and its breakdown are replaced, not kept
+        let connection = db.get_sql_conn();
+
+        let count = |query: String| -> u32 { connection.query_row(query.as_str(), [], |row| row.get(0)).unwrap() };
+
+        assert_eq!(count(format!("SELECT COUNT(*) FROM {} WHERE PAGE_ID={}", CHECK_VERDICTS, page.page_id())), 1);
+        assert_eq!(count(format!("SELECT COUNT(*) FROM {} WHERE CHECK_ID={}", COMPARATOR_VERDICTS, check_id)), 1);
+
+        drop(connection);
+
+        db.del_tracked_page(page).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_delete_page() {
+        let db: SQLLiteDefacementDB<String> = SQLLiteDefacementDB::new();
+
+        let page = db.insert_tracked_page("https://example.org/deleted", 3).unwrap();
+
+        let result = AggregatedResult::new(String::from("AnyDefaced"), Verdict::Defaced, 1.0,
+                                           vec![ComparatorResult::new("Checksum", CompareResult::defaced(1.0, String::from("Changed")))]);
+
+        let dom = db.insert_dom_for_page(&page, String::from("<p>Shop</p>")).unwrap();
+
+        let check_id = db.insert_verdict_for_page(&page, &result).unwrap();
+        db.insert_check_record_for_page(&page, 10, Some(200), Some(&result)).unwrap();
+        let incident = db.open_incident_for_page(&page, &result, &String::from("<p>Hacked</p>"), None).unwrap();
+
+        db.insert_approval_for_page(&page, &incident, &dom, "admin").unwrap();
+
+        db.enqueue_notification(&mut OutboxEntry::new(0, NotificationKind::Defacement, page.page_id(), 3,
+                                                      Some(incident.incident_id()), 1, String::from("Email"))
+            .with_defacement(dom.dom_id(), result)).unwrap();
+
+        let page_id = page.page_id();
+
+        assert!(db.del_tracked_page(page).unwrap());
+
+        //Nothing of the page is left behind
+        let connection = db.get_sql_conn();
+
+        let count = |query: String| -> u32 { connection.query_row(query.as_str(), [], |row| row.get(0)).unwrap() };
+
+        for table in [TRACKED_PAGES_DOMS, CHECK_VERDICTS, CHECK_LOG, APPROVALS, INCIDENTS, OUTBOX] {
+            assert_eq!(count(format!("SELECT COUNT(*) FROM {} WHERE PAGE_ID={}", table, page_id)), 0);
+        }
+
+        assert_eq!(count(format!("SELECT COUNT(*) FROM {} WHERE CHECK_ID={}", COMPARATOR_VERDICTS, check_id)), 0);
+    }
+
+    #[test]
+    fn test_sqlite_check_log() {
+        let db: SQLLiteDefacementDB<String> = SQLLiteDefacementDB::new();
+
+        let page = db.insert_tracked_page("https://example.org/check_log", 0).unwrap();
+
+        let result = AggregatedResult::new(String::from("AnyDefaced"), Verdict::NotDefaced, 0.0,
+                                           vec![ComparatorResult::new("Checksum", CompareResult::maybe_defaced("Changed")),
+                                                ComparatorResult::new("Diff", CompareResult::maybe_defaced("Below").with_score(2.5))]);
+
+        let check = db.insert_check_record_for_page(&page, 120, Some(200), Some(&result)).unwrap();
+
+        assert_eq!(check.scores().len(), 1);
+        assert_eq!(check.scores()["Diff"], 2.5);
+        assert!(check.is_up());
+
+        let failed = db.insert_check_record_for_page(&page, 3000, None, None).unwrap();
+
+        assert!(!failed.is_up());
+
+        assert_eq!(db.list_check_records_for_page(&page, 0).unwrap(), vec![check, failed.clone()]);
+        assert!(db.list_check_records_for_page(&page, failed.check_time() + 1).unwrap().is_empty());
+
+        assert_eq!(db.delete_check_records_older_than(&page, failed.check_time() + 1).unwrap(), 2);
+        assert!(db.list_check_records_for_page(&page, 0).unwrap().is_empty());
+
+        db.del_tracked_page(page).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_outbox() {
+        let db: SQLLiteDefacementDB<String> = SQLLiteDefacementDB::new();
+
+        let page = db.insert_tracked_page("https://example.org/outbox", 0).unwrap();
+
+        let result = AggregatedResult::new(String::from("AnyDefaced"), Verdict::Defaced, 1.0,
+                                           vec![ComparatorResult::new("Diff", CompareResult::defaced(1.0, String::from("Changed")).with_score(80.0))]);
+
+        let mut defacement = OutboxEntry::new(0, NotificationKind::Defacement, page.page_id(), 3, Some(7), 11, String::from("Email"))
+            .with_defacement(5, result);
+
+        let mut recovery = OutboxEntry::new(0, NotificationKind::Recovery, page.page_id(), 3, Some(7), 11, String::from("Slack"));
+
+        db.enqueue_notification(&mut defacement).unwrap();
+        db.enqueue_notification(&mut recovery).unwrap();
+
+        assert_eq!(defacement.next_attempt(), defacement.created_time());
+
+        let claimed: Vec<OutboxEntry> = db.claim_due_notifications(100, 60_000).unwrap().into_iter()
+            .filter(|entry| entry.page_id() == page.page_id())
+            .collect();
+
+        assert_eq!(claimed.len(), 2);
+        assert!(claimed[0].next_attempt() >= defacement.created_time() + 60_000);
+
+        //The notifications are rebuilt from what was stored, scores included
+        defacement.set_next_attempt(claimed[0].next_attempt());
+        assert_eq!(claimed[0], defacement);
+        assert_eq!(claimed[1].kind(), NotificationKind::Recovery);
+        assert!(claimed[1].stored_dom_id().is_none());
+
+        //Claimed notifications are not claimed again until their lease ends
+        assert!(db.claim_due_notifications(100, 60_000).unwrap().iter().all(|entry| entry.page_id() != page.page_id()));
+
+        defacement.set_attempts(10);
+        defacement.set_status(OutboxStatus::DeadLetter);
+        defacement.set_last_error(Some(String::from("Connection refused")));
+
+        db.update_outbox_entry(&defacement).unwrap();
+
+        let dead_letters = db.list_outbox_entries(OutboxStatus::DeadLetter).unwrap();
+
+        assert!(dead_letters.contains(&defacement));
+
+        recovery.set_status(OutboxStatus::Delivered);
+
+        db.update_outbox_entry(&recovery).unwrap();
+
+        assert!(db.list_outbox_entries(OutboxStatus::Pending).unwrap().iter().all(|entry| entry.page_id() != page.page_id()));
+
+        defacement.set_status(OutboxStatus::Delivered);
+
+        db.update_outbox_entry(&defacement).unwrap();
+
+        //Only the delivered notifications created before the given time are purged
+        assert_eq!(db.delete_outbox_entries_older_than(OutboxStatus::Delivered, defacement.created_time()).unwrap(), 0);
+        assert_eq!(db.delete_outbox_entries_older_than(OutboxStatus::Delivered, recovery.created_time() + 1).unwrap(), 2);
+        assert!(db.list_outbox_entries(OutboxStatus::Delivered).unwrap().iter().all(|entry| entry.page_id() != page.page_id()));
+
+        db.del_tracked_page(page).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_approvals() {
+        let db: SQLLiteDefacementDB<String> = SQLLiteDefacementDB::new();
+
+        let mut page = db.insert_tracked_page("https://example.org/approvals", 0).unwrap();
+
+        page.set_defacement_count(3);
+
+        let result = AggregatedResult::new(String::from("AnyDefaced"), Verdict::Defaced, 1.0,
+                                           vec![ComparatorResult::new("Checksum", CompareResult::defaced(1.0, String::from("Changed")))]);
+
+        let incident = db.open_incident_for_page(&page, &result, &String::from("<html>New content</html>"), None).unwrap();
+
+        assert_eq!(db.read_incident_for_id(incident.incident_id()).unwrap(), incident);
+
+        let dom = db.insert_dom_for_page(&page, String::from(incident.offending_dom())).unwrap();
+
+        let approval = db.insert_approval_for_page(&page, &incident, &dom, "editor").unwrap();
+
+        assert_eq!(approval.incident_id(), incident.incident_id());
+        assert_eq!(approval.dom_id(), dom.dom_id());
+        assert_eq!(approval.approver(), "editor");
+        assert_eq!(approval.defacement_count(), 3);
+        assert_eq!(db.list_approvals_for_page(&page).unwrap(), vec![approval]);
+
+        db.delete_dom_for_page(&page, dom).unwrap();
+        db.del_tracked_page(page).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_incidents() {
+        let db: SQLLiteDefacementDB<String> = SQLLiteDefacementDB::new();
+
+        let page = db.insert_tracked_page("https://example.org/incidents", 42).unwrap();
+
+        assert_eq!(db.read_open_incident_for_page(&page).unwrap(), None);
+
+        let result = AggregatedResult::new(String::from("AnyDefaced"), Verdict::Defaced, 0.75,
+                                           vec![ComparatorResult::new("Checksum", CompareResult::maybe_defaced("Changed")),
+                                                ComparatorResult::new("Domain", CompareResult::defaced(0.75, String::from("New domain")))]);
+
+        let mut incident = db.open_incident_for_page(&page, &result, &String::from("<html>Hacked</html>"),
+                                                     Some(&vec![1, 2, 3])).unwrap();
+
+        assert_eq!(incident.offending_screenshot(), Some(&vec![1, 2, 3]));
+
+        assert_eq!(incident.triggering_results(), &vec![result.breakdown()[1].clone()]);
+        assert_eq!(db.read_open_incident_for_page(&page).unwrap(), Some(incident.clone()));
+
+        db.add_notified_contact_to_incident(&mut incident, &UserCommunication::new(7, 42, Email(String::from("a@b.c")))).unwrap();
+        db.resolve_incident(&mut incident, IncidentResolution::Recovered).unwrap();
+
+        assert!(!incident.is_open());
+        assert_eq!(db.read_open_incident_for_page(&page).unwrap(), None);
+
+        let mut filter = IncidentFilter::new();
+
+        assert_eq!(db.list_incidents_for_page(&page, &filter).unwrap(), vec![incident.clone()]);
+
+        filter.set_open(Some(true));
+
+        assert!(db.list_incidents_for_page(&page, &filter).unwrap().is_empty());
+
+        filter.set_open(None);
+        filter.set_resolution(Some(IncidentResolution::Recovered));
+
+        assert_eq!(db.list_incidents_for_user(&User::new(42, String::from("owner")), &filter).unwrap(), vec![incident.clone()]);
+
+        //Correcting the resolution keeps the time the incident ended
+        let end_time = incident.end_time();
+
+        db.resolve_incident(&mut incident, IncidentResolution::FalsePositive).unwrap();
+
+        assert_eq!(incident.end_time(), end_time);
+        assert!(db.list_incidents_for_page(&page, &filter).unwrap().is_empty());
+
+        db.del_tracked_page(page).unwrap();
+    }
+
     #[test]
     fn test_sqlite_store_dom() {
         let db: SQLLiteDefacementDB<String> = SQLLiteDefacementDB::new();
@@ -766,6 +1981,14 @@ mod sqlite_tests {
 
         assert_eq!(doms.unwrap(), vec![inserted_dom.clone()]);
 
+        assert!(db.read_accepted_doms_for_page(&tracked_page).unwrap().is_empty());
+
+        let mut inserted_dom = inserted_dom;
+
+        db.set_dom_accepted(&mut inserted_dom, true).unwrap();
+
+        assert_eq!(db.read_accepted_doms_for_page(&tracked_page).unwrap(), vec![inserted_dom.clone()]);
+
         let delete_result = db.delete_dom_for_page(&tracked_page, inserted_dom);
 
         assert!(delete_result.is_ok());
@@ -797,20 +2020,49 @@ mod sqlite_tests {
 
         assert_eq!(created_user, user_info);
 
+        let mut user_info = user_info;
+
+        db.update_locale_for_user(&mut user_info, Some("pt")).unwrap();
+
+        assert_eq!(user_info.locale(), Some("pt"));
+        assert_eq!(db.get_user_info_for_id(user_info.user_id()).unwrap(), user_info);
+
         let contact = db.insert_contact_for(&user_info, Email(String::from("nunonuninho2@gmail.com"))).unwrap();
 
+        let webhook = db.insert_contact_for(&user_info, CommData::Webhook {
+            url: String::from("https://hooks.example.org/defacements"),
+            secret: Some(String::from("s3cret")),
+        }).unwrap();
+
+        let matrix = db.insert_contact_for(&user_info, CommData::Matrix {
+            homeserver: String::from("https://matrix.example.org"),
+            room_id: String::from("!room:example.org"),
+            access_token: String::from("token"),
+        }).unwrap();
+
+        let telegram = db.insert_contact_for(&user_info, CommData::Telegram {
+            bot_token: String::from("123:abc"),
+            chat_id: String::from("-100200"),
+        }).unwrap();
+
         let result_contact_list = db.list_contacts_for(&user_info);
 
         assert!(result_contact_list.is_ok());
 
         let contact_list = result_contact_list.unwrap();
 
-        assert_eq!(vec![contact.clone()], contact_list);
+        assert_eq!(vec![contact.clone(), webhook.clone(), matrix.clone(), telegram.clone()], contact_list);
+
+        assert_eq!(db.get_contact_for_id(matrix.comm_id()).unwrap(), matrix);
 
         let result_delete_contact = db.delete_contact(contact);
 
         assert!(result_delete_contact.is_ok());
 
+        db.delete_contact(webhook).unwrap();
+        db.delete_contact(matrix).unwrap();
+        db.delete_contact(telegram).unwrap();
+
         let result_delete_user = db.delete_user(user_info);
 
         assert!(result_delete_user.is_ok() && result_delete_user.unwrap());
@@ -1,17 +1,37 @@
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use log::error;
 use log::info;
 use log::warn;
 use log::{debug, LevelFilter};
 
 use crate::communication::CommunicationMethod;
+use crate::communication::chat::matrix::MatrixCommunicator;
+use crate::communication::chat::slack::SlackCommunicator;
+use crate::communication::chat::telegram::TelegramCommunicator;
 use crate::communication::email::EmailCommunicator;
+use crate::communication::outbox::OutboxPolicy;
+use crate::communication::templates::NotificationTemplates;
+use crate::communication::webhook::WebhookCommunicator;
 use crate::comparators::checksum_comparator::ChecksumComparator;
 use crate::comparators::Comparator;
+use crate::comparators::aggregation::AggregationPolicy;
 use crate::comparators::diff_comparator::DiffComparator;
+use crate::comparators::domain_comparator::DomainComparator;
+use crate::comparators::script_comparator::ScriptComparator;
+use crate::comparators::signature_comparator::SignatureComparator;
+use crate::comparators::text_comparator::TextComparator;
+use crate::comparators::tree_comparator::TreeComparator;
+use crate::comparators::visual_comparator::VisualComparator;
 use crate::databases::sqlitedb::SQLLiteDefacementDB;
+use crate::normalisation::Normaliser;
 use crate::page_management::page_management::PageManager;
 use crate::parsers::chromium_parser::ChromiumParser;
+use crate::parsers::devtools_parser::DevToolsParser;
+use crate::parsers::http_parser::HttpParser;
+use crate::parsers::Parser;
+use toml::Value;
 
 pub mod parsers;
 pub mod comparators;
@@ -22,6 +42,24 @@ pub mod page_management {
 
 pub mod communication;
 pub mod databases;
+pub mod normalisation;
+
+//The files that are read from disk instead of being included in the binary (the signatures, so they can be edited
+//While the program runs, and the notification templates) are in the resources directory. It can be set with this
+//Environment variable, otherwise it's the resources directory next to the executable or the one it was built from
+const RESOURCES_DIR_VAR: &str = "DEFACEMENT_MON_RESOURCES";
+const SIGNATURES_FILE: &str = "signatures.toml";
+
+fn resources_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(RESOURCES_DIR_VAR) {
+        return PathBuf::from(dir);
+    }
+
+    std::env::current_exe().ok()
+        .and_then(|executable| executable.parent().map(|dir| dir.join("resources")))
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(|| PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/resources")))
+}
 
 #[tokio::main]
 async fn main() {
@@ -31,24 +69,101 @@ async fn main() {
 
     let database = SQLLiteDefacementDB::new();
 
-    debug!("Initializing chromium parser");
+    let resources_dir = resources_dir();
+
+    if !resources_dir.is_dir() {
+        error!("The resources directory {} does not exist, set {} to the directory with the signatures and the templates",
+            resources_dir.display(), RESOURCES_DIR_VAR);
+    }
+
+    debug!("Initializing parsers");
+
+    let parser_config = include_str!("../resources/parser.toml").parse::<Value>().unwrap();
+
+    let browser_parser: Box<dyn Parser<String>> = match parser_config["parser"].as_str() {
+        Some("devtools") => {
+            let pool_size = parser_config.get("pool_size")
+                .and_then(|size| size.as_integer())
+                .unwrap_or(1);
+
+            let chromium_path = parser_config.get("chromium_path")
+                .and_then(|path| path.as_str())
+                .map(PathBuf::from);
+
+            Box::new(DevToolsParser::new(pool_size as usize, chromium_path))
+        }
+        _ => {
+            Box::new(ChromiumParser::new())
+        }
+    };
+
+    let http_config = &parser_config["http"];
+
+    let headers = match http_config.get("headers").and_then(|headers| headers.as_table()) {
+        Some(headers) => {
+            headers.iter()
+                .map(|(header, value)| (header.clone(), String::from(value.as_str().unwrap())))
+                .collect()
+        }
+        None => { Vec::new() }
+    };
 
-    let parser = ChromiumParser::new();
+    let http_parser = HttpParser::new(http_config["user_agent"].as_str().unwrap(),
+                                      Duration::from_secs(http_config["timeout_secs"].as_integer().unwrap() as u64),
+                                      http_config["max_redirects"].as_integer().unwrap() as u32,
+                                      headers);
+
+    let parsers: Vec<Box<dyn Parser<String>>> = vec![browser_parser, Box::new(http_parser)];
 
     debug!("Init comparators");
     let comparators: Vec<Box<dyn Comparator<String>>> = vec![Box::new(ChecksumComparator::new()),
-                                                     Box::new(DiffComparator::new())];
+                                                     Box::new(SignatureComparator::new(resources_dir.join(SIGNATURES_FILE).to_str().unwrap())),
+                                                     Box::new(DomainComparator::new()),
+                                                     Box::new(ScriptComparator::new(include_str!("../resources/scripts.toml"))),
+                                                     Box::new(TextComparator::new()),
+                                                     Box::new(DiffComparator::new()),
+                                                     Box::new(TreeComparator::new())];
+
+    let screenshot_comparators: Vec<Box<dyn Comparator<Vec<u8>>>> = vec![Box::new(VisualComparator::new())];
+
+    let normaliser = Normaliser::new(include_str!("../resources/normalisation.toml"));
+
+    let aggregation = AggregationPolicy::from_config(include_str!("../resources/aggregation.toml"))
+        .expect("Failed to read the aggregation policy");
+
+    let check_log_config = include_str!("../resources/check_log.toml").parse::<Value>().unwrap();
+
+    let check_log_retention = Duration::from_secs(check_log_config["retention_days"].as_integer().unwrap() as u64 * 60 * 60 * 24);
+
+    let outbox = OutboxPolicy::from_config(include_str!("../resources/outbox.toml"))
+        .expect("Failed to read the outbox configuration");
 
     debug!("Init email communication");
 
     let config_file = include_str!("../resources/email.toml");
 
-    let communicators: Vec<Box<dyn CommunicationMethod<String>>> = vec![Box::new(EmailCommunicator::new(config_file))];
+    let chat_config = include_str!("../resources/chat.toml");
+
+    let templates = match NotificationTemplates::new(include_str!("../resources/templates.toml"), &resources_dir) {
+        Ok(templates) => { Arc::new(templates) }
+        Err(e) => {
+            panic!("Failed to load the notification templates, {} can be set to the resources directory. {}",
+                   RESOURCES_DIR_VAR, e);
+        }
+    };
+
+    let communicators: Vec<Box<dyn CommunicationMethod<String>>> = vec![Box::new(EmailCommunicator::new(config_file, templates.clone())),
+                                                                        Box::new(WebhookCommunicator::new(include_str!("../resources/webhook.toml"), templates.clone())),
+                                                                        Box::new(SlackCommunicator::new(chat_config, templates.clone())),
+                                                                        Box::new(MatrixCommunicator::new(chat_config, templates.clone())),
+                                                                        Box::new(TelegramCommunicator::new(chat_config, templates.clone()))];
 
     debug!("Initializing program....");
 
     let page_manager = Arc::new(PageManager::new(database.clone(), database,
-                                                 parser, comparators, communicators));
+                                                 parsers, comparators, screenshot_comparators,
+                                                 communicators, normaliser, aggregation, check_log_retention,
+                                                 outbox));
 
     page_manager.start().await;
 }
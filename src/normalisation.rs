@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use log::error;
+use regex::Regex;
+use scraper::{Html, Node, Selector};
+use toml::Value;
+use crate::databases::TrackedPage;
+
+/*
+Dynamic pages differ on every fetch because of CSRF tokens, nonces, timestamps, session IDs...
+Which inflates the thresholds learned for them until real defacements fall under them.
+Every dom goes through the normalisation rules before it is stored and before it is compared,
+so those values never show up as differences.
+ */
+
+///A single normalisation rule, written as one of:
+/// replace <regex> => <replacement>
+/// remove <CSS selector>
+/// strip <attribute name>
+pub enum NormalisationRule {
+    //Replace every match of the regex, the replacement can use the regex groups ($1...)
+    Replace(Regex, String),
+    //Remove the elements that match the selector (and everything inside them)
+    Remove(Selector),
+    //Remove the attribute from every element
+    StripAttribute(String),
+}
+
+pub struct Normaliser {
+    //Rules applied to every page, before the rules of the page
+    default_rules: Vec<NormalisationRule>,
+    //The compiled rules of every page, by the ID of the page
+    page_rules: RwLock<HashMap<u32, Arc<PageRules>>>,
+}
+
+///The rules of a page, compiled from the rules, ignored regions and volatility mask they were compiled from.
+///They are compiled again when any of those changes
+struct PageRules {
+    normalisation_rules: Vec<String>,
+    ignored_selectors: Vec<String>,
+    volatility_mask: Vec<String>,
+    rules: Vec<NormalisationRule>,
+    mask: Vec<Selector>,
+}
+
+impl NormalisationRule {
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let rule = rule.trim();
+
+        let (kind, argument) = match rule.split_once(char::is_whitespace) {
+            Some((kind, argument)) => { (kind, argument.trim()) }
+            None => { return Err(format!("The rule {} has no argument", rule)); }
+        };
+
+        match kind.to_ascii_lowercase().as_str() {
+            "replace" => {
+                let (pattern, replacement) = match argument.split_once(" => ") {
+                    Some((pattern, replacement)) => { (pattern, replacement) }
+                    None => { (argument, "") }
+                };
+
+                match Regex::new(pattern) {
+                    Ok(regex) => { Ok(NormalisationRule::Replace(regex, String::from(replacement))) }
+                    Err(e) => { Err(e.to_string()) }
+                }
+            }
+            "remove" => {
+                match Selector::parse(argument) {
+                    Ok(selector) => { Ok(NormalisationRule::Remove(selector)) }
+                    Err(e) => { Err(format!("Invalid selector {}. {:?}", argument, e)) }
+                }
+            }
+            "strip" => {
+                Ok(NormalisationRule::StripAttribute(argument.to_ascii_lowercase()))
+            }
+            _ => {
+                Err(format!("Unknown rule type {}, expected replace, remove or strip", kind))
+            }
+        }
+    }
+
+    fn is_dom_rule(&self) -> bool {
+        !matches!(self, NormalisationRule::Replace(_, _))
+    }
+}
+
+///Parse the rules, logging and skipping the ones that are not valid
+fn parse_rules<'a, I>(rules: I) -> Vec<NormalisationRule>
+    where I: Iterator<Item=&'a str> {
+    rules.filter_map(|rule| {
+        match NormalisationRule::parse(rule) {
+            Ok(rule) => { Some(rule) }
+            Err(e) => {
+                error!("Ignoring invalid normalisation rule {}. {}", rule, e);
+
+                None
+            }
+        }
+    }).collect()
+}
+
+///Turn the ignored regions of a page into rules that remove them, logging and skipping invalid selectors
+fn parse_ignored_regions(selectors: &[String]) -> Vec<NormalisationRule> {
+    selectors.iter().filter_map(|selector| {
+        match Selector::parse(selector) {
+            Ok(selector) => { Some(NormalisationRule::Remove(selector)) }
+            Err(e) => {
+                error!("Ignoring invalid ignored region selector {}. {:?}", selector, e);
+
+                None
+            }
+        }
+    }).collect()
+}
+
+fn apply_dom_rules<'a, I>(rules: I, dom: &str) -> String
+    where I: Iterator<Item=&'a NormalisationRule> {
+    let mut document = Html::parse_document(dom);
+
+    for rule in rules {
+        match rule {
+            NormalisationRule::Remove(selector) => {
+                let removed: Vec<_> = document.select(selector).map(|element| element.id()).collect();
+
+                for node_id in removed {
+                    if let Some(mut node) = document.tree.get_mut(node_id) {
+                        node.detach();
+                    }
+                }
+            }
+            NormalisationRule::StripAttribute(attribute) => {
+                for node in document.tree.values_mut() {
+                    if let Node::Element(element) = node {
+                        element.attrs.retain(|name, _| !(*name.local).eq_ignore_ascii_case(attribute));
+                    }
+                }
+            }
+            NormalisationRule::Replace(_, _) => {}
+        }
+    }
+
+    document.html()
+}
+
+///Parse the selectors of the volatility mask, logging and skipping the ones that are not valid
+fn parse_volatility_mask(mask: &[String]) -> Vec<Selector> {
+    mask.iter().filter_map(|selector| {
+        match Selector::parse(selector) {
+            Ok(selector) => { Some(selector) }
+            Err(e) => {
+                error!("Ignoring invalid volatility mask selector {}. {:?}", selector, e);
+
+                None
+            }
+        }
+    }).collect()
+}
+
+///
+/// Remove the volatile regions of the page. Every region is found before any of them is removed, as the
+/// selectors of the mask can point to the elements by their position, which removing their siblings would change
+pub fn mask_volatile_regions(mask: &[String], dom: &str) -> String {
+    mask_regions(&parse_volatility_mask(mask), dom)
+}
+
+fn mask_regions(selectors: &[Selector], dom: &str) -> String {
+    if selectors.is_empty() {
+        return String::from(dom);
+    }
+
+    let mut document = Html::parse_document(dom);
+
+    let masked: Vec<_> = selectors.iter()
+        .flat_map(|selector| document.select(selector).map(|element| element.id()).collect::<Vec<_>>())
+        .collect();
+
+    for node_id in masked {
+        if let Some(mut node) = document.tree.get_mut(node_id) {
+            node.detach();
+        }
+    }
+
+    document.html()
+}
+
+///
+/// Apply the rules to the dom. The rules that work on the elements (remove and strip) are applied first,
+/// Then the regex replacements in the order they were given.
+/// The dom is only parsed and written again when there are element rules, so the formatting is otherwise kept
+pub fn apply_rules(rules: &[NormalisationRule], dom: &str) -> String {
+    let mut dom = if rules.iter().any(|rule| rule.is_dom_rule()) {
+        apply_dom_rules(rules.iter(), dom)
+    } else {
+        String::from(dom)
+    };
+
+    for rule in rules {
+        if let NormalisationRule::Replace(regex, replacement) = rule {
+            dom = regex.replace_all(dom.as_str(), replacement.as_str()).into_owned();
+        }
+    }
+
+    dom
+}
+
+impl Normaliser {
+    pub fn new(config_file: &str) -> Self {
+        let value = config_file.parse::<Value>().unwrap();
+
+        let default_rules = match value.get("rules").and_then(|rules| rules.as_array()) {
+            Some(rules) => { parse_rules(rules.iter().filter_map(|rule| rule.as_str())) }
+            None => { Vec::new() }
+        };
+
+        Self { default_rules, page_rules: RwLock::new(HashMap::new()) }
+    }
+
+    ///The compiled rules of the page, they are only compiled again when the page's rules changed since the last time
+    fn rules_for(&self, page: &TrackedPage) -> Arc<PageRules> {
+        if let Some(rules) = self.page_rules.read().unwrap().get(&page.page_id()) {
+            if rules.is_compiled_from(page) {
+                return rules.clone();
+            }
+        }
+
+        let mut rules = parse_rules(page.normalisation_rules().iter().map(|rule| rule.as_str()));
+
+        rules.extend(parse_ignored_regions(page.ignored_selectors()));
+
+        let rules = Arc::new(PageRules {
+            normalisation_rules: page.normalisation_rules().clone(),
+            ignored_selectors: page.ignored_selectors().clone(),
+            volatility_mask: page.volatility_mask().clone(),
+            rules,
+            mask: parse_volatility_mask(page.volatility_mask()),
+        });
+
+        self.page_rules.write().unwrap().insert(page.page_id(), rules.clone());
+
+        rules
+    }
+
+    ///
+    /// Normalise the dom with the default rules followed by the rules of the page.
+    /// The ignored regions of the page are removed, so they are never compared,
+    /// And so is the volatility mask, which was learned from doms that had all the other rules applied.
+    /// Must be applied once to a dom as it was fetched, the mask selectors are positional
+    pub fn normalise(&self, page: &TrackedPage, dom: &str) -> String {
+        let page_rules = self.rules_for(page);
+
+        if self.default_rules.is_empty() && page_rules.rules.is_empty() && page_rules.mask.is_empty() {
+            return String::from(dom);
+        }
+
+        let dom = apply_rules(&self.default_rules, dom);
+
+        let dom = apply_rules(&page_rules.rules, dom.as_str());
+
+        mask_regions(&page_rules.mask, dom.as_str())
+    }
+}
+
+impl PageRules {
+    fn is_compiled_from(&self, page: &TrackedPage) -> bool {
+        self.normalisation_rules == *page.normalisation_rules() && self.ignored_selectors == *page.ignored_selectors()
+            && self.volatility_mask == *page.volatility_mask()
+    }
+}
+
+#[cfg(test)]
+mod normalisation_tests {
+    use toml::Value;
+    use crate::databases::{TrackedPage, TrackedPageType};
+    use crate::normalisation::{apply_rules, mask_volatile_regions, NormalisationRule, Normaliser};
+
+    fn rules(rules: &[&str]) -> Vec<NormalisationRule> {
+        rules.iter().map(|rule| NormalisationRule::parse(rule).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_replace_rules_keep_formatting() {
+        let dom = "<html>\n<body>\n<p>Generated at 1650000000</p>\n<a href=\"/cart;jsessionid=AB12\">Cart</a>\n</body>\n</html>";
+
+        let rules = rules(&["replace \\b1[0-9]{9}\\b => TIMESTAMP", "replace (?i)jsessionid=[0-9a-z]+"]);
+
+        assert_eq!(apply_rules(&rules, dom),
+                   "<html>\n<body>\n<p>Generated at TIMESTAMP</p>\n<a href=\"/cart;\">Cart</a>\n</body>\n</html>");
+    }
+
+    #[test]
+    fn test_dom_rules() {
+        let dom = "<html><head></head><body><div class=\"ad\" data-slot=\"8812\">Ad</div>\
+        <form><input type=\"hidden\" name=\"csrf_token\" value=\"x1\"><p data-slot=\"77\">Text</p></form></body></html>";
+
+        let rules = rules(&["remove div.ad", "remove input[name=\"csrf_token\"]", "strip data-slot"]);
+
+        assert_eq!(apply_rules(&rules, dom),
+                   "<html><head></head><body><form><p>Text</p></form></body></html>");
+
+        assert!(NormalisationRule::parse("remove ###").is_err());
+        assert!(NormalisationRule::parse("rename a").is_err());
+        assert!(NormalisationRule::parse("strip").is_err());
+    }
+
+    #[test]
+    fn test_ignored_regions_keep_page_static() {
+        let normaliser = Normaliser::new("rules = []");
+
+        let mut page = TrackedPage::new(1, String::from("https://example.com"), 1, 0, 0, 0, 0, 5, false,
+                                        TrackedPageType::Static);
+
+        page.set_ignored_selectors(vec![String::from("#ticker"), String::from("aside.comments")]);
+
+        let dom = "<html><head></head><body><p>About us</p><div id=\"ticker\">ACME +2%</div>\
+        <aside class=\"comments\"><p>First!</p></aside></body></html>";
+        let dom_2 = dom.replace("ACME +2%", "ACME -1%").replace("First!", "Nice page");
+
+        assert_eq!(normaliser.normalise(&page, dom), normaliser.normalise(&page, dom_2.as_str()));
+
+        let defaced = dom.replace("About us", "Hacked");
+
+        assert_ne!(normaliser.normalise(&page, dom), normaliser.normalise(&page, defaced.as_str()));
+    }
+
+    #[test]
+    fn test_compiled_rules_follow_the_page() {
+        let normaliser = Normaliser::new("rules = []");
+
+        let mut page = TrackedPage::new(1, String::from("https://example.com"), 1, 0, 0, 0, 0, 5, false,
+                                        TrackedPageType::Static);
+
+        page.set_normalisation_rules(vec![String::from("replace [0-9]+ => N")]);
+
+        assert_eq!(normaliser.normalise(&page, "<p>Visitor 1234</p>"), "<p>Visitor N</p>");
+
+        //The rules compiled for the page are not used once the page has other rules
+        page.set_normalisation_rules(vec![String::from("replace Visitor => Guest")]);
+
+        assert_eq!(normaliser.normalise(&page, "<p>Visitor 1234</p>"), "<p>Guest 1234</p>");
+    }
+
+    #[test]
+    fn test_mask_volatile_regions() {
+        let dom = "<html><head></head><body><p>One</p><p>Two</p><p>Three</p></body></html>";
+
+        //Removing the second paragraph first must not make the third one the second
+        let masked = mask_volatile_regions(&[String::from("html > body:nth-child(2) > p:nth-child(2)"),
+            String::from("html > body:nth-child(2) > p:nth-child(3)")], dom);
+
+        assert!(masked.contains("One"));
+        assert!(!masked.contains("Two"));
+        assert!(!masked.contains("Three"));
+    }
+
+    #[test]
+    fn test_default_rules_are_valid() {
+        let config = include_str!("../resources/normalisation.toml").parse::<Value>().unwrap();
+
+        for rule in config["rules"].as_array().unwrap() {
+            assert!(NormalisationRule::parse(rule.as_str().unwrap()).is_ok(), "Invalid rule {}", rule);
+        }
+    }
+}
@@ -1,19 +1,25 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::{BufRead, StdinLock};
 use std::num::ParseIntError;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use log::{debug, error, trace, warn};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use log::{debug, error, info, trace, warn};
+use scraper::Selector;
 
 use tokio::time;
 
-use crate::communication::{CommData, CommunicationMethod, UserCommunication};
-use crate::comparators::{Comparator, CompareResult};
-use crate::comparators::diff_comparator::{analyse_dynamic_page, compare_dom_with_diff};
-use crate::databases::{DEFAULT_INDEXING_INTERVAL, StoredDom, TrackedPage, TrackedPageType, User, UserDB, WebsiteDefacementDB};
+use crate::communication::{CommData, CommunicationMethod, DefacementReport, Notification, RecoveryReport, UserCommunication};
+use crate::communication::dispatcher::NotificationDispatcher;
+use crate::communication::outbox::OutboxPolicy;
+use crate::comparators::{Comparator, ComparatorResult, Verdict, verdict_to_str};
+use crate::comparators::aggregation::{AggregatedResult, AggregationPolicy};
+use crate::comparators::baseline::{ADAPTIVE_BASELINE_WEIGHT, BaselineModel, DEFAULT_THRESHOLD_POLICY, threshold_policy_to_str, ThresholdPolicy};
+use crate::comparators::diff_comparator::{analyse_dynamic_page, compare_dom_with_diff, summarise_diff};
+use crate::databases::{Approval, CheckRecord, DEFAULT_INDEXING_INTERVAL, Incident, IncidentFilter, IncidentResolution, incident_resolution_to_str, notification_kind_to_str, NotificationKind, OutboxEntry, OutboxStatus, parser_type_to_str, ParserType, StoredDom, TrackedPage, TrackedPageType, User, UserDB, WebsiteDefacementDB};
 use crate::databases::TrackedPageType::Dynamic;
 use crate::DiffComparator;
-use crate::parsers::Parser;
+use crate::normalisation::{NormalisationRule, Normaliser};
+use crate::parsers::{FetchedPage, Parser};
 
 /*
 60 minutes between checks
@@ -22,34 +28,61 @@ const TIME_BETWEEN_CHECKS: u128 = 1 * 1 * 1000;
 const TIME_BETWEEN_INDEX_CHECKS: u128 = 30 * TIME_BETWEEN_CHECKS;
 ///60 seconds between attempting checks
 const TIME_INTERVAL: Duration = Duration::from_millis(1 * 1000);
+///Amount of checks that are listed individually when showing the check log of a page
+const LISTED_CHECKS: usize = 20;
+///Amount of added and of removed lines shown when approving the change of a page
+const APPROVAL_DIFF_LINES: usize = 10;
 
-pub struct PageManager<T, V, K> where
+pub struct PageManager<T, V> where
     T: WebsiteDefacementDB<String>,
-    V: UserDB,
-    K: Parser<String> {
+    V: UserDB {
     //A set of all page_id that are currently being indexed
     currently_indexing: Mutex<BTreeSet<u32>>,
     tracked_page_db: T,
     user_db: V,
-    parser: K,
+    //The parsers available to fetch pages, each page is fetched
+    //With the parser that matches its parser type
+    parsers: Vec<Box<dyn Parser<String>>>,
     comparators: Vec<Box<dyn Comparator<String>>>,
-    communications: Vec<Box<dyn CommunicationMethod<String>>>,
+    //Comparators for the screenshots of the pages that have visual comparison enabled
+    screenshot_comparators: Vec<Box<dyn Comparator<Vec<u8>>>>,
+    //Sends the notifications to every contact through the communication methods that match it
+    dispatcher: NotificationDispatcher<String>,
+    //How the notifications of the outbox are delivered and retried
+    outbox: OutboxPolicy,
+    //Held while the outbox is being delivered, so the deliveries of the worker and of the checks don't overlap
+    delivering_outbox: Mutex<()>,
+    //Applied to every dom before it is stored or compared
+    normaliser: Normaliser,
+    //Combines the results of all the comparators into the verdict of a check
+    aggregation: AggregationPolicy,
+    //How long the record of every check is kept in the check log
+    check_log_retention: Duration,
 }
 
-impl<T, V, K> PageManager<T, V, K>
+impl<T, V> PageManager<T, V>
     where T: WebsiteDefacementDB<String> + 'static,
-          V: UserDB + 'static,
-          K: Parser<String> + 'static {
-    pub fn new(tracked_page_db: T, user_db: V, parser: K,
+          V: UserDB + 'static {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(tracked_page_db: T, user_db: V, parsers: Vec<Box<dyn Parser<String>>>,
                comparators: Vec<Box<dyn Comparator<String>>>,
-               communications: Vec<Box<dyn CommunicationMethod<String>>>) -> Self {
+               screenshot_comparators: Vec<Box<dyn Comparator<Vec<u8>>>>,
+               communications: Vec<Box<dyn CommunicationMethod<String>>>,
+               normaliser: Normaliser, aggregation: AggregationPolicy, check_log_retention: Duration,
+               outbox: OutboxPolicy) -> Self {
         Self {
             currently_indexing: Mutex::new(BTreeSet::new()),
             tracked_page_db,
             user_db,
-            parser,
+            parsers,
             comparators,
-            communications,
+            screenshot_comparators,
+            dispatcher: NotificationDispatcher::new(communications),
+            outbox,
+            delivering_outbox: Mutex::new(()),
+            normaliser,
+            aggregation,
+            check_log_retention,
         }
     }
 
@@ -66,6 +99,23 @@ impl<T, V, K> PageManager<T, V, K>
             }
         });
 
+        let outbox_man = self.clone();
+
+        tokio::spawn(async move {
+            let mut duration = time::interval(outbox_man.outbox.poll_interval());
+
+            loop {
+                duration.tick().await;
+
+                let delivering_man = outbox_man.clone();
+
+                //Sending blocks, so it must not hold up the runtime
+                if let Err(e) = tokio::task::spawn_blocking(move || { delivering_man.deliver_outbox() }).await {
+                    error!("The delivery of the outbox stopped unexpectedly. {}", e);
+                }
+            }
+        });
+
         self.show_menu();
     }
 
@@ -87,6 +137,13 @@ impl<T, V, K> PageManager<T, V, K>
             println!("8- Delete user.");
             println!("9- Register contact for user.");
             println!("10- Delete contact for user.");
+            println!("11- Reload comparator configurations (Defacement signatures, ...)");
+            println!("12- Manage the accepted versions of a tracked page (A/B tests, rotating banners...)");
+            println!("13- Approve the change that started the open incident of a tracked page as legitimate (Resets its defacement count)");
+            println!("14- List the incidents of a tracked page or of a user.");
+            println!("15- Show the check log of a tracked page (Uptime, fetch times and comparator scores).");
+            println!("16- Show the notification outbox and retry the notifications that could not be delivered.");
+            println!("17- Choose the language of the notifications of a user.");
             println!("=============================================");
 
             let mut line = String::new();
@@ -126,8 +183,18 @@ impl<T, V, K> PageManager<T, V, K>
                 }
                 4 => {
                     match self.read_page_from_stdin(&mut stdin) {
+                        //The parser of new pages is chosen when they are inserted
                         Ok(mut page) => {
-                            self.alter_tracked_page(&mut stdin, page);
+                            match read_parser_type(&mut stdin, page.parser_type()) {
+                                Ok(parser_type) => {
+                                    page.set_parser_type(parser_type);
+
+                                    self.alter_tracked_page(&mut stdin, page);
+                                }
+                                Err(e) => {
+                                    println!("{}", e);
+                                }
+                            }
                         }
                         Err(e) => {
                             println!("{}", e);
@@ -167,6 +234,55 @@ impl<T, V, K> PageManager<T, V, K>
                         }
                     }
                 }
+                11 => {
+                    self.reload_comparators();
+                }
+                12 => {
+                    match self.read_page_from_stdin(&mut stdin) {
+                        Ok(page) => {
+                            self.manage_accepted_doms(&mut stdin, &page);
+                        }
+                        Err(e) => {
+                            println!("{}", e);
+                        }
+                    }
+                }
+                13 => {
+                    match self.read_page_from_stdin(&mut stdin) {
+                        Ok(page) => {
+                            self.approve_change(&mut stdin, &page);
+                        }
+                        Err(e) => {
+                            println!("{}", e);
+                        }
+                    }
+                }
+                14 => {
+                    self.display_incidents(&mut stdin);
+                }
+                15 => {
+                    match self.read_page_from_stdin(&mut stdin) {
+                        Ok(page) => {
+                            self.display_check_log(&mut stdin, &page);
+                        }
+                        Err(e) => {
+                            println!("{}", e);
+                        }
+                    }
+                }
+                16 => {
+                    self.manage_outbox(&mut stdin);
+                }
+                17 => {
+                    match self.read_user_info(&mut stdin) {
+                        Ok(mut user) => {
+                            self.set_user_locale(&mut stdin, &mut user);
+                        }
+                        Err(error) => {
+                            println!("{}", error)
+                        }
+                    }
+                }
                 _ => { println!("Could not find that option!") }
             }
         }
@@ -194,7 +310,17 @@ impl<T, V, K> PageManager<T, V, K>
 
                 page_url.pop();
 
-                self.tracked_page_db().insert_tracked_page(page_url.as_str(), id)
+                let parser_type = read_parser_type(stdin, &ParserType::Browser)?;
+
+                let mut page = self.tracked_page_db().insert_tracked_page(page_url.as_str(), id)?;
+
+                if parser_type != *page.parser_type() {
+                    page.set_parser_type(parser_type);
+
+                    self.tracked_page_db().update_tracking_type_for_page(&page)?;
+                }
+
+                Ok(page)
             }
             Err(e) => Err(e.to_string())
         };
@@ -246,7 +372,11 @@ impl<T, V, K> PageManager<T, V, K>
                         tracked_page.set_tracked_page_type(TrackedPageType::Static);
                     }
                     2 => {
-                        tracked_page.set_tracked_page_type(TrackedPageType::Dynamic(-1.0));
+                        //Keep what was learned about the page if it was already dynamic
+                        if let TrackedPageType::Static = tracked_page.tracked_page_type() {
+                            tracked_page.set_tracked_page_type(
+                                TrackedPageType::Dynamic(BaselineModel::unlearned(DEFAULT_THRESHOLD_POLICY)));
+                        }
                     }
                     _ => {}
                 }
@@ -257,6 +387,337 @@ impl<T, V, K> PageManager<T, V, K>
             }
         }
 
+        if let TrackedPageType::Dynamic(model) = tracked_page.tracked_page_type() {
+            let mut model = model.clone();
+
+            println!("How should the threshold be calculated from the changes seen while analysing the page?");
+            println!("<n>sd - The mean change plus n standard deviations (3sd)");
+            println!("p<n> - The nth percentile of the changes (p99)");
+            println!("<n> - A fixed threshold of n% of the page");
+            println!("Press ENTER to keep the current ({})", threshold_policy_to_str(&model.policy()));
+
+            line.clear();
+
+            if let Err(e) = stdin.read_line(&mut line) {
+                println!("Failed to read the policy. {:?}", e);
+                return;
+            }
+
+            line.pop();
+
+            if !line.is_empty() {
+                match ThresholdPolicy::parse(line.as_str()) {
+                    Ok(policy) => { model.set_policy(policy); }
+                    Err(e) => {
+                        println!("Failed to read the policy. {}", e);
+                        return;
+                    }
+                }
+            }
+
+            tracked_page.set_tracked_page_type(TrackedPageType::Dynamic(model));
+
+            println!("Insert how many samples of the page should be collected when analysing it.");
+            println!("Press ENTER to keep the current ({})", tracked_page.sample_count());
+
+            line.clear();
+
+            if let Err(e) = stdin.read_line(&mut line) {
+                println!("Failed to read the sample count. {:?}", e);
+                return;
+            }
+
+            line.pop();
+
+            if !line.is_empty() {
+                match line.parse::<u32>() {
+                    Ok(sample_count) if sample_count >= 2 => { tracked_page.set_sample_count(sample_count); }
+                    Ok(_) => {
+                        println!("At least 2 samples are needed to analyse the page.");
+                        return;
+                    }
+                    Err(e) => {
+                        println!("Failed to read the sample count. {:?}, {}", e, line);
+                        return;
+                    }
+                }
+            }
+
+            println!("Insert the seconds between each sample.");
+            println!("Press ENTER to keep the current ({})", tracked_page.sample_interval() / 1000);
+
+            line.clear();
+
+            if let Err(e) = stdin.read_line(&mut line) {
+                println!("Failed to read the sample interval. {:?}", e);
+                return;
+            }
+
+            line.pop();
+
+            if !line.is_empty() {
+                match line.parse::<u64>() {
+                    Ok(interval) if interval >= 1 => {
+                        tracked_page.set_sample_interval(Duration::from_secs(interval).as_millis());
+                    }
+                    Ok(_) => {
+                        println!("There must be at least a second between each sample.");
+                        return;
+                    }
+                    Err(e) => {
+                        println!("Failed to read the sample interval. {:?}, {}", e, line);
+                        return;
+                    }
+                }
+            }
+
+            println!("Should the baseline keep learning from the checks where the page is not considered defaced? (y/n)");
+            println!("This follows pages that change slowly over time. Checks during a suspected defacement are never learned.");
+            println!("Press ENTER to keep the current ({})", if tracked_page.adaptive_baseline() { "y" } else { "n" });
+
+            line.clear();
+
+            if let Err(e) = stdin.read_line(&mut line) {
+                println!("Failed to read your choice. {:?}", e);
+                return;
+            }
+
+            line.pop();
+
+            match line.to_ascii_lowercase().as_str() {
+                "y" => { tracked_page.set_adaptive_baseline(true); }
+                "n" => { tracked_page.set_adaptive_baseline(false); }
+                _ => {}
+            }
+        }
+
+        //Plain HTTP pages are never rendered, so there is no screenshot to compare
+        if *tracked_page.parser_type() == ParserType::Http {
+            if tracked_page.screenshot_threshold().is_some() {
+                println!("Pages fetched with plain HTTP can't be compared visually, the visual comparison was disabled.");
+
+                tracked_page.set_screenshot_threshold(None);
+            }
+
+            line.clear();
+        } else {
+            println!("Insert the % of the page's screenshot that can change before the page is considered defaced.");
+            println!("Insert 0 to disable the visual comparison of the page.");
+            println!("Press ENTER to keep the current ({})", match tracked_page.screenshot_threshold() {
+                Some(threshold) => { format!("{}%", threshold) }
+                None => { String::from("Disabled") }
+            });
+
+            line.clear();
+
+            if let Err(e) = stdin.read_line(&mut line) {
+                println!("Failed to read the threshold. {:?}", e);
+                return;
+            }
+
+            line.pop();
+        }
+
+        if !line.is_empty() {
+            match line.parse::<f64>() {
+                Ok(threshold) => {
+                    if threshold > 0.0 {
+                        tracked_page.set_screenshot_threshold(Some(threshold));
+                    } else {
+                        tracked_page.set_screenshot_threshold(None);
+                    }
+                }
+                Err(e) => {
+                    println!("Failed to read the threshold. {:?}, {}", e, line);
+                    return;
+                }
+            }
+        }
+
+        println!("Insert the third party domains this page is allowed to reference, separated by commas.");
+        println!("Subdomains of the inserted domains are also allowed. Insert - to clear the list.");
+        println!("Press ENTER to keep the current ({})", tracked_page.allowed_domains().join(", "));
+
+        line.clear();
+
+        if let Err(e) = stdin.read_line(&mut line) {
+            println!("Failed to read the domains. {:?}", e);
+            return;
+        }
+
+        line.pop();
+
+        if line == "-" {
+            tracked_page.set_allowed_domains(Vec::new());
+        } else if !line.is_empty() {
+            tracked_page.set_allowed_domains(line.split(',')
+                .map(|domain| domain.trim().to_ascii_lowercase())
+                .filter(|domain| !domain.is_empty())
+                .collect());
+        }
+
+        println!("Insert the normalisation rules for this page, one per line, followed by an empty line.");
+        println!("The rules are applied to the page before it is compared, so values that change on every fetch");
+        println!("(CSRF tokens, nonces, timestamps...) are not seen as changes. The available rules are:");
+        println!("replace <regex> => <replacement>");
+        println!("remove <CSS selector>");
+        println!("strip <attribute name>");
+        println!("Insert - to clear the rules. Press ENTER to keep the current ({} rules)",
+                 tracked_page.normalisation_rules().len());
+
+        let mut rules = Vec::new();
+
+        loop {
+            line.clear();
+
+            if let Err(e) = stdin.read_line(&mut line) {
+                println!("Failed to read the rule. {:?}", e);
+                return;
+            }
+
+            line.pop();
+
+            if line.is_empty() {
+                break;
+            }
+
+            if line == "-" {
+                tracked_page.set_normalisation_rules(Vec::new());
+                continue;
+            }
+
+            match NormalisationRule::parse(line.as_str()) {
+                Ok(_) => { rules.push(line.clone()); }
+                Err(e) => { println!("Ignoring the rule {}. {}", line, e); }
+            }
+        }
+
+        if !rules.is_empty() {
+            tracked_page.set_normalisation_rules(rules);
+        }
+
+        println!("Insert the CSS selectors of the regions of the page that should never be compared, one per line,");
+        println!("followed by an empty line (News tickers, comment boxes...). The rest of the page can then be tracked as Static.");
+        println!("The regions are blanked in the screenshots of the page, except with the chromium CLI parser, which can't find");
+        println!("them in the screenshots, so the pages it fetches are not compared visually while they have ignored regions.");
+        println!("Insert - to clear the regions. Press ENTER to keep the current ({})",
+                 tracked_page.ignored_selectors().join(" | "));
+
+        let mut selectors = Vec::new();
+
+        loop {
+            line.clear();
+
+            if let Err(e) = stdin.read_line(&mut line) {
+                println!("Failed to read the selector. {:?}", e);
+                return;
+            }
+
+            line.pop();
+
+            if line.is_empty() {
+                break;
+            }
+
+            if line == "-" {
+                tracked_page.set_ignored_selectors(Vec::new());
+                continue;
+            }
+
+            match Selector::parse(line.as_str()) {
+                Ok(_) => { selectors.push(line.clone()); }
+                Err(e) => { println!("Ignoring the selector {}. {:?}", line, e); }
+            }
+        }
+
+        if !selectors.is_empty() {
+            tracked_page.set_ignored_selectors(selectors);
+        }
+
+        if let TrackedPageType::Dynamic(_) = tracked_page.tracked_page_type() {
+            println!("The volatility mask holds the regions that changed while the page was analysed, they are removed");
+            println!("and the rest of the page is compared strictly. It is learned again every time the page is analysed,");
+            println!("the selectors you pin are kept in it when it is learned again.");
+            println!("Current mask:");
+
+            for selector in tracked_page.volatility_mask() {
+                if tracked_page.pinned_volatility_mask().contains(selector) {
+                    println!("{} (Pinned)", selector);
+                } else {
+                    println!("{}", selector);
+                }
+            }
+
+            println!("Insert the selectors to pin to the mask, one per line, followed by an empty line.");
+            println!("Insert - to clear the pinned selectors. Press ENTER to keep the current ones");
+
+            let mut mask = Vec::new();
+
+            loop {
+                line.clear();
+
+                if let Err(e) = stdin.read_line(&mut line) {
+                    println!("Failed to read the selector. {:?}", e);
+                    return;
+                }
+
+                line.pop();
+
+                if line.is_empty() {
+                    break;
+                }
+
+                if line == "-" {
+                    pin_volatility_mask(&mut tracked_page, Vec::new());
+                    continue;
+                }
+
+                match Selector::parse(line.as_str()) {
+                    Ok(_) => { mask.push(line.clone()); }
+                    Err(e) => { println!("Ignoring the selector {}. {:?}", line, e); }
+                }
+            }
+
+            if !mask.is_empty() {
+                pin_volatility_mask(&mut tracked_page, mask);
+            }
+
+            println!("Insert the selectors to remove from the mask, one per line, followed by an empty line.");
+            println!("The removed selectors are not learned again, so the regions they point to are compared.");
+            println!("Insert - to let the removed selectors be learned again. Press ENTER to keep the current ({})",
+                     tracked_page.excluded_volatility_mask().join(" | "));
+
+            let mut excluded = Vec::new();
+
+            loop {
+                line.clear();
+
+                if let Err(e) = stdin.read_line(&mut line) {
+                    println!("Failed to read the selector. {:?}", e);
+                    return;
+                }
+
+                line.pop();
+
+                if line.is_empty() {
+                    break;
+                }
+
+                if line == "-" {
+                    tracked_page.set_excluded_volatility_mask(Vec::new());
+                    continue;
+                }
+
+                excluded.push(line.clone());
+            }
+
+            if !excluded.is_empty() {
+                exclude_from_volatility_mask(&mut tracked_page, excluded);
+            }
+        }
+
+        line.clear();
+
         println!("How regularly do you wish your page to be re indexed (Choose this depending on the amount of cumulative changes you think your page will have over that period of time)");
         println!("Please insert time in minutes");
         println!("Press ENTER for DEFAULT ({} minutes)",
@@ -285,14 +746,65 @@ impl<T, V, K> PageManager<T, V, K>
         match pages_res {
             Ok(pages) => {
                 for page in &pages {
-                    println!("Page with ID {} tracking url {}", page.page_id(), page.page_url());
+                    println!("Page with ID {} tracking url {} fetched with {} parser", page.page_id(),
+                             page.page_url(), parser_type_to_str(page.parser_type()));
 
                     match page.tracked_page_type() {
                         TrackedPageType::Static => {
                             println!("Static page.");
                         }
-                        TrackedPageType::Dynamic(diff_tolerance) => {
-                            println!("Dynamic page with diff threshold of {}", diff_tolerance);
+                        TrackedPageType::Dynamic(model) => {
+                            println!("Dynamic page analysed with {} samples every {}s, with a{} baseline of {}",
+                                     page.sample_count(), page.sample_interval() / 1000,
+                                     if page.adaptive_baseline() { "n adaptive" } else { "" }, model);
+                        }
+                    }
+
+                    if let Some(threshold) = page.screenshot_threshold() {
+                        println!("Screenshots are compared with a threshold of {}%", threshold);
+                    }
+
+                    if !page.allowed_domains().is_empty() {
+                        println!("Allowed third party domains: {}", page.allowed_domains().join(", "));
+                    }
+
+                    for rule in page.normalisation_rules() {
+                        println!("Normalisation rule: {}", rule);
+                    }
+
+                    if !page.ignored_selectors().is_empty() {
+                        println!("Ignored regions: {}", page.ignored_selectors().join(" | "));
+                    }
+
+                    if !page.volatility_mask().is_empty() {
+                        println!("Volatile regions (masked): {}", page.volatility_mask().join(" | "));
+                    }
+
+                    if !page.excluded_volatility_mask().is_empty() {
+                        println!("Removed from the mask: {}", page.excluded_volatility_mask().join(" | "));
+                    }
+
+                    if let Some(approval) = self.tracked_page_db().list_approvals_for_page(page).ok()
+                        .and_then(|approvals| approvals.into_iter().next()) {
+                        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+                        println!("Last change approved by {} {} minutes ago (Incident {})", approval.approver(),
+                                 now.saturating_sub(approval.approval_time()) / 60000, approval.incident_id());
+                    }
+
+                    if let Ok(accepted_doms) = self.tracked_page_db().read_accepted_doms_for_page(page) {
+                        if !accepted_doms.is_empty() {
+                            println!("Accepted versions: {}", accepted_doms.iter()
+                                .map(|dom| dom.dom_id().to_string()).collect::<Vec<String>>().join(", "));
+                        }
+                    }
+
+                    if let Ok(verdict) = self.tracked_page_db().read_latest_verdict_for_page(page) {
+                        println!("Latest check: {:?} with a score of {:.2} ({} policy)", verdict.verdict(),
+                                 verdict.score(), verdict.policy());
+
+                        for comparator_result in verdict.defaced_results() {
+                            println!("  {}: {}", comparator_result.comparator(), comparator_result.result().explanation());
                         }
                     }
                 }
@@ -419,9 +931,23 @@ impl<T, V, K> PageManager<T, V, K>
         //Allow the scheduler to take over a different task
         //while we are reading the dom from the parser, which might take a while
         //As it has to fetch the result
-        let dom_result = self.read_current_page_for(&page);
+        let fetch_start = Instant::now();
+
+        let dom_result = self.fetch_current_page_for(&page);
+
+        let fetch_duration = fetch_start.elapsed().as_millis();
 
-        let current_dom = dom_result.expect("Failed to read current dom.");
+        let (fetched_dom, http_status, current_screenshot) = match dom_result {
+            Ok(fetched) => { fetched }
+            Err(e) => {
+                error!("Failed to read the current dom of page {} with ID {}. {}", page.page_url(), page.page_id(), e);
+
+                //Failed fetches are kept in the log as well, as they count against the uptime of the page
+                self.log_check(&page, fetch_duration, None, None);
+
+                return;
+            }
+        };
 
         let mut latest_dom = &doms[0];
 
@@ -431,14 +957,55 @@ impl<T, V, K> PageManager<T, V, K>
             }
         }
 
-        if !self.verify_page(&page, latest_dom, &current_dom) {
+        let current_dom = self.normaliser.normalise(&page, fetched_dom.as_str());
+
+        let mut reference_dom = latest_dom;
+
+        let mut result = self.check_against(&page, latest_dom, &current_dom, current_screenshot.as_ref());
+
+        //Pages that alternate between a few legitimate versions (A/B tests, rotating banners...)
+        //Pass the check when they match any of their accepted doms
+        if result.is_defaced() {
+            for accepted_dom in doms.iter().filter(|dom| dom.accepted() && dom.dom_id() != latest_dom.dom_id()) {
+                let accepted_result = self.check_against(&page, accepted_dom, &current_dom,
+                                                         current_screenshot.as_ref());
+
+                if !accepted_result.is_defaced() {
+                    debug!("Page {} with ID {} matches its accepted dom {}", page.page_url(), page.page_id(),
+                        accepted_dom.dom_id());
+
+                    reference_dom = accepted_dom;
+                    result = accepted_result;
+
+                    break;
+                }
+            }
+        }
+
+        debug!("Check of page {} with ID {} resulted in {:?} with a score of {} ({} policy)", page.page_url(),
+            page.page_id(), result.verdict(), result.score(), result.policy());
+
+        if let Err(e) = self.tracked_page_db().insert_verdict_for_page(&page, &result) {
+            error!("Failed to store the verdict for page {} with ID {}. {}", page.page_url(), page.page_id(), e);
+        }
+
+        self.log_check(&page, fetch_duration, http_status, Some(&result));
+
+        if result.is_defaced() {
+            //The first defaced check of a breach starts the incident, the following ones are part of it
+            let incident = self.incident_for(&page, &result, &fetched_dom, current_screenshot.as_ref());
+
             let mut notify = false;
 
             if page.defacement_count() + 1 >= page.defacement_threshold() && !page.notified_of_current_breach() {
                 notify = true;
             }
 
-            match self.tracked_page_db().increment_defacement_count(&mut page, notify) {
+            //The breach only counts as notified once its notifications are safely in the outbox,
+            //Otherwise they are queued again by the next check
+            let queued = notify && self.queue_defacement(&page, incident.as_ref(), latest_dom, &result);
+
+            match self.tracked_page_db().increment_defacement_count(&mut page, queued) {
                 Ok(_) => {}
                 Err(error) => {
                     error!("Failed to increment defacement count of {} because {}", page.page_id(), error);
@@ -447,164 +1014,1060 @@ impl<T, V, K> PageManager<T, V, K>
 
             debug!("Page now has {} defacements out of {} possible ones", page.defacement_count(), page.defacement_threshold());
 
-            if !notify {
-                return;
+            if queued {
+                //Deliver right away instead of waiting for the next run of the worker
+                self.deliver_outbox();
             }
+        } else {
+            //Checks during a suspected breach must never teach the baseline, so this is done before the reset
+            let stored_dom = self.normaliser.normalise(&page, reference_dom.dom());
 
-            let owning_user = self.user_db()
-                .get_user_info_for_id(page.owning_user_id());
+            self.adapt_baseline(&mut page, &result, &stored_dom, &current_dom);
 
-            match owning_user {
-                Ok(user) => {
-                    let contacts_res = self.user_db().list_contacts_for(&user);
+            if let Some(incident) = self.resolve_open_incident(&page, IncidentResolution::Recovered) {
+                self.notify_recovery(&page, &incident);
+            }
+
+            match self.tracked_page_db().reset_defacement_count(&mut page) {
+                Ok(_) => {}
+                Err(error) => {
+                    error!("Failed to reset defacement count {}", error);
+                }
+            }
+        }
+    }
+
+    ///Append the check to the check log of the page and remove the checks that are past the retention period
+    fn log_check(&self, page: &TrackedPage, fetch_duration: u128, http_status: Option<u16>,
+                 result: Option<&AggregatedResult>) {
+        if let Err(e) = self.tracked_page_db().insert_check_record_for_page(page, fetch_duration, http_status, result) {
+            error!("Failed to log the check of page {} with ID {}. {}", page.page_url(), page.page_id(), e);
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        match self.tracked_page_db().delete_check_records_older_than(page, now.saturating_sub(self.check_log_retention.as_millis())) {
+            Ok(deleted) if deleted > 0 => {
+                trace!("Removed {} expired checks from the log of page {} with ID {}", deleted, page.page_url(), page.page_id());
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to remove the expired checks of page {} with ID {}. {}", page.page_url(), page.page_id(), e);
+            }
+        }
+    }
+
+    ///The open incident of the page, a new one is started when there is none
+    fn incident_for(&self, page: &TrackedPage, result: &AggregatedResult, current_dom: &String,
+                    current_screenshot: Option<&Vec<u8>>) -> Option<Incident> {
+        let incident = match self.tracked_page_db().read_open_incident_for_page(page) {
+            Ok(Some(incident)) => { Ok(incident) }
+            Ok(None) => { self.tracked_page_db().open_incident_for_page(page, result, current_dom, current_screenshot) }
+            Err(e) => { Err(e) }
+        };
+
+        match incident {
+            Ok(incident) => { Some(incident) }
+            Err(e) => {
+                error!("Failed to record the incident of page {} with ID {}. {}", page.page_url(), page.page_id(), e);
+
+                None
+            }
+        }
+    }
+
+    ///Queue the defacement notification for every contact of the owner of the page
+    ///Returns whether the breach was handled, which is false when the notifications could not be queued
+    ///The notifications only refer to the stored dom, the defaced dom is the snapshot kept by the incident
+    fn queue_defacement(&self, page: &TrackedPage, incident: Option<&Incident>, stored_dom: &StoredDom<String>,
+                        result: &AggregatedResult) -> bool {
+        let incident = match incident {
+            Some(incident) => { incident }
+            None => {
+                error!("DETECTED DEFACEMENT IN PAGE {} WITH ID {} BUT ITS INCIDENT COULD NOT BE RECORDED",
+                    page.page_url(), page.page_id());
+                return false;
+            }
+        };
+
+        let user = match self.user_db().get_user_info_for_id(page.owning_user_id()) {
+            Ok(user) => { user }
+            Err(e) => {
+                error!("DETECTED DEFACEMENT IN PAGE {} BUT COULD NOT FIND \
+                                    USER INFO FOR OWNER {}, {}", page.page_url(),
+                       page.owning_user_id(), e);
+                return false;
+            }
+        };
+
+        let contacts = match self.user_db().list_contacts_for(&user) {
+            Ok(contacts) => { contacts }
+            Err(e) => {
+                error!("Failed to load contacts for user {}, {}", page.owning_user_id(), e);
+                return false;
+            }
+        };
+
+        if contacts.is_empty() {
+            warn!("Found defacement in tracked page {} with id {} \
+                                but owner {} with id {} has not registered contacts.",
+                  page.page_url(), page.page_id(), user.user(), user.user_id());
+
+            return true;
+        }
+
+        self.queue_notifications(page, &user, &contacts, |contact, channel| {
+            OutboxEntry::new(0, NotificationKind::Defacement, page.page_id(), user.user_id(),
+                             Some(incident.incident_id()), contact.comm_id(), String::from(channel))
+                .with_defacement(stored_dom.dom_id(), result.clone())
+        })
+    }
+
+    ///Tell every contact that was alerted of the incident that the page is back to normal
+    fn notify_recovery(&self, page: &TrackedPage, incident: &Incident) {
+        if incident.notified_contacts().is_empty() {
+            return;
+        }
+
+        let user = match self.user_db().get_user_info_for_id(incident.user_id()) {
+            Ok(user) => { user }
+            Err(e) => {
+                error!("Page {} with ID {} recovered but could not find the user {} that was alerted. {}",
+                    page.page_url(), page.page_id(), incident.user_id(), e);
+                return;
+            }
+        };
+
+        let contacts = match self.user_db().list_contacts_for(&user) {
+            Ok(contacts) => { contacts }
+            Err(e) => {
+                error!("Failed to load contacts for user {}, {}", user.user_id(), e);
+                return;
+            }
+        };
+
+        let alerted_contacts: Vec<UserCommunication> = contacts.into_iter()
+            .filter(|contact| incident.notified_contacts().contains(&contact.comm_id()))
+            .collect();
+
+        let queued = self.queue_notifications(page, &user, &alerted_contacts, |contact, channel| {
+            OutboxEntry::new(0, NotificationKind::Recovery, page.page_id(), user.user_id(),
+                             Some(incident.incident_id()), contact.comm_id(), String::from(channel))
+        });
+
+        if queued {
+            self.deliver_outbox();
+        }
+    }
+
+    ///Write a notification to the outbox for every contact, through every communication method that matches it
+    ///Returns whether all of them were written
+    fn queue_notifications<F>(&self, page: &TrackedPage, user: &User, contacts: &[UserCommunication], entry_for: F) -> bool
+        where F: Fn(&UserCommunication, &str) -> OutboxEntry {
+        let mut queued = true;
+
+        for contact in contacts {
+            let channels = self.dispatcher.routes(contact);
+
+            if channels.is_empty() {
+                warn!("There is no communication method able to send to contact {} of user {} with ID {}",
+                    contact.comm_id(), user.user(), user.user_id());
+            }
+
+            for channel in channels {
+                let mut entry = entry_for(contact, channel);
+
+                match self.tracked_page_db().enqueue_notification(&mut entry) {
+                    Ok(_) => {
+                        debug!("Queued notification {} for user {} with ID {} through {} (contact {}) about page {} with id {}",
+                            entry.entry_id(), user.user(), user.user_id(), channel, contact.comm_id(), page.page_url(), page.page_id());
+                    }
+                    Err(e) => {
+                        error!("Failed to queue the notification for user {} with ID {} through {} (contact {}) about \
+                        tracked page {} with page ID {}. {}", user.user(), user.user_id(), channel, contact.comm_id(),
+                            page.page_url(), page.page_id(), e);
+
+                        queued = false;
+                    }
+                }
+            }
+        }
+
+        queued
+    }
+
+    ///Deliver the notifications of the outbox that are due. Failed deliveries are retried later
+    ///With an exponential backoff, until they run out of attempts and become dead letters
+    fn deliver_outbox(&self) {
+        //Another delivery is running, it will pick up the due notifications
+        let _delivering = match self.delivering_outbox.try_lock() {
+            Ok(delivering) => { delivering }
+            Err(_) => { return; }
+        };
+
+        let entries = match self.tracked_page_db().claim_due_notifications(self.outbox.batch_size(),
+                                                                            self.outbox.lease().as_millis()) {
+            Ok(entries) => { entries }
+            Err(e) => {
+                error!("Failed to read the outbox. {}", e);
+                return;
+            }
+        };
+
+        for mut entry in entries {
+            match self.send_outbox_entry(&entry) {
+                Ok(answer) => {
+                    debug!("Delivered {} notification {} through {} (contact {}). {}",
+                        notification_kind_to_str(&entry.kind()), entry.entry_id(), entry.channel(), entry.contact_id(), answer);
+
+                    entry.set_status(OutboxStatus::Delivered);
+                    entry.set_last_error(None);
+                }
+                Err(e) => {
+                    entry.set_attempts(entry.attempts() + 1);
+
+                    if self.outbox.is_exhausted(entry.attempts()) {
+                        error!("Gave up on {} notification {} about page {} through {} (contact {}) after {} attempts. {}",
+                            notification_kind_to_str(&entry.kind()), entry.entry_id(), entry.page_id(), entry.channel(),
+                            entry.contact_id(), entry.attempts(), e);
+
+                        entry.set_status(OutboxStatus::DeadLetter);
+                    } else {
+                        let backoff = self.outbox.backoff_for(entry.attempts());
+
+                        warn!("Failed to deliver {} notification {} about page {} through {} (contact {}), retrying in {} seconds. {}",
+                            notification_kind_to_str(&entry.kind()), entry.entry_id(), entry.page_id(), entry.channel(),
+                            entry.contact_id(), backoff.as_secs(), e);
+
+                        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+                        entry.set_next_attempt(now + backoff.as_millis());
+                    }
+
+                    entry.set_last_error(Some(e));
+                }
+            }
+
+            if let Err(e) = self.tracked_page_db().update_outbox_entry(&entry) {
+                error!("Failed to store the delivery of notification {}. {}", entry.entry_id(), e);
+            }
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        match self.tracked_page_db().delete_outbox_entries_older_than(OutboxStatus::Delivered,
+                                                                      now.saturating_sub(self.outbox.delivered_retention().as_millis())) {
+            Ok(deleted) if deleted > 0 => {
+                trace!("Removed {} delivered notifications from the outbox", deleted);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to remove the delivered notifications from the outbox. {}", e);
+            }
+        }
+    }
+
+    ///Build the notification of the entry again and send it through its channel
+    fn send_outbox_entry(&self, entry: &OutboxEntry) -> Result<String, String> {
+        let page = self.tracked_page_db().get_information_for_tracked_page(entry.page_id())?;
+
+        let user = self.user_db().get_user_info_for_id(entry.user_id())?;
+
+        let contact = self.user_db().get_contact_for_id(entry.contact_id())?;
+
+        let mut incident = match entry.incident_id() {
+            Some(incident_id) => { Some(self.tracked_page_db().read_incident_for_id(incident_id)?) }
+            None => { None }
+        };
+
+        match entry.kind() {
+            NotificationKind::Defacement => {
+                let (stored_dom_id, result) = match (entry.stored_dom_id(), entry.result()) {
+                    (Some(stored_dom_id), Some(result)) => { (stored_dom_id, result) }
+                    _ => { return Err(String::from("The notification is missing the stored dom or the verdict of the defacement")); }
+                };
+
+                //The defaced dom is shared by all the notifications of the incident
+                let offending = match &incident {
+                    Some(incident) => { incident.clone() }
+                    None => { return Err(String::from("The incident of the defacement no longer exists")); }
+                };
+
+                //The doms are stored as they were fetched, the report shows them with the current rules of the page
+                let mut stored_dom = self.tracked_page_db().read_dom_for_id(stored_dom_id)?;
+
+                stored_dom.set_dom(self.normaliser.normalise(&page, stored_dom.dom()));
+
+                let current_dom = self.normaliser.normalise(&page, offending.offending_dom());
+
+                //The notification was queued when the defacement was detected
+                let notification = Notification::Defacement(DefacementReport::new(&page, &stored_dom, &current_dom,
+                                                                                  offending.offending_screenshot(), result)
+                    .with_detected_time(entry.created_time()));
+
+                let answer = self.dispatcher.deliver(entry.channel(), &user, &contact, &notification).result().clone()?;
+
+                if let Some(incident) = &mut incident {
+                    if let Err(e) = self.tracked_page_db().add_notified_contact_to_incident(incident, &contact) {
+                        error!("Failed to record the notification of incident {}. {}", incident.incident_id(), e);
+                    }
+
+                    //The page recovered before the alert was delivered, so the contact is told right after it
+                    if incident.resolution() == Some(IncidentResolution::Recovered) {
+                        let mut recovery = OutboxEntry::new(0, NotificationKind::Recovery, page.page_id(), user.user_id(),
+                                                            Some(incident.incident_id()), contact.comm_id(),
+                                                            String::from(entry.channel()));
+
+                        if let Err(e) = self.tracked_page_db().enqueue_notification(&mut recovery) {
+                            error!("Failed to queue the recovery of incident {}. {}", incident.incident_id(), e);
+                        }
+                    }
+                }
+
+                Ok(answer)
+            }
+            NotificationKind::Recovery => {
+                let incident = match incident {
+                    Some(incident) => { incident }
+                    None => { return Err(String::from("The incident of the recovery no longer exists")); }
+                };
+
+                let notification = Notification::Recovery(RecoveryReport::new(&page, &incident));
+
+                self.dispatcher.deliver(entry.channel(), &user, &contact, &notification).result().clone()
+            }
+        }
+    }
+
+    ///Resolve the open incident of the page, if there is one. Returns the resolved incident
+    fn resolve_open_incident(&self, page: &TrackedPage, resolution: IncidentResolution) -> Option<Incident> {
+        let mut incident = match self.tracked_page_db().read_open_incident_for_page(page) {
+            Ok(incident) => { incident? }
+            Err(e) => {
+                error!("Failed to read the open incident of page {} with ID {}. {}", page.page_url(), page.page_id(), e);
+
+                return None;
+            }
+        };
+
+        match self.tracked_page_db().resolve_incident(&mut incident, resolution) {
+            Ok(_) => {
+                info!("Incident {} of page {} with ID {} was resolved as {}", incident.incident_id(), page.page_url(),
+                    page.page_id(), incident_resolution_to_str(&resolution));
+
+                Some(incident)
+            }
+            Err(e) => {
+                error!("Failed to resolve incident {} of page {} with ID {}. {}", incident.incident_id(),
+                    page.page_url(), page.page_id(), e);
+
+                None
+            }
+        }
+    }
+
+    ///Feed a check into the baseline of an adaptive dynamic page, so it follows pages that drift slowly.
+    ///Only checks where no comparator considered the page defaced and that are not part of a
+    ///Suspected breach are used
+    fn adapt_baseline(&self, page: &mut TrackedPage, result: &AggregatedResult, stored_dom: &String, current_dom: &String) {
+        if !page.adaptive_baseline() || page.defacement_count() > 0 || !result.defaced_results().is_empty() {
+            return;
+        }
+
+        let mut model = match page.tracked_page_type() {
+            //Pages that were not analysed yet have nothing to adapt
+            Dynamic(model) if model.sample_count() > 0 => { model.clone() }
+            _ => { return; }
+        };
+
+        model.update(compare_dom_with_diff(stored_dom, current_dom), ADAPTIVE_BASELINE_WEIGHT);
+
+        trace!("Adapted the baseline of page {} with ID {} to {}", page.page_url(), page.page_id(), model);
+
+        page.set_tracked_page_type(Dynamic(model));
+
+        //The thresholds the comparators learned from the samples follow the page as well
+        for comparator in &self.comparators {
+            if let Some(data) = comparator.adapt_to_check(page, stored_dom, current_dom, ADAPTIVE_BASELINE_WEIGHT) {
+                page.set_comparator_data(comparator.name(), data);
+            }
+        }
+
+        if let Err(e) = self.tracked_page_db().update_baseline_for_page(page) {
+            error!("Failed to store the baseline of page {} with ID {}. {}", page.page_url(), page.page_id(), e);
+        }
+    }
+
+    ///Compare the current version of the page against one of its stored doms with every comparator
+    ///And aggregate their results
+    fn check_against(&self, page: &TrackedPage, reference_dom: &StoredDom<String>, current_dom: &String,
+                     current_screenshot: Option<&Vec<u8>>) -> AggregatedResult {
+        //The doms are stored as they were fetched and only normalised here, so they follow the current rules and mask
+        //Of the page. Normalising twice would mask the regions again, and the mask selectors are positional
+        let stored_dom = self.normaliser.normalise(page, reference_dom.dom());
+
+        let mut breakdown = self.run_comparators(&self.comparators, page, &stored_dom, current_dom);
+
+        //Some defacements (CSS changes, swapped images) don't show up in the dom at all
+        if let (Some(stored_screenshot), Some(screenshot)) = (reference_dom.screenshot(), current_screenshot) {
+            breakdown.extend(self.run_comparators(&self.screenshot_comparators, page, stored_screenshot, screenshot));
+        }
+
+        self.aggregation.aggregate(breakdown)
+    }
+
+    ///Runs every one of the given comparators, so the result of all of them can be aggregated
+    fn run_comparators<D>(&self, comparators: &[Box<dyn Comparator<D>>], page: &TrackedPage,
+                          stored: &D, current: &D) -> Vec<ComparatorResult> {
+        let mut results = Vec::with_capacity(comparators.len());
+
+        for comparator in comparators {
+            let result = comparator.compare_between(page, stored, current);
+
+            match result.verdict() {
+                Verdict::NotDefaced => {
+                    trace!("Not defaced, page {}, comparator {}", page.page_url(), comparator.name());
+                }
+                Verdict::MaybeDefaced => {
+                    trace!("Inconclusive result for page {} with ID {},\
+                     could not determine if page was defaced or not with comparator {}", page.page_url(), page.page_id(),
+                             comparator.name())
+                }
+                Verdict::Defaced => {
+                    trace!("Defaced, page {}, comparator {} with confidence {}. {}", page.page_url(), comparator.name(),
+                        result.confidence(), result.explanation());
+                }
+            }
+
+            results.push(ComparatorResult::new(comparator.name(), result));
+        }
+
+        results
+    }
+
+    ///Fetch the latest version doms, insert it into the DB
+    ///When the page is registered as a dynamic page, also performs
+    ///An analysis for 5 minutes, taking samples every 30 seconds and does an average of the diff between
+    ///them so we can calculate a threshold that will trigger a defacement alarm
+    async fn analyse_page(self: Arc<Self>, mut page: TrackedPage) {
+        debug!("Analysing page {} with ID {}", page.page_url(), page.page_id());
+
+        //We insert this scope here as the compiler is still not capable of detecting a drop(),
+        //So when we do the await further up ahead, which gives the tokio runtime permission
+        //To put this task to sleep, the mutex guard isn't part of the data that needs to be
+        //Stored (Mutex guards are not Send)
+        {
+            let mut currently_indexing = self.currently_indexing.lock().unwrap();
+
+            if currently_indexing.contains(&page.page_id()) {
+                return;
+            }
+
+            currently_indexing.insert(page.page_id());
+        }
+
+        match page.tracked_page_type() {
+            TrackedPageType::Static => {
+                let dom_res = self.read_current_page_for(&page);
+
+                match dom_res {
+                    Ok((dom, screenshot)) => {
+                        let mut stored_dom = self.tracked_page_db().insert_dom_for_page(&page, dom).unwrap();
+                        debug!("Inserted DOM for page {} with ID {}", page.page_url(), page.page_id());
+
+                        self.store_screenshot_for(&page, &mut stored_dom, screenshot);
+                    }
+                    Err(e) => {
+                        error!("FAILED TO ANALYSE PAGE {}, PLEASE FIX WHAT IS WRONG. {}", page.page_url(), e);
+                    }
+                }
+            }
+            Dynamic(_) => {
+                let result = analyse_dynamic_page(self.parser_for(&page), &self.normaliser, &page).await;
+
+                match result {
+                    Ok((model, volatility_mask, samples)) => {
+                        page.set_tracked_page_type(Dynamic(model));
+                        page.set_volatility_mask(volatility_mask);
+
+                        self.learn_from_samples(&mut page, &samples);
+                    }
+                    Err(e) => {
+                        error!("FAILED TO ANALYSE DYNAMIC PAGE {}, PLEASE FIX WHAT IS WRONG. {}", page.page_url(), e);
+                    }
+                }
+
+                let dom_res = self.read_current_page_for(&page);
+
+                match dom_res {
+                    Ok((dom, screenshot)) => {
+                        let mut stored_dom = self.tracked_page_db().insert_dom_for_page(&page, dom).unwrap();
+                        debug!("Inserted DOM for page {} with ID {}", page.page_url(), page.page_id());
+
+                        self.store_screenshot_for(&page, &mut stored_dom, screenshot);
+                    }
+                    Err(e) => {
+                        error!("FAILED TO ANALYSE PAGE {}, PLEASE FIX WHAT IS WRONG. {}", page.page_url(), e);
+                    }
+                }
+            }
+        }
+
+        self.tracked_page_db().update_tracking_type_for_page(&page).unwrap();
+
+        {
+            let mut currently_indexing = self.currently_indexing.lock().unwrap();
+
+            currently_indexing.remove(&page.page_id());
+        }
+    }
+
+    ///Let every comparator learn what is normal for the page from the samples taken during the analysis
+    fn learn_from_samples(&self, page: &mut TrackedPage, samples: &[String]) {
+        for comparator in &self.comparators {
+            if let Some(data) = comparator.learn_from_samples(page, samples) {
+                debug!("Comparator {} learned {} for page {} with ID {}", comparator.name(), data,
+                    page.page_url(), page.page_id());
+
+                page.set_comparator_data(comparator.name(), data);
+            }
+        }
+    }
+
+    ///Fetch the current dom of the page, as it is stored, and its screenshot if the page has visual comparison enabled
+    fn read_current_page_for(&self, page: &TrackedPage) -> Result<(String, Option<Vec<u8>>), String> {
+        self.fetch_current_page_for(page).map(|(dom, _, screenshot)| (dom, screenshot))
+    }
+
+    ///Read the current dom of the page along with the HTTP status of the response, when the parser sees it.
+    ///Pages with visual comparison enabled are captured in the same navigation, so the screenshot matches the dom.
+    ///The dom is not normalised, that is done when it is compared
+    fn fetch_current_page_for(&self, page: &TrackedPage) -> Result<FetchedPage<String>, String> {
+        let parser = self.parser_for(page);
+
+        match page.screenshot_threshold() {
+            Some(_) => { parser.fetch_page_with_screenshot(page) }
+            None => { parser.fetch_page(page).map(|(dom, status)| (dom, status, None)) }
+        }
+    }
+
+    ///Store the current screenshot of the page alongside the dom, so it can be used as the visual baseline
+    fn store_screenshot_for(&self, page: &TrackedPage, stored_dom: &mut StoredDom<String>, screenshot: Option<Vec<u8>>) {
+        if let Some(screenshot) = screenshot {
+            if let Err(e) = self.tracked_page_db().update_screenshot_for_dom(stored_dom, screenshot) {
+                error!("Failed to store screenshot of page {} with ID {}. {}", page.page_url(), page.page_id(), e);
+            }
+        }
+    }
+
+    ///Get the parser that should be used to fetch the given page.
+    ///If there is no parser registered for the page's parser type, we fall back to the first one
+    fn parser_for(&self, page: &TrackedPage) -> &dyn Parser<String> {
+        for parser in &self.parsers {
+            if parser.parser_type() == *page.parser_type() {
+                return parser.as_ref();
+            }
+        }
+
+        warn!("There is no {} parser registered for page {} with ID {}, using the default parser.",
+            parser_type_to_str(page.parser_type()), page.page_url(), page.page_id());
+
+        self.parsers[0].as_ref()
+    }
+
+    ///
+    /// Fetch the page as it is now and add it to the accepted versions of the page.
+    /// The version the page was being compared against is also accepted, as both are now legitimate
+    pub fn accept_current_dom(&self, page: &TrackedPage) -> Result<StoredDom<String>, String> {
+        if let Ok(mut latest_dom) = self.tracked_page_db().read_latest_dom_for_page(page) {
+            if !latest_dom.accepted() {
+                self.tracked_page_db().set_dom_accepted(&mut latest_dom, true)?;
+            }
+        }
+
+        let (dom, screenshot) = self.read_current_page_for(page)?;
+
+        let mut stored_dom = self.tracked_page_db().insert_dom_for_page(page, dom)?;
+
+        self.store_screenshot_for(page, &mut stored_dom, screenshot);
+
+        self.tracked_page_db().set_dom_accepted(&mut stored_dom, true)?;
+
+        Ok(stored_dom)
+    }
+
+    ///
+    /// Approve the change that started the incident as a legitimate one (A content update...).
+    /// The dom the incident flagged is stored as the new baseline of the page, so what is approved is what was
+    /// reviewed, even if the page changed again since. The defacement count of the page is reset, the incident
+    /// is resolved as accepted and the approval is recorded along with who approved it
+    pub fn accept_incident(&self, incident_id: u32, approver: &str) -> Result<Approval, String> {
+        let mut incident = self.tracked_page_db().read_incident_for_id(incident_id)?;
+
+        if !incident.is_open() {
+            return Err(format!("Incident {} was already resolved", incident_id));
+        }
+
+        let mut page = self.tracked_page_db().get_information_for_tracked_page(incident.page_id())?;
+
+        let mut stored_dom = self.tracked_page_db().insert_dom_for_page(&page, String::from(incident.offending_dom()))?;
+
+        self.store_screenshot_for(&page, &mut stored_dom, incident.offending_screenshot().cloned());
+
+        let approval = self.tracked_page_db().insert_approval_for_page(&page, &incident, &stored_dom, approver)?;
+
+        self.tracked_page_db().reset_defacement_count(&mut page)?;
+
+        self.tracked_page_db().resolve_incident(&mut incident, IncidentResolution::Accepted)?;
+
+        info!("The change of page {} with ID {} that started incident {} was approved by {}, after {} defacement checks",
+            page.page_url(), page.page_id(), incident_id, approver, approval.defacement_count());
+
+        Ok(approval)
+    }
+
+    fn approve_change(&self, stdin: &mut StdinLock, page: &TrackedPage) {
+        let incident = match self.tracked_page_db().read_open_incident_for_page(page) {
+            Ok(Some(incident)) => { incident }
+            Ok(None) => {
+                println!("The page has no open incident, so there is no change to approve");
+                return;
+            }
+            Err(e) => {
+                println!("Failed to read the open incident of the page. {}", e);
+                return;
+            }
+        };
+
+        println!("Incident {} of page {}, with {} defacement checks out of {}", incident.incident_id(),
+                 page.page_url(), page.defacement_count(), page.defacement_threshold());
+
+        for result in incident.triggering_results() {
+            println!("  - {}: {}", result.comparator(), result.result().explanation());
+        }
+
+        //Show what would become the baseline, against what the page is compared to now
+        if let Ok(stored_dom) = self.tracked_page_db().read_latest_dom_for_page(page) {
+            let diff = summarise_diff(self.normaliser.normalise(page, stored_dom.dom()).as_str(),
+                                      self.normaliser.normalise(page, incident.offending_dom()).as_str(), APPROVAL_DIFF_LINES);
+
+            println!("{:.2}% of the page changed, {} lines were added and {} removed", diff.changed_percent(),
+                     diff.added_count(), diff.removed_count());
+
+            for line in diff.removed() {
+                println!("- {}", line);
+            }
+
+            for line in diff.added() {
+                println!("+ {}", line);
+            }
+        }
+
+        println!("Insert the username of who is approving the change");
+
+        let mut line = String::new();
+
+        if let Err(e) = stdin.read_line(&mut line) {
+            println!("Failed to read the username. {:?}", e);
+            return;
+        }
+
+        line.pop();
+
+        let approver = match self.user_db().get_user_info_for(line.as_str()) {
+            Ok(user) => { user }
+            Err(e) => {
+                println!("There is no user {}. {}", line, e);
+                return;
+            }
+        };
+
+        println!("Store the version of the page flagged by the incident as its new baseline? (y/n)");
+
+        line.clear();
+
+        if let Err(e) = stdin.read_line(&mut line) {
+            println!("Failed to read your choice. {:?}", e);
+            return;
+        }
+
+        line.pop();
+
+        if !line.eq_ignore_ascii_case("y") {
+            println!("The change was not approved");
+            return;
+        }
+
+        match self.accept_incident(incident.incident_id(), approver.user()) {
+            Ok(approval) => {
+                println!("The change was approved, the version of the page flagged by the incident (ID {}) is now its baseline",
+                         approval.dom_id());
+            }
+            Err(e) => { println!("Failed to approve the change. {}", e); }
+        }
+    }
+
+    fn display_incidents(&self, stdin: &mut StdinLock) {
+        println!("List the incidents of:");
+        println!("1- A tracked page");
+        println!("2- A user");
+
+        let mut line = String::new();
+
+        if let Err(e) = stdin.read_line(&mut line) {
+            println!("Failed to read your choice. {:?}", e);
+            return;
+        }
+
+        line.pop();
+
+        let choice = line.clone();
+
+        println!("Which incidents should be listed? open, resolved, recovered, accepted or falsepositive.");
+        println!("Press ENTER to list all of them");
+
+        line.clear();
+
+        if let Err(e) = stdin.read_line(&mut line) {
+            println!("Failed to read the filter. {:?}", e);
+            return;
+        }
+
+        line.pop();
+
+        let mut filter = IncidentFilter::new();
+
+        match line.to_ascii_lowercase().as_str() {
+            "" => {}
+            "open" => { filter.set_open(Some(true)); }
+            "resolved" => { filter.set_open(Some(false)); }
+            "recovered" => { filter.set_resolution(Some(IncidentResolution::Recovered)); }
+            "accepted" => { filter.set_resolution(Some(IncidentResolution::Accepted)); }
+            "falsepositive" => { filter.set_resolution(Some(IncidentResolution::FalsePositive)); }
+            _ => {
+                println!("Unknown filter {}", line);
+                return;
+            }
+        }
 
-                    match contacts_res {
-                        Ok(contacts) => {
-                            if contacts.is_empty() {
-                                warn!("Found defacement in tracked page {} with id {} \
-                                but owner {} with id {} has not registered contacts.",
-                                page.page_url(), page.page_id(), user.user(), user.user_id());
+        let incidents = match choice.as_str() {
+            "1" => {
+                self.read_page_from_stdin(stdin)
+                    .and_then(|page| self.tracked_page_db().list_incidents_for_page(&page, &filter))
+            }
+            "2" => {
+                self.read_user_info(stdin)
+                    .and_then(|user| self.user_db().list_incidents_for_user(&user, &filter))
+            }
+            _ => {
+                println!("Could not find that option!");
+                return;
+            }
+        };
 
-                                return;
-                            }
+        let incidents = match incidents {
+            Ok(incidents) => { incidents }
+            Err(e) => {
+                println!("Failed to list the incidents. {}", e);
+                return;
+            }
+        };
 
-                            for contact in &contacts {
-                                for comm_method in &self.communications {
-                                    match (*comm_method).send_report_to(&user, contact, &page,
-                                                                        latest_dom, &current_dom) {
-                                        Ok(_) => {
-                                            debug!("Sent notification to user {} with ID {} about defacement on page {} with id {}",
-                                                     user.user(), user.user_id(), page.page_url(),
-                                                     page.page_id());
-                                        }
-                                        Err(e) => {
-                                            error!("Failed to contact user {} with ID {}\
-                                                             on communication method {:?} for \
-                                                             tracked page defacement {} with page ID {}. {}",
-                                                     user.user(), user.user_id(), contact, page.page_url(),
-                                                     page.page_id(), e);
-                                        }
-                                    };
-
-                                    break;
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            error!("Failed to load contacts for user {}, {}", page.owning_user_id(), e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("DETECTED DEFACEMENT IN PAGE {} BUT COULD NOT FIND \
-                                    USER INFO FOR OWNER {}, {}", page.page_url(),
-                             page.owning_user_id(), e);
-                }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        for incident in &incidents {
+            let status = match incident.resolution() {
+                Some(resolution) => { format!("resolved as {}", incident_resolution_to_str(&resolution)) }
+                None => { String::from("OPEN") }
+            };
+
+            println!("Incident {} of page {} started {} minutes ago, lasted {} minutes, {}. {} contacts were notified",
+                     incident.incident_id(), incident.page_id(), now.saturating_sub(incident.start_time()) / 60000,
+                     incident.end_time().unwrap_or(now).saturating_sub(incident.start_time()) / 60000, status,
+                     incident.notified_contacts().len());
+
+            for result in incident.triggering_results() {
+                println!("  - {}: {}", result.comparator(), result.result().explanation());
             }
-        } else {
-            match self.tracked_page_db().reset_defacement_count(&mut page) {
-                Ok(_) => {}
-                Err(error) => {
-                    error!("Failed to reset defacement count {}", error);
+        }
+
+        if incidents.is_empty() {
+            println!("There are no incidents.");
+            return;
+        }
+
+        println!("Insert the ID of an incident to mark it as a false positive, or press ENTER to go back");
+
+        line.clear();
+
+        if let Err(e) = stdin.read_line(&mut line) {
+            println!("Failed to read the incident ID. {:?}", e);
+            return;
+        }
+
+        line.pop();
+
+        if line.is_empty() {
+            return;
+        }
+
+        let mut incident = match line.parse::<u32>().ok()
+            .and_then(|incident_id| incidents.into_iter().find(|incident| incident.incident_id() == incident_id)) {
+            Some(incident) => { incident }
+            None => {
+                println!("There is no listed incident with ID {}", line);
+                return;
+            }
+        };
+
+        let was_open = incident.is_open();
+
+        if let Err(e) = self.tracked_page_db().resolve_incident(&mut incident, IncidentResolution::FalsePositive) {
+            println!("Failed to mark the incident as a false positive. {}", e);
+            return;
+        }
+
+        //The page was never defaced, so the breach it was counting towards is over
+        if was_open {
+            if let Ok(mut page) = self.tracked_page_db().get_information_for_tracked_page(incident.page_id()) {
+                if let Err(e) = self.tracked_page_db().reset_defacement_count(&mut page) {
+                    println!("Failed to reset the defacement count of the page. {}", e);
                 }
             }
         }
+
+        println!("Incident {} was marked as a false positive", incident.incident_id());
     }
 
-    ///Verifies if the page is as it's supposed to be.
-    ///Returns true if the page is good (not defaced)
-    ///Returns false if the page is not good (defaced)
-    fn verify_page(&self, page: &TrackedPage, stored_dom: &StoredDom<String>, current_dom: &String) -> bool {
-        for comparator in &self.comparators {
-            let result = comparator.compare_between(page, stored_dom.dom(),
-                                                    &current_dom);
+    fn display_check_log(&self, stdin: &mut StdinLock, page: &TrackedPage) {
+        println!("How many hours of checks should be shown? Press ENTER to show all of the logged checks ({} days are kept)",
+                 self.check_log_retention.as_secs() / (60 * 60 * 24));
 
-            match result {
-                CompareResult::NotDefaced => {
-                    trace!("Not defaced, page {}, comparator {}", page.page_url(),
-                    comparator.name());
-                    return true;
-                }
-                CompareResult::MaybeDefaced => {
-                    trace!("Inconclusive result for page {} with ID {},\
-                     could not determine if page was defaced or not with comparator {}", page.page_url(), page.page_id(),
-                             comparator.name())
-                }
-                CompareResult::Defaced => {
-                    trace!("Defaced, page {}, comparator {}", page.page_url(), comparator.name());
-                    return false;
+        let mut line = String::new();
+
+        if let Err(e) = stdin.read_line(&mut line) {
+            println!("Failed to read the amount of hours. {:?}", e);
+            return;
+        }
+
+        line.pop();
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        let since = if line.is_empty() {
+            0
+        } else {
+            match line.parse::<u64>() {
+                Ok(hours) => { now.saturating_sub(Duration::from_secs(hours * 60 * 60).as_millis()) }
+                Err(e) => {
+                    println!("Failed to parse the amount of hours. {}", e);
+                    return;
                 }
             }
+        };
+
+        let checks = match self.tracked_page_db().list_check_records_for_page(page, since) {
+            Ok(checks) => { checks }
+            Err(e) => {
+                println!("Failed to read the check log of the page. {}", e);
+                return;
+            }
+        };
+
+        if checks.is_empty() {
+            println!("There are no logged checks for the page in that period.");
+            return;
         }
 
-        true
-    }
+        let up_checks = checks.iter().filter(|check| check.is_up()).count();
 
-    ///Fetch the latest version doms, insert it into the DB
-    ///When the page is registered as a dynamic page, also performs
-    ///An analysis for 5 minutes, taking samples every 30 seconds and does an average of the diff between
-    ///them so we can calculate a threshold that will trigger a defacement alarm
-    async fn analyse_page(self: Arc<Self>, mut page: TrackedPage) {
-        debug!("Analysing page {} with ID {}", page.page_url(), page.page_id());
+        let mean_fetch_duration = checks.iter().map(CheckRecord::fetch_duration).sum::<u128>() / checks.len() as u128;
 
-        //We insert this scope here as the compiler is still not capable of detecting a drop(),
-        //So when we do the await further up ahead, which gives the tokio runtime permission
-        //To put this task to sleep, the mutex guard isn't part of the data that needs to be
-        //Stored (Mutex guards are not Send)
-        {
-            let mut currently_indexing = self.currently_indexing.lock().unwrap();
+        println!("{} checks, {:.2}% uptime, fetched in {} ms on average", checks.len(),
+                 up_checks as f64 * 100.0 / checks.len() as f64, mean_fetch_duration);
 
-            if currently_indexing.contains(&page.page_id()) {
+        let mut scores: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+
+        for check in &checks {
+            for (comparator, score) in check.scores() {
+                scores.entry(comparator.as_str()).or_default().push(*score);
+            }
+        }
+
+        for (comparator, scores) in &scores {
+            println!("  {} score: min {:.2}, mean {:.2}, max {:.2}", comparator,
+                     scores.iter().copied().fold(f64::INFINITY, f64::min),
+                     scores.iter().sum::<f64>() / scores.len() as f64,
+                     scores.iter().copied().fold(f64::NEG_INFINITY, f64::max));
+        }
+
+        println!("The last {} checks:", LISTED_CHECKS.min(checks.len()));
+
+        for check in checks.iter().rev().take(LISTED_CHECKS) {
+            let verdict = match check.verdict() {
+                Some(verdict) => { verdict_to_str(&verdict) }
+                None => { "FetchFailed" }
+            };
+
+            let status = check.http_status().map(|status| status.to_string()).unwrap_or_else(|| String::from("-"));
+
+            let scores: Vec<String> = check.scores().iter()
+                .map(|(comparator, score)| format!("{}={:.2}", comparator, score))
+                .collect();
+
+            println!("  {} minutes ago: {} (Status {}, fetched in {} ms) {}", now.saturating_sub(check.check_time()) / 60000,
+                     verdict, status, check.fetch_duration(), scores.join(" "));
+        }
+    }
+
+    fn manage_outbox(&self, stdin: &mut StdinLock) {
+        let (pending, dead_letters) = match (self.tracked_page_db().list_outbox_entries(OutboxStatus::Pending),
+                                             self.tracked_page_db().list_outbox_entries(OutboxStatus::DeadLetter)) {
+            (Ok(pending), Ok(dead_letters)) => { (pending, dead_letters) }
+            (Err(e), _) | (_, Err(e)) => {
+                println!("Failed to read the outbox. {}", e);
                 return;
             }
+        };
 
-            currently_indexing.insert(page.page_id());
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+
+        println!("{} notifications are waiting to be delivered:", pending.len());
+
+        for entry in &pending {
+            println!("  {}- {} of page {} to contact {} through {}, {} failed attempts, next attempt in {} seconds{}",
+                     entry.entry_id(), notification_kind_to_str(&entry.kind()), entry.page_id(), entry.contact_id(),
+                     entry.channel(), entry.attempts(), entry.next_attempt().saturating_sub(now) / 1000,
+                     entry.last_error().map(|error| format!(". Last error: {}", error)).unwrap_or_default());
         }
 
-        match page.tracked_page_type() {
-            TrackedPageType::Static => {
-                let dom_res = self.read_current_page_for(&page);
+        println!("{} notifications could not be delivered:", dead_letters.len());
 
-                match dom_res {
-                    Ok(dom) => {
-                        self.tracked_page_db().insert_dom_for_page(&page, dom).unwrap();
-                        debug!("Inserted DOM for page {} with ID {}", page.page_url(), page.page_id());
-                    }
-                    Err(e) => {
-                        error!("FAILED TO ANALYSE PAGE {}, PLEASE FIX WHAT IS WRONG. {}", page.page_url(), e);
-                    }
+        for entry in &dead_letters {
+            println!("  {}- {} of page {} to contact {} through {}, gave up after {} attempts. Last error: {}",
+                     entry.entry_id(), notification_kind_to_str(&entry.kind()), entry.page_id(), entry.contact_id(),
+                     entry.channel(), entry.attempts(), entry.last_error().unwrap_or_default());
+        }
+
+        if dead_letters.is_empty() {
+            return;
+        }
+
+        let line = match read_value(stdin, "Insert the ID of a notification that could not be delivered to retry it, \
+        A to retry all of them or press ENTER to go back") {
+            Some(line) => { line }
+            None => { return; }
+        };
+
+        let retried: Vec<OutboxEntry> = if line.eq_ignore_ascii_case("a") {
+            dead_letters
+        } else if line.is_empty() {
+            return;
+        } else {
+            match line.parse::<u32>() {
+                Ok(entry_id) => {
+                    dead_letters.into_iter().filter(|entry| entry.entry_id() == entry_id).collect()
+                }
+                Err(e) => {
+                    println!("Failed to parse the notification ID. {}", e);
+                    return;
                 }
             }
-            Dynamic(_) => {
-                let result = analyse_dynamic_page(self.parser(), &page).await;
+        };
 
-                page.set_tracked_page_type(Dynamic(result.unwrap()));
+        if retried.is_empty() {
+            println!("There is no notification with that ID that could not be delivered.");
+            return;
+        }
 
-                let dom_res = self.read_current_page_for(&page);
+        for mut entry in retried {
+            //Retried notifications start over with all of their attempts
+            entry.set_status(OutboxStatus::Pending);
+            entry.set_attempts(0);
+            entry.set_next_attempt(now);
 
-                match dom_res {
-                    Ok(dom) => {
-                        self.tracked_page_db().insert_dom_for_page(&page, dom).unwrap();
-                        debug!("Inserted DOM for page {} with ID {}", page.page_url(), page.page_id());
-                    }
-                    Err(e) => {
-                        error!("FAILED TO ANALYSE PAGE {}, PLEASE FIX WHAT IS WRONG. {}", page.page_url(), e);
-                    }
-                }
+            match self.tracked_page_db().update_outbox_entry(&entry) {
+                Ok(_) => { println!("Notification {} will be delivered again.", entry.entry_id()); }
+                Err(e) => { println!("Failed to retry notification {}. {}", entry.entry_id(), e); }
+            }
+        }
+    }
+
+    fn manage_accepted_doms(&self, stdin: &mut StdinLock, page: &TrackedPage) {
+        let doms = match self.tracked_page_db().read_doms_for_page(page) {
+            Ok(doms) => { doms }
+            Err(e) => {
+                println!("Failed to read the stored doms of the page. {}", e);
+                return;
             }
+        };
+
+        println!("Stored versions of page {} (The latest one is always compared against):", page.page_url());
+
+        for dom in &doms {
+            println!("{}- {} characters{}", dom.dom_id(), dom.dom().len(),
+                     if dom.accepted() { " (Accepted)" } else { "" });
         }
 
-        self.tracked_page_db().update_tracking_type_for_page(&page).unwrap();
+        println!("Insert A to accept the page as it is now, or the ID of a stored version to accept it or stop accepting it.");
+        println!("Press ENTER to go back");
 
-        {
-            let mut currently_indexing = self.currently_indexing.lock().unwrap();
+        let mut line = String::new();
 
-            currently_indexing.remove(&page.page_id());
+        if let Err(e) = stdin.read_line(&mut line) {
+            println!("Failed to read your choice. {:?}", e);
+            return;
+        }
+
+        line.pop();
+
+        if line.is_empty() {
+            return;
+        }
+
+        if line.eq_ignore_ascii_case("A") {
+            match self.accept_current_dom(page) {
+                Ok(dom) => { println!("The current version of the page was accepted with ID {}", dom.dom_id()); }
+                Err(e) => { println!("Failed to accept the current version of the page. {}", e); }
+            }
+
+            return;
+        }
+
+        let dom_id = match line.parse::<u32>() {
+            Ok(dom_id) => { dom_id }
+            Err(e) => {
+                println!("Failed to read the ID. {:?}, {}", e, line);
+                return;
+            }
+        };
+
+        match doms.into_iter().find(|dom| dom.dom_id() == dom_id) {
+            Some(mut dom) => {
+                let accepted = !dom.accepted();
+
+                match self.tracked_page_db().set_dom_accepted(&mut dom, accepted) {
+                    Ok(_) => {
+                        println!("The version with ID {} is {} accepted", dom_id, if accepted { "now" } else { "no longer" });
+                    }
+                    Err(e) => { println!("Failed to change the version. {}", e); }
+                }
+            }
+            None => { println!("There is no stored version with ID {} for this page", dom_id); }
         }
     }
 
-    fn read_current_page_for(&self, page: &TrackedPage) -> Result<String, String> {
-        self.parser().parse_page(page)
+    fn reload_comparators(&self) {
+        for comparator in &self.comparators {
+            match comparator.reload_configuration() {
+                Ok(_) => {
+                    println!("Reloaded comparator {}", comparator.name());
+                }
+                Err(e) => {
+                    println!("Failed to reload comparator {}. {}", comparator.name(), e);
+                }
+            }
+        }
     }
 
     fn insert_new_user(&self, stdin: &mut StdinLock) -> Result<User, String> {
@@ -643,6 +2106,8 @@ impl<T, V, K> PageManager<T, V, K>
             Ok(user) => {
                 println!("That username corresponds to the user ID {}.", user.user_id());
 
+                println!("The notifications are sent in {}.", describe_locale(&user));
+
                 let contacts_result = self.user_db().list_contacts_for(&user);
 
                 match contacts_result {
@@ -653,9 +2118,7 @@ impl<T, V, K> PageManager<T, V, K>
                         }
 
                         for contact in &contacts {
-                            println!("{} - {} - {}", contact.comm_id(),
-                                     match contact.communication() { CommData::Email(_) => { "Email" } },
-                                     match contact.communication() { CommData::Email(lettre_email) => { lettre_email } })
+                            println!("{} - {}", contact.comm_id(), describe_contact(contact.communication()))
                         }
                     }
                     Err(error) => {
@@ -723,23 +2186,46 @@ impl<T, V, K> PageManager<T, V, K>
         };
     }
 
-    fn insert_contact_for(&self, stdin: &mut StdinLock, user: &User) {
-        println!("Insert contact email.");
+    fn set_user_locale(&self, stdin: &mut StdinLock, user: &mut User) {
+        println!("The notifications of {} are sent in {}.", user.user(), describe_locale(user));
+        println!("Insert the new locale (en, pt, pt-BR...), the notifications are sent in the default locale \
+        when it has no templates. Leave it empty to go back to the default locale.");
 
         let mut line = String::new();
 
         match stdin.read_line(&mut line) {
             Ok(_) => {}
             Err(e) => {
-                println!("Failed to read email because {:?}.", e);
+                println!("Failed to read the locale because {:?}.", e);
+                return;
             }
         };
 
-        line.pop();
+        let locale = line.trim();
 
-        match self.user_db().insert_contact_for(user, CommData::Email(line.clone())) {
+        let locale = if locale.is_empty() { None } else { Some(locale) };
+
+        match self.user_db().update_locale_for_user(user, locale) {
+            Ok(_) => {
+                println!("The notifications of {} are now sent in {}.", user.user(), describe_locale(user));
+            }
+            Err(e) => {
+                println!("Failed to update the locale because {}", e);
+            }
+        }
+    }
+
+    fn insert_contact_for(&self, stdin: &mut StdinLock, user: &User) {
+        let comm = match read_contact_data(stdin) {
+            Some(comm) => { comm }
+            None => { return; }
+        };
+
+        let description = describe_contact(&comm);
+
+        match self.user_db().insert_contact_for(user, comm) {
             Ok(res) => {
-                println!("Inserted contact {} with ID {}", line, res.comm_id());
+                println!("Inserted contact {} with ID {}", description, res.comm_id());
             }
             Err(error) => {
                 println!("Failed to insert contact because {}", error);
@@ -797,7 +2283,158 @@ impl<T, V, K> PageManager<T, V, K>
     pub fn user_db(&self) -> &V {
         &self.user_db
     }
-    pub fn parser(&self) -> &K {
-        &self.parser
+    pub fn parsers(&self) -> &Vec<Box<dyn Parser<String>>> {
+        &self.parsers
+    }
+}
+
+fn read_value(stdin: &mut StdinLock, prompt: &str) -> Option<String> {
+    println!("{}", prompt);
+
+    let mut line = String::new();
+
+    if let Err(e) = stdin.read_line(&mut line) {
+        println!("Failed to read the value because {:?}.", e);
+        return None;
+    }
+
+    line.pop();
+
+    Some(line)
+}
+
+///Ask for the type of the contact and the values that type needs
+fn read_contact_data(stdin: &mut StdinLock) -> Option<CommData> {
+    let comm_type = read_value(stdin, "Insert the contact type (email, webhook, slack, mattermost, matrix or telegram). \
+    Press ENTER for email.")?;
+
+    let comm = match comm_type.to_ascii_lowercase().as_str() {
+        "" | "email" => {
+            CommData::Email(read_value(stdin, "Insert contact email.")?)
+        }
+        "webhook" => {
+            let url = read_value(stdin, "Insert the webhook URL.")?;
+
+            let secret = read_value(stdin, "Insert the secret the payloads are signed with, or press ENTER to not sign them.")?;
+
+            CommData::Webhook { url, secret: if secret.is_empty() { None } else { Some(secret) } }
+        }
+        "slack" => {
+            CommData::Slack(read_value(stdin, "Insert the URL of the Slack incoming webhook.")?)
+        }
+        "mattermost" => {
+            CommData::Mattermost(read_value(stdin, "Insert the URL of the Mattermost incoming webhook.")?)
+        }
+        "matrix" => {
+            CommData::Matrix {
+                homeserver: read_value(stdin, "Insert the URL of the Matrix homeserver (https://matrix.example.org).")?,
+                room_id: read_value(stdin, "Insert the ID of the room (!room:example.org).")?,
+                access_token: read_value(stdin, "Insert the access token of the account that sends the messages.")?,
+            }
+        }
+        "telegram" => {
+            CommData::Telegram {
+                bot_token: read_value(stdin, "Insert the token of the Telegram bot.")?,
+                chat_id: read_value(stdin, "Insert the ID of the chat the bot sends the messages to.")?,
+            }
+        }
+        _ => {
+            println!("Unknown contact type {}", comm_type);
+            return None;
+        }
+    };
+
+    Some(comm)
+}
+
+///Ask how the page should be fetched, the current parser type is kept when nothing is chosen
+fn read_parser_type(stdin: &mut StdinLock, current: &ParserType) -> Result<ParserType, String> {
+    println!("How should the page be fetched?");
+    println!("1- Browser (Renders the page, running JS)");
+    println!("2- Plain HTTP (Only for pages that don't need JS, a lot cheaper)");
+    println!("Press ENTER to keep the current ({})", parser_type_to_str(current));
+
+    let mut line = String::new();
+
+    if let Err(e) = stdin.read_line(&mut line) {
+        return Err(format!("Failed to read your choice. {:?}", e));
+    }
+
+    line.pop();
+
+    match line.as_str() {
+        "1" => { Ok(ParserType::Browser) }
+        "2" => { Ok(ParserType::Http) }
+        _ => { Ok(current.clone()) }
+    }
+}
+
+///Replace the selectors the user pinned to the mask of the page, the learned ones are kept
+fn pin_volatility_mask(page: &mut TrackedPage, pinned: Vec<String>) {
+    let mut mask: Vec<String> = page.volatility_mask().iter()
+        .filter(|selector| !page.pinned_volatility_mask().contains(selector))
+        .cloned()
+        .collect();
+
+    for selector in &pinned {
+        if !mask.contains(selector) {
+            mask.push(selector.clone());
+        }
+    }
+
+    //Pinning a selector that was removed from the mask puts it back
+    let excluded: Vec<String> = page.excluded_volatility_mask().iter()
+        .filter(|selector| !pinned.contains(selector))
+        .cloned()
+        .collect();
+
+    page.set_volatility_mask(mask);
+    page.set_pinned_volatility_mask(pinned);
+    page.set_excluded_volatility_mask(excluded);
+}
+
+///Remove the selectors from the mask of the page, pinned or learned, and keep them from being learned again
+fn exclude_from_volatility_mask(page: &mut TrackedPage, excluded: Vec<String>) {
+    let mask: Vec<String> = page.volatility_mask().iter()
+        .filter(|selector| !excluded.contains(selector))
+        .cloned()
+        .collect();
+
+    let pinned: Vec<String> = page.pinned_volatility_mask().iter()
+        .filter(|selector| !excluded.contains(selector))
+        .cloned()
+        .collect();
+
+    let mut all_excluded = page.excluded_volatility_mask().clone();
+
+    for selector in excluded {
+        if !all_excluded.contains(&selector) {
+            all_excluded.push(selector);
+        }
+    }
+
+    page.set_volatility_mask(mask);
+    page.set_pinned_volatility_mask(pinned);
+    page.set_excluded_volatility_mask(all_excluded);
+}
+
+fn describe_locale(user: &User) -> String {
+    match user.locale() {
+        Some(locale) => { format!("the locale {}", locale) }
+        None => { String::from("the default locale") }
+    }
+}
+
+///The type and destination of the contact, without its secrets
+fn describe_contact(comm: &CommData) -> String {
+    match comm {
+        CommData::Email(email) => { format!("Email - {}", email) }
+        CommData::Webhook { url, secret } => {
+            format!("Webhook - {}{}", url, if secret.is_some() { " (signed)" } else { "" })
+        }
+        CommData::Slack(webhook_url) => { format!("Slack - {}", webhook_url) }
+        CommData::Mattermost(webhook_url) => { format!("Mattermost - {}", webhook_url) }
+        CommData::Matrix { homeserver, room_id, .. } => { format!("Matrix - {} on {}", room_id, homeserver) }
+        CommData::Telegram { chat_id, .. } => { format!("Telegram - chat {}", chat_id) }
     }
-}
\ No newline at end of file
+}
@@ -1,8 +1,28 @@
 use std::fmt::{Debug, Display};
-use crate::databases::TrackedPage;
+use crate::databases::{ParserType, TrackedPage};
 
 pub mod chromium_parser;
+pub mod devtools_parser;
+pub mod http_parser;
+
+///The dom of a page, the HTTP status the server answered with and the screenshot of the page
+pub type FetchedPage<T> = (T, Option<u16>, Option<Vec<u8>>);
 
 pub trait Parser<T>: Send + Sync where T: Debug + Display + Send + Sync {
+    ///The kind of tracked pages this parser is able to fetch
+    fn parser_type(&self) -> ParserType;
+
     fn parse_page(&self, page: &TrackedPage) -> Result<T, String>;
+
+    ///Parse the page along with the HTTP status the server answered with.
+    ///Parsers that can't see the response of the server don't return a status
+    fn fetch_page(&self, page: &TrackedPage) -> Result<(T, Option<u16>), String> {
+        self.parse_page(page).map(|dom| (dom, None))
+    }
+
+    ///Fetch the page along with a PNG screenshot of it, taken from the same render so both show the same version.
+    ///The screenshot is None when it could not be captured, parsers that don't render the page never capture one
+    fn fetch_page_with_screenshot(&self, page: &TrackedPage) -> Result<FetchedPage<T>, String> {
+        self.fetch_page(page).map(|(dom, status)| (dom, status, None))
+    }
 }
\ No newline at end of file
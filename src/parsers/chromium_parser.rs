@@ -1,10 +1,18 @@
 use std::process::Command;
-use crate::databases::TrackedPage;
-use crate::parsers::Parser;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use log::{debug, warn};
+use crate::databases::{ParserType, TrackedPage};
+use crate::parsers::{FetchedPage, Parser};
 
 const CHROME_HEADLESS: &str = "chromium";
 const HEADLESS: &str = "--headless";
 const DUMP_TO_DOM: &str = "--dump-dom";
+const SCREENSHOT: &str = "--screenshot";
+const HIDE_SCROLLBARS: &str = "--hide-scrollbars";
+const WINDOW_SIZE: &str = "--window-size=1280,1024";
+
+//Used to give every screenshot file a unique name, as multiple pages can be captured at the same time
+static SCREENSHOT_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 /**
 Runs chromium headless to render and then obtain the websites we want
@@ -33,7 +41,35 @@ pub fn read_website_to_dom(website: &str) -> Result<String, String> {
     };
 }
 
-pub fn read_website_to_pdf(_website: &str) {}
+/**
+Runs chromium headless to render the website and capture a PNG screenshot of it.
+Chromium can only write the screenshot to a file, so we write it to a temporary file and read it back
+ */
+pub fn read_website_to_screenshot(website: &str) -> Result<Vec<u8>, String> {
+    let screenshot_path = std::env::temp_dir().join(format!("defacement_screenshot_{}_{}.png",
+                                                            std::process::id(),
+                                                            SCREENSHOT_COUNTER.fetch_add(1, Ordering::Relaxed)));
+
+    let result = Command::new(CHROME_HEADLESS)
+        .arg(HEADLESS)
+        .arg(HIDE_SCROLLBARS)
+        .arg(WINDOW_SIZE)
+        .arg(format!("{}={}", SCREENSHOT, screenshot_path.display()))
+        .arg(website).output();
+
+    if let Err(error) = result {
+        return Err(error.to_string());
+    }
+
+    let screenshot = std::fs::read(&screenshot_path);
+
+    let _ = std::fs::remove_file(&screenshot_path);
+
+    match screenshot {
+        Ok(screenshot) => { Ok(screenshot) }
+        Err(error) => { Err(error.to_string()) }
+    }
+}
 
 pub struct ChromiumParser {}
 
@@ -44,9 +80,39 @@ impl ChromiumParser {
 }
 
 impl Parser<String> for ChromiumParser {
+    fn parser_type(&self) -> ParserType {
+        ParserType::Browser
+    }
+
     fn parse_page(&self, page: &TrackedPage) -> Result<String, String> {
         read_website_to_dom(page.page_url())
     }
+
+    ///The chromium CLI can't dump the dom and take a screenshot in the same run, so the page is rendered twice.
+    ///Use the DevTools parser to get both from a single navigation.
+    ///It can't find where the ignored regions are in the screenshot either, so the pages with ignored regions
+    ///Are not captured and can't be compared visually
+    fn fetch_page_with_screenshot(&self, page: &TrackedPage) -> Result<FetchedPage<String>, String> {
+        let dom = read_website_to_dom(page.page_url())?;
+
+        if !page.ignored_selectors().is_empty() {
+            debug!("Not capturing the screenshot of page {} with ID {}, it has ignored regions", page.page_url(),
+                page.page_id());
+
+            return Ok((dom, None, None));
+        }
+
+        let screenshot = match read_website_to_screenshot(page.page_url()) {
+            Ok(screenshot) => { Some(screenshot) }
+            Err(e) => {
+                warn!("Failed to capture the screenshot of page {}. {}", page.page_url(), e);
+
+                None
+            }
+        };
+
+        Ok((dom, None, screenshot))
+    }
 }
 
 
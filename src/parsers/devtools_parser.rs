@@ -0,0 +1,286 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use headless_chrome::{Browser, LaunchOptions, Tab};
+use headless_chrome::protocol::cdp::Page::CaptureScreenshotFormatOption;
+use log::{debug, warn};
+use crate::comparators::visual_comparator::{blank_regions, ScreenshotRegion};
+use crate::databases::{ParserType, TrackedPage};
+use crate::parsers::{FetchedPage, Parser};
+
+//How long we wait for a page to finish navigating before giving up
+const NAVIGATION_TIMEOUT: Duration = Duration::from_secs(30);
+//The browsers are kept alive between checks, so we don't want the connection to them
+//To be dropped while we are waiting for the next round of checks
+const BROWSER_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60 * 24);
+
+/**
+Keeps a pool of long lived headless chromium instances alive and drives them through the
+Chrome DevTools Protocol.
+Unlike the ChromiumParser, which spawns a brand new process for every single fetch, the browsers
+are only started once and their tabs are reused between fetches, so the cost of
+launching chromium is not paid on every check.
+ */
+pub struct DevToolsParser {
+    chromium_path: Option<PathBuf>,
+    //Each slot holds a browser process, browsers are only launched when they are first needed
+    //And are relaunched if they die
+    browsers: Mutex<Vec<Option<Browser>>>,
+    next_browser: AtomicUsize,
+    //Tabs that have finished their work and can be reused by the next fetch
+    idle_tabs: Mutex<Vec<PooledTab>>,
+}
+
+struct PooledTab {
+    browser_slot: usize,
+    tab: Arc<Tab>,
+}
+
+impl DevToolsParser {
+    ///pool_size is the amount of browser processes that will be kept alive.
+    ///When chromium_path is None, the browser is searched for in the usual install locations
+    pub fn new(pool_size: usize, chromium_path: Option<PathBuf>) -> Self {
+        let pool_size = std::cmp::max(pool_size, 1);
+
+        let mut browsers = Vec::with_capacity(pool_size);
+
+        browsers.resize_with(pool_size, || None);
+
+        Self {
+            chromium_path,
+            browsers: Mutex::new(browsers),
+            next_browser: AtomicUsize::new(0),
+            idle_tabs: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn pool_size(&self) -> usize {
+        self.browsers.lock().unwrap().len()
+    }
+
+    fn launch_browser(&self) -> Result<Browser, String> {
+        let launch_options = LaunchOptions::default_builder()
+            .headless(true)
+            .path(self.chromium_path.clone())
+            .idle_browser_timeout(BROWSER_IDLE_TIMEOUT)
+            .build();
+
+        let launch_options = match launch_options {
+            Ok(options) => { options }
+            Err(e) => { return Err(e.to_string()); }
+        };
+
+        match Browser::new(launch_options) {
+            Ok(browser) => {
+                debug!("Launched new headless browser with PID {:?}", browser.get_process_id());
+
+                Ok(browser)
+            }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    ///Get a tab to perform a fetch with, reusing an idle tab if there is one
+    ///Otherwise open a new tab in the next browser of the pool (launching it if necessary)
+    fn acquire_tab(&self) -> Result<PooledTab, String> {
+        if let Some(tab) = self.idle_tabs.lock().unwrap().pop() {
+            return Ok(tab);
+        }
+
+        let mut browsers = self.browsers.lock().unwrap();
+
+        let browser_slot = self.next_browser.fetch_add(1, Ordering::Relaxed) % browsers.len();
+
+        if browsers[browser_slot].is_none() {
+            browsers[browser_slot] = Some(self.launch_browser()?);
+        }
+
+        let browser = browsers[browser_slot].as_ref().unwrap();
+
+        match browser.new_tab() {
+            Ok(tab) => {
+                tab.set_default_timeout(NAVIGATION_TIMEOUT);
+
+                Ok(PooledTab { browser_slot, tab })
+            }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+
+    ///Return a tab to the pool after a fetch.
+    ///If the fetch failed, we check if the browser that owns the tab is still alive
+    ///And drop it if it's not, so it gets relaunched on the next fetch
+    fn release_tab(&self, tab: PooledTab, fetch_succeeded: bool) {
+        if fetch_succeeded {
+            self.idle_tabs.lock().unwrap().push(tab);
+
+            return;
+        }
+
+        let mut browsers = self.browsers.lock().unwrap();
+
+        let browser_alive = match &browsers[tab.browser_slot] {
+            Some(browser) => { browser.get_version().is_ok() }
+            None => { false }
+        };
+
+        if browser_alive {
+            //The browser is fine, the page was the problem, so we can keep using this tab
+            self.idle_tabs.lock().unwrap().push(tab);
+        } else {
+            warn!("Headless browser in slot {} is no longer responding, it will be relaunched.", tab.browser_slot);
+
+            browsers[tab.browser_slot] = None;
+
+            //Tabs that belong to a dead browser are of no use to us
+            self.idle_tabs.lock().unwrap().retain(|idle| idle.browser_slot != tab.browser_slot);
+        }
+    }
+
+    pub fn read_website_to_dom(&self, website: &str) -> Result<String, String> {
+        let tab = self.acquire_tab()?;
+
+        let result = read_dom_with_tab(&tab.tab, website);
+
+        self.release_tab(tab, result.is_ok());
+
+        result
+    }
+
+    ///Read the dom and capture the screenshot of the website in a single navigation.
+    ///The elements that match the ignored selectors are blanked in the screenshot.
+    ///A failed screenshot does not fail the fetch, the dom is still returned
+    pub fn read_website_to_dom_and_screenshot(&self, website: &str, ignored_selectors: &[String])
+                                              -> Result<(String, Option<Vec<u8>>), String> {
+        let tab = self.acquire_tab()?;
+
+        let result = read_dom_with_tab(&tab.tab, website).map(|dom| {
+            let screenshot = capture_screenshot_of_tab(&tab.tab).and_then(|screenshot| {
+                let regions = regions_of_elements(&tab.tab, ignored_selectors);
+
+                if regions.is_empty() {
+                    return Ok(screenshot);
+                }
+
+                blank_regions(&screenshot, &regions)
+            });
+
+            let screenshot = match screenshot {
+                Ok(screenshot) => { Some(screenshot) }
+                Err(e) => {
+                    warn!("Failed to capture the screenshot of {}. {}", website, e);
+
+                    None
+                }
+            };
+
+            (dom, screenshot)
+        });
+
+        self.release_tab(tab, result.is_ok());
+
+        result
+    }
+}
+
+fn navigate_with_tab(tab: &Tab, website: &str) -> Result<(), String> {
+    let navigation = tab.navigate_to(website)
+        .and_then(|tab| tab.wait_until_navigated());
+
+    match navigation {
+        Ok(_) => { Ok(()) }
+        Err(e) => { Err(e.to_string()) }
+    }
+}
+
+fn read_dom_with_tab(tab: &Tab, website: &str) -> Result<String, String> {
+    navigate_with_tab(tab, website)?;
+
+    match tab.get_content() {
+        Ok(dom) => { Ok(dom) }
+        Err(e) => { Err(e.to_string()) }
+    }
+}
+
+///Capture the page the tab is currently showing
+fn capture_screenshot_of_tab(tab: &Tab) -> Result<Vec<u8>, String> {
+    match tab.capture_screenshot(CaptureScreenshotFormatOption::Png, None, None, true) {
+        Ok(screenshot) => { Ok(screenshot) }
+        Err(e) => { Err(e.to_string()) }
+    }
+}
+
+///The regions of the viewport taken by the elements that match the selectors in the page the tab is showing.
+///The screenshots are captured at the scale of the page, so the regions are the same in them
+fn regions_of_elements(tab: &Tab, selectors: &[String]) -> Vec<ScreenshotRegion> {
+    selectors.iter()
+        //No element matching the selector is not an error, the region is not in the page right now
+        .flat_map(|selector| tab.find_elements(selector.as_str()).unwrap_or_default())
+        .filter_map(|element| element.get_box_model().ok())
+        .map(|model| {
+            let border = &model.border;
+
+            ScreenshotRegion::new(border.most_left().max(0.0) as u32, border.most_top().max(0.0) as u32,
+                                  border.width().ceil() as u32, border.height().ceil() as u32)
+        })
+        .collect()
+}
+
+impl Parser<String> for DevToolsParser {
+    fn parser_type(&self) -> ParserType {
+        ParserType::Browser
+    }
+
+    fn parse_page(&self, page: &TrackedPage) -> Result<String, String> {
+        self.read_website_to_dom(page.page_url())
+    }
+
+    fn fetch_page_with_screenshot(&self, page: &TrackedPage) -> Result<FetchedPage<String>, String> {
+        self.read_website_to_dom_and_screenshot(page.page_url(), page.ignored_selectors())
+            .map(|(dom, screenshot)| (dom, None, screenshot))
+    }
+}
+
+#[cfg(test)]
+mod devtools_parser_tests {
+    use std::thread;
+    use tiny_http::{Header, Response, Server};
+    use crate::parsers::devtools_parser::DevToolsParser;
+
+    const TEST_PAGE: &str = "<html><head><title>Test</title></head><body><p id=\"marker\">Static test page</p></body></html>";
+
+    fn serve_static_page(requests: usize) -> String {
+        let server = Server::http("127.0.0.1:0").unwrap();
+
+        let address = format!("http://{}/", server.server_addr());
+
+        thread::spawn(move || {
+            for request in server.incoming_requests().take(requests) {
+                let response = Response::from_string(TEST_PAGE)
+                    .with_header("Content-Type: text/html".parse::<Header>().unwrap());
+
+                let _ = request.respond(response);
+            }
+        });
+
+        address
+    }
+
+    #[test]
+    #[ignore = "requires a local chromium install"]
+    fn test_devtools_parser_reuses_tabs() {
+        let address = serve_static_page(2);
+
+        let parser = DevToolsParser::new(1, None);
+
+        let first = parser.read_website_to_dom(address.as_str()).unwrap();
+        let second = parser.read_website_to_dom(address.as_str()).unwrap();
+
+        assert!(first.contains("Static test page"));
+        assert_eq!(first, second);
+
+        //Both fetches were sequential, so they should have used the same tab
+        assert_eq!(parser.idle_tabs.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,149 @@
+use std::time::Duration;
+use log::warn;
+use ureq::{Agent, AgentBuilder};
+use crate::databases::{ParserType, TrackedPage};
+use crate::parsers::Parser;
+
+/**
+Fetches the pages directly over HTTP/HTTPS, without rendering them.
+This is a lot cheaper than rendering the page in a browser, but it will only see
+the HTML the server sends back, so it should only be used for pages that do not rely on JS
+ */
+pub struct HttpParser {
+    agent: Agent,
+    headers: Vec<(String, String)>,
+}
+
+impl HttpParser {
+    ///max_redirects is the amount of redirects that will be followed before giving up
+    ///headers are sent with every request
+    pub fn new(user_agent: &str, timeout: Duration, max_redirects: u32, headers: Vec<(String, String)>) -> Self {
+        let agent = AgentBuilder::new()
+            .user_agent(user_agent)
+            .timeout(timeout)
+            .redirects(max_redirects)
+            .build();
+
+        Self { agent, headers }
+    }
+
+    pub fn read_website_to_dom(&self, website: &str) -> Result<String, String> {
+        self.fetch_website(website).map(|(dom, _)| dom)
+    }
+
+    ///Read the website along with the status the server answered with
+    pub fn fetch_website(&self, website: &str) -> Result<(String, u16), String> {
+        let mut request = self.agent.get(website);
+
+        for (header, value) in &self.headers {
+            request = request.set(header, value);
+        }
+
+        let response = match request.call() {
+            Ok(response) => { response }
+            Err(ureq::Error::Status(status, response)) => {
+                //The page that is served with an error status is still the page visitors see,
+                //So we still want to compare it
+                warn!("Page {} answered with status {}", website, status);
+
+                response
+            }
+            Err(e) => {
+                return Err(e.to_string());
+            }
+        };
+
+        let status = response.status();
+
+        match response.into_string() {
+            Ok(dom) => { Ok((dom, status)) }
+            Err(e) => { Err(e.to_string()) }
+        }
+    }
+}
+
+impl Parser<String> for HttpParser {
+    fn parser_type(&self) -> ParserType {
+        ParserType::Http
+    }
+
+    fn parse_page(&self, page: &TrackedPage) -> Result<String, String> {
+        self.read_website_to_dom(page.page_url())
+    }
+
+    fn fetch_page(&self, page: &TrackedPage) -> Result<(String, Option<u16>), String> {
+        self.fetch_website(page.page_url()).map(|(dom, status)| (dom, Some(status)))
+    }
+}
+
+#[cfg(test)]
+mod http_parser_tests {
+    use std::thread;
+    use std::time::Duration;
+    use tiny_http::{Header, Response, Server};
+    use crate::parsers::http_parser::HttpParser;
+
+    const TEST_PAGE: &str = "<html><body><p>Static test page</p></body></html>";
+
+    #[test]
+    fn test_http_parser_follows_redirects_with_headers() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+
+        let address = format!("http://{}", server.server_addr());
+
+        let server_thread = thread::spawn(move || {
+            let mut received_headers = Vec::new();
+
+            for request in server.incoming_requests().take(2) {
+                for header in request.headers() {
+                    received_headers.push(format!("{}: {}", header.field, header.value));
+                }
+
+                let response = if request.url() == "/old" {
+                    Response::from_string("")
+                        .with_status_code(301)
+                        .with_header("Location: /new".parse::<Header>().unwrap())
+                } else {
+                    Response::from_string(TEST_PAGE)
+                        .with_header("Content-Type: text/html".parse::<Header>().unwrap())
+                };
+
+                request.respond(response).unwrap();
+            }
+
+            received_headers
+        });
+
+        let parser = HttpParser::new("defacement-monitor-test", Duration::from_secs(5), 2,
+                                     vec![(String::from("X-Test"), String::from("yes"))]);
+
+        let dom = parser.read_website_to_dom(format!("{}/old", address).as_str()).unwrap();
+
+        assert_eq!(dom, TEST_PAGE);
+
+        let received_headers = server_thread.join().unwrap();
+
+        assert!(received_headers.contains(&String::from("User-Agent: defacement-monitor-test")));
+        assert!(received_headers.contains(&String::from("X-Test: yes")));
+    }
+
+    #[test]
+    fn test_http_parser_returns_error_status() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+
+        let address = format!("http://{}/missing", server.server_addr());
+
+        thread::spawn(move || {
+            let request = server.incoming_requests().next().unwrap();
+
+            request.respond(Response::from_string(TEST_PAGE).with_status_code(404)).unwrap();
+        });
+
+        let parser = HttpParser::new("defacement-monitor-test", Duration::from_secs(5), 2, Vec::new());
+
+        let (dom, status) = parser.fetch_website(address.as_str()).unwrap();
+
+        assert_eq!(dom, TEST_PAGE);
+        assert_eq!(status, 404);
+    }
+}